@@ -0,0 +1,90 @@
+//! Bounces a rect around the scene with nothing but a frame hook, as an
+//! example of driving the demo from outside its event loop. Takes the same
+//! options as the demo itself:
+//!
+//! ```text
+//! cargo run --example bounce -- --scene-name grid
+//! ```
+
+extern crate env_logger;
+
+use std::{env, process};
+
+use webrender::api::{
+    units::{LayoutPoint, LayoutRect, LayoutSize, LayoutVector2D},
+    ColorF,
+};
+use webrender_demo::{scene::SceneRect, Config, FrameHook};
+
+/// Side of the bouncing rect, in layout pixels.
+const SIZE: f32 = 80.0;
+
+/// Speed along each axis, in layout pixels per second.
+const VELOCITY: (f32, f32) = (240.0, 150.0);
+
+/// Longest step taken at once, so that a stalled frame doesn't throw the
+/// rect through a wall.
+const MAX_STEP: f32 = 0.05;
+
+const COLOR: ColorF = ColorF {
+    r: 1.0,
+    g: 0.6,
+    b: 0.0,
+    a: 1.0,
+};
+
+fn main() {
+    env_logger::init();
+
+    let config = Config::from_args(env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(2);
+    });
+    webrender_demo::run(config, Some(hook()));
+}
+
+/// Adds a rect to the scene and moves it on every frame, reflecting it off
+/// the scene's edges, with nothing but what `App` gives any hook.
+fn hook() -> FrameHook {
+    let mut index = None;
+    let mut velocity = LayoutVector2D::new(VELOCITY.0, VELOCITY.1);
+    Box::new(move |app, elapsed| {
+        let scene_size = app.coords().scene_size();
+        let rects = &mut app.scene_mut().rects;
+        // Added on the first frame, and again whenever the scene has been
+        // replaced by one without it.
+        let i = match index.filter(|&i| i < rects.len()) {
+            Some(i) => i,
+            None => {
+                rects.push(SceneRect {
+                    bounds: LayoutRect::from_size(LayoutSize::new(SIZE, SIZE)),
+                    color: COLOR,
+                    opacity: 1.0,
+                    border: None,
+                    corner_radii: [SIZE / 4.0; 4],
+                    rotation: 0.0,
+                    layer: None,
+                });
+                rects.len() - 1
+            }
+        };
+        index = Some(i);
+
+        let rect = &mut rects[i];
+        let size = rect.bounds.size();
+        let max = LayoutPoint::new(
+            (scene_size.width - size.width).max(0.0),
+            (scene_size.height - size.height).max(0.0),
+        );
+        let mut origin = rect.bounds.min + velocity * elapsed.as_secs_f32().min(MAX_STEP);
+        if origin.x < 0.0 || origin.x > max.x {
+            velocity.x = -velocity.x;
+        }
+        if origin.y < 0.0 || origin.y > max.y {
+            velocity.y = -velocity.y;
+        }
+        origin.x = origin.x.clamp(0.0, max.x);
+        origin.y = origin.y.clamp(0.0, max.y);
+        rect.bounds = LayoutRect::from_origin_and_size(origin, size);
+    })
+}
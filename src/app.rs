@@ -0,0 +1,1960 @@
+use std::{
+    f32::consts::TAU,
+    mem,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use log::{debug, error, info, warn};
+use webrender::{
+    api::{
+        units::{
+            DeviceIntPoint, DeviceIntRect, DeviceIntSize, LayoutPoint, LayoutRect, LayoutSize,
+            LayoutTransform, LayoutVector2D, WorldPoint,
+        },
+        AlphaType, ColorF, CommonItemProperties, DisplayListBuilder, DocumentId, DynamicProperties,
+        Epoch, FilterOp, ImageData, ImageDescriptor, ImageDescriptorFlags, ImageFormat, ImageKey,
+        ImageRendering, ItemTag, PipelineId, PrimitiveFlags, PropertyBinding, PropertyBindingKey,
+        ReferenceFrameKind, RenderReasons, Shadow, SpaceAndClipInfo, SpatialTreeItemKey,
+        TransformStyle,
+    },
+    PipelineInfo, RenderApi, Transaction,
+};
+
+use crate::{
+    clip_debug::ClipLog,
+    color::{day_night, rotate_hue, AlphaMode, ColorFilter},
+    config::Config,
+    coords::{ClipRect, CoordinateConverter},
+    cursor_readout::CursorReadout,
+    easing::{ease_in_out, Easing},
+    editor::{centered_square, Editor, Target},
+    hit_map::{unit_transform, HitMap, GRADIENT_KIND, IMAGE_TILE_KIND, PATH_KIND, SPINNER_KIND},
+    hud::{hud_position, stacked_hud_position, Corner, Section, StatusPanel},
+    image::{BackdropFit, TiledImage},
+    incremental::{IncrementalUpdates, UpdatePath},
+    nested_scroll::{NestedScroll, MAX_SCROLL_FRAMES},
+    pacing::{PipelineTracker, STALLED_FRAME_THRESHOLD},
+    palette::Palette,
+    particles::ParticleSystem,
+    path::fill_spans,
+    primitives::{
+        push_conic_spin, push_gradient_sweep, push_grid, push_path, push_ramp, push_spinner,
+        push_vignette,
+    },
+    ruler::Ruler,
+    scene::{merge_rects, BuiltinScene, SceneModel, SceneRect},
+    scene_file::{self, SceneFile},
+    svg_import,
+    text_input::{self, TextEdit, TextInput},
+    touch::{Phase, TouchTracker},
+    view::View,
+};
+
+/// Interval between animation frames while something on screen is moving.
+pub const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Time for the spinner to complete one revolution.
+const SPINNER_PERIOD: Duration = Duration::from_secs(1);
+
+/// Time for the `--animate-gradient` sweep to move one repeat, and for the
+/// conic gradient to turn once.
+const GRADIENT_PERIOD: Duration = Duration::from_secs(3);
+
+/// Time the wipe takes to reveal the whole scene.
+const WIPE_DURATION: Duration = Duration::from_millis(800);
+
+/// Time `App::scroll_to` takes to bring a rect to the center.
+const SCROLL_DURATION: Duration = Duration::from_millis(300);
+
+/// Size of each of the two `--animate-gradient` panels.
+const GRADIENT_PANEL_SIZE: f32 = 160.0;
+
+/// First of the `--nested-scroll` frames' property binding keys. The
+/// highlight has 1, the hit-test outline 2 to 5 and the readout 6.
+const NESTED_SCROLL_FIRST_KEY: u64 = 7;
+
+/// Keys of the `--incremental-updates` label, after the scroll frames', and
+/// the first of its rects', after everything else's.
+const UPDATE_LABEL_FIRST_KEY: u64 = NESTED_SCROLL_FIRST_KEY + MAX_SCROLL_FRAMES as u64;
+const SCENE_RECTS_FIRST_KEY: u64 = UPDATE_LABEL_FIRST_KEY + 2;
+
+/// Pipeline of the `--retain-display-list` scene.
+const RETAINED_PIPELINE_ID: PipelineId = PipelineId(0, 1);
+
+/// Pipeline of the HUD's status panel.
+const STATUS_PIPELINE_ID: PipelineId = PipelineId(0, 2);
+
+/// How far the retained scene's iframe reaches from the origin each way, in
+/// world layout pixels, so any rect the view can reach is inside it.
+const RETAINED_EXTENT: f32 = 1.0e6;
+
+/// Fill color of the `--svg-path`.
+const PATH_COLOR: ColorF = ColorF {
+    r: 1.0,
+    g: 1.0,
+    b: 1.0,
+    a: 1.0,
+};
+
+/// Overlay drawn over the rect under the pointer.
+const HIGHLIGHT_COLOR: ColorF = ColorF {
+    r: 1.0,
+    g: 1.0,
+    b: 1.0,
+    a: 0.35,
+};
+
+const TEXT_INPUT_WIDTH: f32 = 280.0;
+
+/// Color of the debug grid lines.
+const GRID_COLOR: ColorF = ColorF {
+    r: 0.0,
+    g: 0.0,
+    b: 0.0,
+    a: 0.6,
+};
+
+/// Offset of the `--group-shadow`, down and to the right, and its blur
+/// radius, in scene pixels.
+const GROUP_SHADOW_OFFSET: f32 = 8.0;
+const GROUP_SHADOW_BLUR: f32 = 12.0;
+
+const GROUP_SHADOW_COLOR: ColorF = ColorF {
+    r: 0.0,
+    g: 0.0,
+    b: 0.0,
+    a: 0.5,
+};
+
+/// State of the optional `--image` or `--bg-image`.
+pub enum ImageState {
+    None,
+    Loading,
+    Ready {
+        size: DeviceIntSize,
+        tiles: Vec<(ImageKey, DeviceIntRect)>,
+    },
+}
+
+/// An eased pan from one view offset to another.
+struct ScrollAnimation {
+    from: LayoutVector2D,
+    to: LayoutVector2D,
+    started: Instant,
+}
+
+/// Custom logic run on every animation frame, given the time since the
+/// last one. See `App::set_on_frame`.
+pub type FrameHook = Box<dyn FnMut(&mut App, Duration)>;
+
+/// Owns the document and everything needed to rebuild its display list.
+pub struct App {
+    api: RenderApi,
+    builder: DisplayListBuilder,
+    document_id: DocumentId,
+    pipeline_id: PipelineId,
+    epoch: Epoch,
+    /// Time spent in `build_display_list` since `take_build_time`.
+    build_time: Duration,
+    /// Id of the last frame asked for, counting up from 1.
+    frame_id: u64,
+    /// The time given to the last `animate`, which animations started by
+    /// input count from, so that they follow `--fixed-timestep` too.
+    now: Instant,
+    /// Advances animations on every step of the clock rather than at most
+    /// once a `FRAME_INTERVAL`, for `--fixed-timestep`.
+    fixed_timestep: bool,
+    /// Run on every animation frame, before the display list is built.
+    on_frame: Option<FrameHook>,
+    /// Turns fallbacks into failures, for `--strict`.
+    strict: bool,
+    /// The first fallback taken under `--strict`.
+    strict_failure: Option<String>,
+    /// Largest document width and height GL can draw.
+    max_document_size: i32,
+    /// The scene's own pipeline and display list, for
+    /// `--retain-display-list`, and the model it was last built from.
+    retained: Option<RetainedScene>,
+    /// Sends reloaded scenes that only moved or recolored rects as binding
+    /// updates, for `--incremental-updates`.
+    incremental: Option<IncrementalUpdates>,
+    coords: CoordinateConverter,
+    background: ColorF,
+    /// Leaves the root background out of the display list, for `--no-clear`.
+    no_clear: bool,
+    /// How input colors are written; everything stored here is straight.
+    alpha_mode: AlphaMode,
+    image: ImageState,
+    fit_image: bool,
+    /// The `--bg-image`, drawn under everything else.
+    backdrop: ImageState,
+    backdrop_fit: BackdropFit,
+    /// Draws the `--ramp` gradient under the scene.
+    ramp: bool,
+    /// `Auto` samples the image linearly, `Pixelated` takes the nearest
+    /// pixel.
+    image_rendering: ImageRendering,
+    scene: BuiltinScene,
+    palette: Option<Palette>,
+    /// Scene loaded with `--scene` or `--svg`, drawn instead of `scene`,
+    /// laid out again at each size since it can be given in percentages.
+    scene_file: Option<SceneFile>,
+    optimize: bool,
+    model: SceneModel,
+    /// Scanline spans covering the `--svg-path`, computed once at startup.
+    path_spans: Vec<LayoutRect>,
+    /// Set once the model has been edited, after which resizes keep it.
+    edited: bool,
+    editor: Editor,
+    ruler: Ruler,
+    text_input: Option<TextInput>,
+    /// Corner the HUD overlays are kept in.
+    hud_corner: Corner,
+    clip_rect: Option<ClipRect>,
+    /// Fills the bars around the `--design-size` area.
+    letterbox_color: ColorF,
+    /// Draws the debug grid.
+    grid: bool,
+    /// Draws the scene's rects as outlines instead of fills.
+    wireframe: bool,
+    /// Snaps the grid and ruler lines to device pixels.
+    dpi_aware_lines: bool,
+    /// Drops one shadow under all the scene's rects.
+    group_shadow: bool,
+    /// The `--invert`, `--sepia` or `--grayscale` matrix over everything.
+    color_filter: Option<ColorFilter>,
+    /// Alpha of the vignette in the corners, drawn over everything.
+    vignette: Option<f32>,
+    touches: TouchTracker,
+    /// Index of the rect under the pointer.
+    hover: Option<usize>,
+    hover_rebuild: bool,
+    highlight_key: PropertyBindingKey<LayoutTransform>,
+    hits: HitMap,
+    /// Outlines the front item under the pointer and logs its tag.
+    hit_test_debug: bool,
+    /// The clips in the display list, outlined while it is enabled.
+    clips: ClipLog,
+    /// The `--coords` label following the cursor.
+    readout: Option<CursorReadout>,
+    spinner: bool,
+    spinner_angle: f32,
+    particles: Option<ParticleSystem>,
+    /// The `--nested-scroll` frames, scrolled by the wheel.
+    nested_scroll: Option<NestedScroll>,
+    /// Length of the `--daynight` cycle.
+    daynight: Option<Duration>,
+    /// How far through the day the background is, from 0 to 1.
+    daynight_phase: f32,
+    animate_gradient: bool,
+    /// How far through `GRADIENT_PERIOD` the gradients are, from 0 to 1.
+    gradient_phase: f32,
+    scroll: Option<ScrollAnimation>,
+    scroll_easing: Easing,
+    /// When the running wipe started.
+    wipe_started: Option<Instant>,
+    /// Fraction of the window's width the wipe has revealed, 1 when no wipe
+    /// is running.
+    wipe_progress: f32,
+    /// Starts a wipe whenever the scene changes.
+    wipe_transitions: bool,
+    last_tick: Option<Instant>,
+    pipeline_tracker: PipelineTracker,
+    status: StatusPanel,
+    /// Adds the debug lines to the status panel; P toggles it along with
+    /// webrender's profiler.
+    debug_hud: bool,
+    /// The renderer's memory use as last reported, for the debug lines.
+    memory_lines: Vec<String>,
+}
+
+impl App {
+    /// `max_document_size` caps the document's width and height, in device
+    /// pixels, whatever size the window is.
+    pub fn new(
+        mut api: RenderApi,
+        device_size: DeviceIntSize,
+        device_pixel_ratio: f32,
+        max_document_size: i32,
+        config: &Config,
+    ) -> App {
+        let pipeline_id = PipelineId(0, 0);
+        let (device_size, capped) = clamp_document_size(device_size, max_document_size);
+        let document_id = api.add_document(device_size);
+
+        let loaded = match (&config.scene, &config.svg) {
+            (Some(path), _) => Some(
+                scene_file::load(path)
+                    .map(|file| file.map_colors(|color| config.alpha_mode.decode(color))),
+            ),
+            // SVG colors are always straight alpha.
+            (None, Some(path)) => Some(
+                svg_import::load(path, config.strict).map(|model| SceneFile::from_model(&model)),
+            ),
+            (None, None) => None,
+        };
+        let (scene_file, load_error) = match loaded {
+            Some(Ok(model)) => (Some(model), None),
+            Some(Err(report)) => {
+                error!("{}", report);
+                error!("falling back to the default scene");
+                (None, Some(report))
+            }
+            None => (None, None),
+        };
+
+        let mut app = App {
+            api,
+            builder: DisplayListBuilder::new(pipeline_id),
+            document_id,
+            pipeline_id,
+            epoch: Epoch(0),
+            build_time: Duration::ZERO,
+            frame_id: 0,
+            now: Instant::now(),
+            fixed_timestep: config.fixed_timestep.is_some(),
+            on_frame: None,
+            strict: config.strict,
+            strict_failure: None,
+            max_document_size,
+            retained: config.retain_display_list.then(|| RetainedScene {
+                builder: DisplayListBuilder::new(RETAINED_PIPELINE_ID),
+                epoch: Epoch(0),
+                model: None,
+                wireframe: false,
+            }),
+            incremental: config
+                .incremental_updates
+                .then(|| IncrementalUpdates::new(UPDATE_LABEL_FIRST_KEY, SCENE_RECTS_FIRST_KEY)),
+            coords: CoordinateConverter {
+                design_size: config.design_size,
+                ..CoordinateConverter::new(device_size, device_pixel_ratio)
+            },
+            background: config.alpha_mode.decode(config.background),
+            no_clear: config.no_clear,
+            alpha_mode: config.alpha_mode,
+            image: if config.image.is_some() {
+                ImageState::Loading
+            } else {
+                ImageState::None
+            },
+            fit_image: config.fit_image,
+            backdrop: if config.bg_image.is_some() {
+                ImageState::Loading
+            } else {
+                ImageState::None
+            },
+            backdrop_fit: config.bg_fit,
+            ramp: config.ramp,
+            image_rendering: ImageRendering::Auto,
+            scene: match (config.scene_name, config.rects) {
+                (Some(scene), _) => scene,
+                _ if config.alpha_test => BuiltinScene::AlphaOverlap,
+                (None, Some(count)) => BuiltinScene::Grid(count),
+                (None, None) => BuiltinScene::ThreeRects,
+            },
+            palette: config.palette,
+            scene_file,
+            optimize: config.optimize,
+            model: SceneModel::default(),
+            path_spans: config
+                .svg_path
+                .as_ref()
+                .map(|path| fill_spans(&path.flatten(config.path_tolerance)))
+                .unwrap_or_default(),
+            edited: false,
+            editor: Editor::default(),
+            ruler: Ruler::default(),
+            clip_rect: config.clip_rect,
+            letterbox_color: config.alpha_mode.decode(config.letterbox_color),
+            text_input: if config.text_input {
+                Some(TextInput::new(LayoutPoint::zero(), TEXT_INPUT_WIDTH))
+            } else {
+                None
+            },
+            hud_corner: config.hud_corner,
+            grid: false,
+            wireframe: false,
+            dpi_aware_lines: config.dpi_aware_lines,
+            group_shadow: config.group_shadow,
+            color_filter: config.color_filter,
+            vignette: config.vignette,
+            touches: TouchTracker::default(),
+            hover: None,
+            hover_rebuild: config.hover_rebuild,
+            highlight_key: PropertyBindingKey::new(1),
+            hits: HitMap::new(2),
+            hit_test_debug: config.hit_test_debug,
+            clips: ClipLog::new(config.clip_debug),
+            readout: config
+                .coords
+                .then(|| CursorReadout::new(PropertyBindingKey::new(6))),
+            spinner: config.spinner,
+            spinner_angle: 0.0,
+            particles: None,
+            nested_scroll: None,
+            daynight: config.daynight,
+            daynight_phase: 0.0,
+            animate_gradient: config.animate_gradient,
+            gradient_phase: 0.0,
+            scroll: None,
+            scroll_easing: config.scroll_easing,
+            wipe_started: None,
+            wipe_progress: 1.0,
+            wipe_transitions: config.wipe_transitions,
+            last_tick: None,
+            pipeline_tracker: PipelineTracker::new(STALLED_FRAME_THRESHOLD),
+            status: StatusPanel::new(STATUS_PIPELINE_ID, config.hud_corner),
+            debug_hud: false,
+            memory_lines: Vec::new(),
+        };
+
+        if let Some(report) = load_error {
+            app.note_fallback(report);
+        }
+        if let Some(report) = capped {
+            app.note_fallback(report);
+        }
+        app.generate_model();
+        // Spread over the scene as laid out at startup; later resizes only
+        // move the edges they bounce off.
+        app.particles = config.particles.map(|count| {
+            info!("simulating {} particles", count);
+            ParticleSystem::new(
+                count,
+                config.particle_seed,
+                app.coords.scene_size(),
+                app.palette.unwrap_or(Palette::Golden),
+            )
+        });
+        app.nested_scroll = config.nested_scroll.map(|count| {
+            NestedScroll::new(
+                count,
+                app.coords.scene_size(),
+                NESTED_SCROLL_FIRST_KEY,
+                app.palette.unwrap_or(Palette::Golden),
+            )
+        });
+
+        let mut txn = Transaction::new();
+        app.build_display_list(&mut txn);
+        txn.set_root_pipeline(pipeline_id);
+        txn.generate_frame(app.next_frame_id(), RenderReasons::empty());
+        app.api.send_transaction(document_id, txn);
+
+        app
+    }
+
+    pub fn device_size(&self) -> DeviceIntSize {
+        self.coords.device_size
+    }
+
+    pub fn coords(&self) -> CoordinateConverter {
+        self.coords
+    }
+
+    /// Installs `hook` to run on every animation frame from now on, in
+    /// place of any other, for custom per-frame logic without an event loop
+    /// of its own.
+    ///
+    /// The hook gets the app and the time since the last frame, zero on the
+    /// first. It runs just before the frame's display list is built, so
+    /// changes it makes through `scene_mut` show in that same frame. With a
+    /// hook installed the app keeps animating, at `FRAME_INTERVAL` or on
+    /// every `--fixed-timestep` step.
+    pub fn set_on_frame(&mut self, hook: FrameHook) {
+        self.on_frame = Some(hook);
+    }
+
+    /// The live scene, to change in place. Colors are the straight alpha
+    /// webrender takes. Taking it counts as an edit, so a resize no longer
+    /// lays the built-in scene out again over the changes. Nothing is
+    /// rebuilt until the next frame or input.
+    pub fn scene_mut(&mut self) -> &mut SceneModel {
+        self.edited = true;
+        &mut self.model
+    }
+
+    /// Sends any pending changes; called once per event.
+    pub fn send_transaction(&mut self, txn: Transaction) {
+        self.api.send_transaction(self.document_id, txn);
+    }
+
+    /// Takes the pipeline info of the frame just rendered. The status
+    /// panel's own pipeline is left out, so that it's never waited on and
+    /// showing it doesn't change what it shows.
+    pub fn update_pipeline_info(&mut self, mut info: PipelineInfo) {
+        info.epochs
+            .retain(|&(pipeline_id, _), _| pipeline_id != STATUS_PIPELINE_ID);
+        info.removed_pipelines
+            .retain(|&(pipeline_id, _)| pipeline_id != STATUS_PIPELINE_ID);
+        self.pipeline_tracker.update(info);
+        self.send_status();
+    }
+
+    /// Shows or hides the debug lines of the HUD's status panel.
+    pub fn toggle_debug_hud(&mut self) {
+        self.debug_hud = !self.debug_hud;
+        self.send_status();
+    }
+
+    pub fn debug_hud(&self) -> bool {
+        self.debug_hud
+    }
+
+    /// Replaces the memory figures the debug lines show.
+    pub fn set_memory_lines(&mut self, lines: Vec<String>) {
+        self.memory_lines = lines;
+        self.send_status();
+    }
+
+    /// Each layer's key, name and whether it is shown.
+    fn layer_states(&self) -> Vec<String> {
+        self.model
+            .layers
+            .iter()
+            .enumerate()
+            .map(|(i, layer)| {
+                let state = if layer.visible { "on" } else { "off" };
+                format!("{} {} {}", i + 1, layer.name, state)
+            })
+            .collect()
+    }
+
+    /// Brings the status panel's lines up to date with the app.
+    fn update_status_lines(&mut self) {
+        // Outside debug mode layers are only listed once one is hidden, so
+        // the panel stays out of the way while the whole scene is shown.
+        let layers = if self.debug_hud || self.model.layers.iter().any(|layer| !layer.visible) {
+            self.layer_states()
+                .into_iter()
+                .map(|state| format!("layer {}", state))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        self.status.set(Section::Layers, layers);
+
+        let hover = if self.hit_test_debug {
+            self.hits.hover_lines()
+        } else {
+            Vec::new()
+        };
+        self.status.set(Section::Hover, hover);
+
+        let (pacing, memory) = if self.debug_hud {
+            (
+                self.pipeline_tracker.status_lines(),
+                self.memory_lines.clone(),
+            )
+        } else {
+            (Vec::new(), Vec::new())
+        };
+        self.status.set(Section::Pacing, pacing);
+        self.status.set(Section::Memory, memory);
+    }
+
+    /// Sends the status panel on its own if what it shows changed since the
+    /// last display list.
+    fn send_status(&mut self) {
+        self.update_status_lines();
+        let mut txn = Transaction::new();
+        if self.status.send(&mut txn) {
+            txn.generate_frame(self.next_frame_id(), RenderReasons::empty());
+            self.api.send_transaction(self.document_id, txn);
+        }
+    }
+
+    /// Whether the last rendered frame shows the latest display list.
+    pub fn is_up_to_date(&self) -> bool {
+        self.pipeline_tracker.is_idle()
+    }
+
+    /// Rects in the scene and image tiles uploaded, to set memory use
+    /// against.
+    pub fn content_counts(&self) -> (usize, usize) {
+        let tiles = |image: &ImageState| match image {
+            ImageState::Ready { tiles, .. } => tiles.len(),
+            _ => 0,
+        };
+        (
+            self.model.rects.len(),
+            tiles(&self.image) + tiles(&self.backdrop),
+        )
+    }
+
+    /// Size of the `--image` once it has loaded, in device pixels.
+    pub fn image_size(&self) -> Option<DeviceIntSize> {
+        match self.image {
+            ImageState::Ready { size, .. } => Some(size),
+            _ => None,
+        }
+    }
+
+    /// The epoch of the last display list submitted.
+    pub fn epoch(&self) -> u32 {
+        self.epoch.0.saturating_sub(1)
+    }
+
+    /// A new id for `generate_frame`, one more than the last.
+    fn next_frame_id(&mut self) -> u64 {
+        self.frame_id += 1;
+        self.frame_id
+    }
+
+    /// CPU time spent building display lists since the last call.
+    pub fn take_build_time(&mut self) -> Duration {
+        mem::take(&mut self.build_time)
+    }
+
+    /// Display lists that were replaced before any frame showed them.
+    pub fn skipped_display_lists(&self) -> u64 {
+        self.pipeline_tracker.skipped()
+    }
+
+    /// Follows a window resize with a new document view and display list.
+    pub fn resize(&mut self, device_size: DeviceIntSize) {
+        let (device_size, capped) = clamp_document_size(device_size, self.max_document_size);
+        if let Some(report) = capped {
+            self.note_fallback(report);
+        }
+        self.coords.device_size = device_size;
+        if !self.edited {
+            self.generate_model();
+        }
+
+        let mut txn = Transaction::new();
+        txn.set_document_view(DeviceIntRect::from_size(device_size));
+        self.build_display_list(&mut txn);
+        txn.generate_frame(self.next_frame_id(), RenderReasons::empty());
+        self.api.send_transaction(self.document_id, txn);
+    }
+
+    /// Deletes the document and submits the scene again in a new one, to
+    /// exercise the document lifecycle. Images stay uploaded, since
+    /// resources belong to the API rather than to a document.
+    pub fn recreate_document(&mut self) {
+        let old = self.document_id;
+        self.api.delete_document(old);
+        self.pipeline_tracker.forget_document(old);
+        if let Some(retained) = &mut self.retained {
+            retained.model = None;
+        }
+        self.document_id = self.api.add_document(self.coords.device_size);
+        info!("recreated document {:?} as {:?}", old, self.document_id);
+
+        let mut txn = Transaction::new();
+        self.build_display_list(&mut txn);
+        txn.set_root_pipeline(self.pipeline_id);
+        txn.generate_frame(self.next_frame_id(), RenderReasons::empty());
+        self.api.send_transaction(self.document_id, txn);
+    }
+
+    pub fn set_device_pixel_ratio(&mut self, device_pixel_ratio: f32) {
+        self.coords.scale_factor = device_pixel_ratio;
+        // The layout size changed with it.
+        if !self.edited {
+            self.generate_model();
+        }
+        self.rebuild();
+    }
+
+    /// Hit tests at `point` in layout pixels, which are world space to
+    /// webrender since the root has no transform.
+    fn hit_test_tags(&self, point: LayoutPoint) -> Vec<ItemTag> {
+        self.api
+            .hit_test(self.document_id, WorldPoint::new(point.x, point.y))
+            .items
+            .iter()
+            .filter(|item| {
+                item.pipeline == self.pipeline_id || item.pipeline == RETAINED_PIPELINE_ID
+            })
+            .map(|item| item.tag)
+            .collect()
+    }
+
+    fn hit_test(&self, point: LayoutPoint) -> Option<Target> {
+        self.hit_test_tags(point)
+            .into_iter()
+            .find_map(Target::from_tag)
+    }
+
+    pub fn pointer_down(&mut self, point: DeviceIntPoint) {
+        let layout = self.coords.device_to_layout(point);
+
+        // Clicks on the text field focus it, and clicks anywhere else blur it.
+        if self.text_input.is_some() {
+            let on_input = self.hit_test_tags(layout).first() == Some(&text_input::TAG);
+            let input = self.text_input.as_mut().unwrap();
+            if input.set_focused(on_input) {
+                self.rebuild();
+            }
+            if on_input {
+                return;
+            }
+        }
+
+        let target = self.hit_test(layout);
+        if self
+            .editor
+            .pointer_down(&self.model, target, self.coords.layout_to_world(layout))
+        {
+            self.rebuild();
+        }
+    }
+
+    pub fn pointer_moved(&mut self, point: DeviceIntPoint) {
+        let layout = self.coords.device_to_layout(point);
+        let mut relabel = false;
+        if let Some(readout) = &mut self.readout {
+            readout.set_pointer(layout);
+            relabel = readout.needs_rebuild(&self.coords);
+        }
+        if self
+            .editor
+            .pointer_moved(&mut self.model, self.coords.layout_to_world(layout))
+        {
+            self.edited = true;
+            self.rebuild();
+            return;
+        }
+
+        let tags = self.hit_test_tags(layout);
+        let mut changed = self.hit_test_debug && self.hits.hover(&tags);
+        if changed {
+            self.send_status();
+        }
+        let hover = match tags.into_iter().find_map(Target::from_tag) {
+            Some(Target::Rect(index)) | Some(Target::Handle(index, _)) => Some(index),
+            None => None,
+        };
+        if hover != self.hover {
+            self.hover = hover;
+            changed = true;
+        }
+        if relabel {
+            self.rebuild();
+        } else if changed || self.readout.is_some() {
+            self.update_highlight();
+        }
+    }
+
+    /// Moves the hover highlight over the hovered rect, or hides it, the
+    /// `--hit-test-debug` outline over the hovered item and the `--coords`
+    /// label to the cursor.
+    ///
+    /// The highlight is a unit rect in its own reference frame whose
+    /// transform is a property binding, and so are each edge of the outline
+    /// and the label, so this only updates those bindings instead of
+    /// resubmitting the display list, unless `--hover-rebuild` asks for the
+    /// naive path to compare against.
+    fn update_highlight(&mut self) {
+        if self.hover_rebuild {
+            self.rebuild();
+            return;
+        }
+        self.send_bindings();
+    }
+
+    /// Sends the current value of every binding.
+    fn send_bindings(&mut self) {
+        let mut txn = Transaction::new();
+        txn.update_dynamic_properties(self.dynamic_properties());
+        txn.generate_frame(self.next_frame_id(), RenderReasons::empty());
+        self.api.send_transaction(self.document_id, txn);
+    }
+
+    /// The current value of every binding. Webrender replaces all the
+    /// dynamic properties at once, so any left out would fall back to their
+    /// values in the display list.
+    fn dynamic_properties(&self) -> DynamicProperties {
+        let mut transforms = vec![self.highlight_key.with(self.highlight_transform())];
+        if self.hit_test_debug {
+            transforms.extend(
+                self.hits
+                    .outline_transforms()
+                    .into_iter()
+                    .map(|(key, transform)| key.with(transform)),
+            );
+        }
+        if let Some(readout) = &self.readout {
+            transforms.push(readout.binding(&self.coords));
+        }
+        if let Some(nested_scroll) = &self.nested_scroll {
+            transforms.extend(nested_scroll.bindings());
+        }
+        let mut colors = Vec::new();
+        if let Some(incremental) = &self.incremental {
+            transforms.extend(incremental.transforms(&self.model));
+            colors = incremental.colors(&self.model);
+        }
+        DynamicProperties {
+            transforms,
+            floats: Vec::new(),
+            colors,
+        }
+    }
+
+    /// Maps the unit highlight rect onto the hovered rect, or scales it away
+    /// when nothing is hovered.
+    fn highlight_transform(&self) -> LayoutTransform {
+        match self.hover.and_then(|index| self.model.rects.get(index)) {
+            Some(rect) => {
+                let transform = unit_transform(rect.bounds);
+                match rect.rotation_transform() {
+                    Some(rotation) => transform.then(&rotation),
+                    None => transform,
+                }
+            }
+            None => LayoutTransform::scale(0.0, 0.0, 1.0),
+        }
+    }
+
+    /// Scrolls the `--nested-scroll` frame under `point` by `delta`, both in
+    /// device pixels, handing what it can't take to the frames around it.
+    pub fn wheel(&mut self, point: DeviceIntPoint, delta: LayoutVector2D) {
+        if self.nested_scroll.is_none() {
+            return;
+        }
+        let tags = self.hit_test_tags(self.coords.device_to_layout(point));
+        // The frames are in the letterboxed scene, so the delta is scaled
+        // into design pixels.
+        let delta = delta / (self.coords.scale_factor * self.coords.letterbox().zoom);
+        let scrolled = match &mut self.nested_scroll {
+            Some(nested_scroll) => nested_scroll.scroll(&tags, delta),
+            None => false,
+        };
+        if scrolled {
+            self.send_bindings();
+        }
+    }
+
+    pub fn pointer_up(&mut self) {
+        self.editor.pointer_up();
+    }
+
+    /// Pans and pinch-zooms the view from touchscreen input.
+    pub fn touch(&mut self, id: u64, phase: Phase, point: DeviceIntPoint) {
+        let point = self
+            .coords
+            .layout_to_design(self.coords.device_to_layout(point));
+        if self.touches.update(id, phase, point, &mut self.coords.view) {
+            self.rebuild();
+        }
+    }
+
+    /// Places the next ruler point, or clears the ruler on the third click.
+    pub fn measure_click(&mut self, point: DeviceIntPoint) {
+        let point = self.coords.device_to_layout(point);
+        self.ruler.click(point);
+        self.rebuild();
+    }
+
+    pub fn has_measurement(&self) -> bool {
+        !self.ruler.is_empty()
+    }
+
+    /// Removes the ruler. Returns whether there was one to remove.
+    pub fn clear_measurement(&mut self) -> bool {
+        if self.ruler.is_empty() {
+            return false;
+        }
+        self.ruler.clear();
+        self.rebuild();
+        true
+    }
+
+    /// Shows or hides layer `index` of the scene, and logs and shows on the
+    /// HUD the visibility of every layer. Does nothing if there is no such
+    /// layer.
+    pub fn toggle_layer(&mut self, index: usize) {
+        if !self.model.toggle_layer(index) {
+            return;
+        }
+        info!("layers: {}", self.layer_states().join(", "));
+
+        if self
+            .hover
+            .and_then(|index| self.model.rects.get(index))
+            .is_some_and(|rect| !self.model.is_visible(rect))
+        {
+            self.hover = None;
+        }
+        self.rebuild();
+    }
+
+    pub fn toggle_grid(&mut self) {
+        self.grid = !self.grid;
+        self.rebuild();
+    }
+
+    pub fn toggle_wireframe(&mut self) {
+        self.wireframe = !self.wireframe;
+        info!("wireframe {}", if self.wireframe { "on" } else { "off" });
+        self.rebuild();
+    }
+
+    pub fn toggle_clip_debug(&mut self) {
+        self.clips.toggle();
+        info!(
+            "clip debug {}",
+            if self.clips.is_enabled() { "on" } else { "off" }
+        );
+        self.rebuild();
+    }
+
+    /// Switches the image between linear and nearest neighbour sampling.
+    pub fn toggle_image_sampling(&mut self) {
+        self.image_rendering = match self.image_rendering {
+            ImageRendering::Pixelated => ImageRendering::Auto,
+            _ => ImageRendering::Pixelated,
+        };
+        info!(
+            "image sampling: {}",
+            match self.image_rendering {
+                ImageRendering::Pixelated => "nearest",
+                _ => "linear",
+            }
+        );
+        self.rebuild();
+    }
+
+    /// Swaps in a reloaded `--scene`. On an error the last good scene stays.
+    pub fn set_scene_file(&mut self, file: Result<SceneFile, String>) {
+        match file {
+            Ok(file) => {
+                info!("scene reloaded");
+                self.scene_file = Some(file.map_colors(|color| self.alpha_mode.decode(color)));
+                self.edited = false;
+                self.generate_model();
+                if self.wipe_transitions {
+                    self.start_wipe();
+                }
+                self.update_scene();
+            }
+            Err(report) => {
+                error!("{}", report);
+                error!("keeping the previous scene");
+                self.note_fallback(report);
+            }
+        }
+    }
+
+    /// Shows a reloaded model, with `--incremental-updates` through the
+    /// rects' bindings alone when only their positions and colors changed.
+    fn update_scene(&mut self) {
+        let path = match &mut self.incremental {
+            // The wipe rebuilds every frame anyway.
+            Some(incremental) => incremental.update(&self.model, self.wipe_progress < 1.0),
+            None => UpdatePath::Rebuild,
+        };
+        match path {
+            UpdatePath::Bindings => self.send_bindings(),
+            UpdatePath::Rebuild => self.rebuild(),
+        }
+    }
+
+    /// Under `--strict`, keeps `report` if it is the first fallback taken,
+    /// for the event loop to exit on. The caller has logged it already.
+    fn note_fallback(&mut self, report: String) {
+        if self.strict && self.strict_failure.is_none() {
+            self.strict_failure = Some(report);
+        }
+    }
+
+    /// The first fallback taken under `--strict`, if any.
+    pub fn strict_failure(&self) -> Option<&str> {
+        self.strict_failure.as_deref()
+    }
+
+    /// The live model, including any edits, in the `--scene` JSON format
+    /// with colors written in the `--alpha-mode`.
+    pub fn export_scene(&self) -> String {
+        let model = self.model.map_colors(|color| self.alpha_mode.encode(color));
+        serde_json::to_string_pretty(&SceneFile::from_model(&model))
+            .expect("scene models always serialize")
+    }
+
+    /// The color the scene should show at the center of the window, worked
+    /// out on the CPU to compare against a readback.
+    pub fn expected_center_color(&self) -> ColorF {
+        let center = centered(self.coords.layout_size(), LayoutSize::zero());
+        self.model
+            .color_at(self.coords.layout_to_world(center), self.background_color())
+    }
+
+    pub fn set_scene(&mut self, scene: BuiltinScene) {
+        self.scene = scene;
+        self.scene_file = None;
+        self.edited = false;
+        self.generate_model();
+        if self.wipe_transitions {
+            self.start_wipe();
+        }
+        self.rebuild();
+    }
+
+    /// Hides the scene and reveals it again from left to right.
+    pub fn wipe(&mut self) {
+        self.start_wipe();
+        self.rebuild();
+    }
+
+    fn start_wipe(&mut self) {
+        self.wipe_started = Some(self.now);
+        self.wipe_progress = 0.0;
+    }
+
+    /// Appends a rect, with its color in the `--alpha-mode`, on top of the
+    /// scene.
+    pub fn add_rect(&mut self, mut rect: SceneRect) {
+        rect.color = self.alpha_mode.decode(rect.color);
+        self.model.rects.push(rect);
+        self.edited = true;
+        self.rebuild();
+    }
+
+    /// Removes every rect, leaving just the background.
+    pub fn clear_scene(&mut self) {
+        self.model.rects.clear();
+        self.editor.clear();
+        self.hover = None;
+        self.hits.clear_hover();
+        self.edited = true;
+        self.rebuild();
+    }
+
+    /// Zooms to `zoom` about the center of the window.
+    pub fn zoom(&mut self, zoom: f32) {
+        let center = centered(self.coords.scene_size(), LayoutSize::zero());
+        self.coords.view.zoom_about(center, zoom);
+        self.rebuild();
+    }
+
+    /// Pans smoothly so that rect `index` ends up centered, as far as the
+    /// scene extends, keeping the zoom.
+    pub fn scroll_to(&mut self, index: usize) {
+        let rect = match self.model.rects.get(index) {
+            Some(rect) if self.model.is_visible(rect) => rect,
+            _ => return,
+        };
+        let scene_size = self.coords.scene_size();
+        let mut target = self
+            .coords
+            .view
+            .centered_on(rect.bounds.center(), scene_size);
+        if let Some(bounds) = self.model.bounds() {
+            target = target.clamp_to(bounds, scene_size);
+        }
+        self.scroll = Some(ScrollAnimation {
+            from: self.coords.view.pan,
+            to: target.pan,
+            started: self.now,
+        });
+    }
+
+    pub fn set_view(&mut self, view: View) {
+        self.scroll = None;
+        self.coords.view = view;
+        self.rebuild();
+    }
+
+    /// Puts the view back to where it started and drops any selection.
+    pub fn reset_view(&mut self) {
+        self.scroll = None;
+        self.coords.view = View::default();
+        self.editor.clear();
+        self.rebuild();
+        println!("view reset");
+    }
+
+    pub fn set_spinner(&mut self, spinner: bool) {
+        self.spinner = spinner;
+        self.rebuild();
+    }
+
+    pub fn set_background(&mut self, background: ColorF) {
+        self.background = background;
+        self.rebuild();
+    }
+
+    /// Pushes the `--bg-image` over `scene_size`, which it never draws out
+    /// of. Tiles of an image too large for one texture are placed like the
+    /// whole image would be.
+    fn push_backdrop(&mut self, scene_size: LayoutSize, space_and_clip: SpaceAndClipInfo) {
+        let (size, tiles) = match &self.backdrop {
+            ImageState::Ready { size, tiles } => (self.coords.device_size_to_layout(*size), tiles),
+            _ => return,
+        };
+        let scene = LayoutRect::from_size(scene_size);
+        for &(key, rect) in tiles {
+            let tile = self.coords.device_rect_to_layout(rect);
+            let (bounds, stretch_size, tile_spacing) = match self.backdrop_fit {
+                BackdropFit::Center => {
+                    // Snapped like `--image`, so tile edges land on device
+                    // pixels.
+                    let origin = self
+                        .coords
+                        .snap_design_to_device(centered(scene_size, size));
+                    let bounds = tile.translate(origin.to_vector());
+                    (bounds, bounds.size(), LayoutSize::zero())
+                }
+                BackdropFit::Stretch => {
+                    let bounds = tile.scale(
+                        scene_size.width / size.width,
+                        scene_size.height / size.height,
+                    );
+                    (bounds, bounds.size(), LayoutSize::zero())
+                }
+                // Each tile repeats on its own at the whole image's pitch,
+                // leaving gaps where the other tiles go.
+                BackdropFit::Tile => (
+                    LayoutRect::new(tile.min, scene.max),
+                    tile.size(),
+                    size - tile.size(),
+                ),
+            };
+            let clip = match bounds.intersection(&scene) {
+                Some(clip) => clip,
+                None => continue,
+            };
+            self.builder.push_repeating_image(
+                &CommonItemProperties::new(clip, space_and_clip),
+                bounds,
+                stretch_size,
+                tile_spacing,
+                self.image_rendering,
+                AlphaType::PremultipliedAlpha,
+                key,
+                ColorF::WHITE,
+            );
+        }
+    }
+
+    /// The background at the moment, which `--daynight` animates.
+    fn background_color(&self) -> ColorF {
+        match self.daynight {
+            Some(_) => day_night(self.daynight_phase),
+            None => self.background,
+        }
+    }
+
+    /// Turns the hue of the selected rect, if any, by `degrees`.
+    pub fn rotate_selected_hue(&mut self, degrees: f32) {
+        let rect = match self.editor.selection() {
+            Some(index) => &mut self.model.rects[index],
+            None => return,
+        };
+        rect.color = rotate_hue(rect.color, degrees);
+        info!("selected color {:?}", rect.color);
+        self.edited = true;
+        self.rebuild();
+    }
+
+    /// Turns the selected rect, if any, by `degrees` about its center.
+    pub fn rotate_selected(&mut self, degrees: f32) {
+        let rect = match self.editor.selection() {
+            Some(index) => &mut self.model.rects[index],
+            None => return,
+        };
+        rect.rotation = (rect.rotation + degrees).rem_euclid(360.0);
+        info!("selected rotation {}°", rect.rotation);
+        self.edited = true;
+        self.rebuild();
+    }
+
+    /// Applies an eyedropper pick to the selected rect, or to the background
+    /// when nothing is selected.
+    pub fn apply_picked_color(&mut self, color: ColorF) {
+        match self.editor.selection() {
+            Some(index) => {
+                self.model.rects[index].color = color;
+                self.edited = true;
+                self.rebuild();
+            }
+            None => self.set_background(color),
+        }
+    }
+
+    /// Uploads a decoded `--image` and swaps it in for the placeholder.
+    pub fn set_image(&mut self, image: Result<TiledImage, String>) {
+        let mut txn = Transaction::new();
+        self.image = self.upload_image(image, &mut txn);
+        self.build_display_list(&mut txn);
+        txn.generate_frame(self.next_frame_id(), RenderReasons::empty());
+        self.api.send_transaction(self.document_id, txn);
+    }
+
+    /// Uploads a decoded `--bg-image` and draws it under the scene.
+    pub fn set_backdrop(&mut self, image: Result<TiledImage, String>) {
+        let mut txn = Transaction::new();
+        self.backdrop = self.upload_image(image, &mut txn);
+        self.build_display_list(&mut txn);
+        txn.generate_frame(self.next_frame_id(), RenderReasons::empty());
+        self.api.send_transaction(self.document_id, txn);
+    }
+
+    /// Adds each of `image`'s tiles to `txn` under a new key.
+    fn upload_image(
+        &mut self,
+        image: Result<TiledImage, String>,
+        txn: &mut Transaction,
+    ) -> ImageState {
+        match image {
+            Ok(image) => {
+                let tiles = image
+                    .tiles
+                    .into_iter()
+                    .map(|tile| {
+                        let key = self.api.generate_image_key();
+                        txn.add_image(
+                            key,
+                            ImageDescriptor::new(
+                                tile.rect.width(),
+                                tile.rect.height(),
+                                ImageFormat::BGRA8,
+                                ImageDescriptorFlags::empty(),
+                            ),
+                            ImageData::new(tile.data),
+                            None,
+                        );
+                        (key, tile.rect)
+                    })
+                    .collect();
+                ImageState::Ready {
+                    size: image.size,
+                    tiles,
+                }
+            }
+            Err(err) => {
+                error!("failed to load image {}", err);
+                self.note_fallback(format!("failed to load image {}", err));
+                ImageState::None
+            }
+        }
+    }
+
+    pub fn text_input_focused(&self) -> bool {
+        self.text_input.as_ref().is_some_and(TextInput::is_focused)
+    }
+
+    pub fn edit_text(&mut self, edit: TextEdit) {
+        if let Some(input) = self.text_input.as_mut() {
+            if input.edit(edit) {
+                self.rebuild();
+            }
+        }
+    }
+
+    pub fn blur_text_input(&mut self) {
+        if let Some(input) = self.text_input.as_mut() {
+            if input.set_focused(false) {
+                self.rebuild();
+            }
+        }
+    }
+
+    /// The text typed into the `--text-input` field.
+    pub fn text_input_value(&self) -> Option<&str> {
+        self.text_input.as_ref().map(TextInput::value)
+    }
+
+    /// Loads the path typed into the text field as a scene.
+    pub fn submit_text_input(&mut self) {
+        let path = match self.text_input_value() {
+            Some(value) if !value.trim().is_empty() => PathBuf::from(value.trim()),
+            _ => return,
+        };
+        info!("loading {}", path.display());
+        self.set_scene_file(scene_file::load(&path));
+    }
+
+    fn is_animating(&self) -> bool {
+        self.spinner
+            || self.animate_gradient
+            || self.daynight.is_some()
+            || self.particles.is_some()
+            || self.scroll.is_some()
+            || self.wipe_started.is_some()
+            || self.on_frame.is_some()
+            || matches!(self.image, ImageState::Loading)
+    }
+
+    /// Advances running animations and the caret blink to `now` and returns
+    /// when they next need to run, or `None` once nothing is animating so the
+    /// event loop can go back to waiting for input.
+    pub fn animate(&mut self, now: Instant) -> Option<Instant> {
+        self.now = now;
+        let (blinked, next_blink) = match self.text_input.as_mut() {
+            Some(input) => input.update_blink(now),
+            None => (false, None),
+        };
+        if blinked {
+            self.rebuild();
+        }
+
+        match (self.animate_frames(now), next_blink) {
+            (Some(frame), Some(blink)) => Some(frame.min(blink)),
+            (frame, blink) => frame.or(blink),
+        }
+    }
+
+    fn animate_frames(&mut self, now: Instant) -> Option<Instant> {
+        if !self.is_animating() {
+            self.last_tick = None;
+            return None;
+        }
+
+        let last_tick = match self.last_tick {
+            Some(last_tick) if self.fixed_timestep && now <= last_tick => return Some(last_tick),
+            Some(last_tick) if !self.fixed_timestep && now < last_tick + FRAME_INTERVAL => {
+                return Some(last_tick + FRAME_INTERVAL)
+            }
+            Some(last_tick) => last_tick,
+            None => now,
+        };
+
+        let elapsed = (now - last_tick).as_secs_f32();
+        self.spinner_angle =
+            (self.spinner_angle + elapsed / SPINNER_PERIOD.as_secs_f32() * TAU) % TAU;
+        // Advanced by the actual elapsed time rather than per frame, so the
+        // sweep stays smooth when frames arrive unevenly.
+        self.gradient_phase = (self.gradient_phase + elapsed / GRADIENT_PERIOD.as_secs_f32()) % 1.0;
+        if let Some(particles) = &mut self.particles {
+            particles.step(elapsed, self.coords.scene_size());
+        }
+        if let Some(period) = self.daynight {
+            self.daynight_phase = (self.daynight_phase + elapsed / period.as_secs_f32()) % 1.0;
+        }
+        if let Some(scroll) = &self.scroll {
+            let progress = now.saturating_duration_since(scroll.started).as_secs_f32()
+                / SCROLL_DURATION.as_secs_f32();
+            self.coords.view.pan = scroll
+                .from
+                .lerp(scroll.to, self.scroll_easing.apply(progress));
+            if progress >= 1.0 {
+                self.scroll = None;
+            }
+        }
+        if let Some(started) = self.wipe_started {
+            let progress =
+                now.saturating_duration_since(started).as_secs_f32() / WIPE_DURATION.as_secs_f32();
+            self.wipe_progress = progress.min(1.0);
+            if progress >= 1.0 {
+                self.wipe_started = None;
+            }
+        }
+        if let Some(mut hook) = self.on_frame.take() {
+            hook(self, now - last_tick);
+            // Unless the hook installed another.
+            if self.on_frame.is_none() {
+                self.on_frame = Some(hook);
+            }
+        }
+        self.last_tick = Some(now);
+        self.rebuild();
+
+        Some(now + FRAME_INTERVAL)
+    }
+
+    /// Resubmits the display list with the current state.
+    pub fn rebuild(&mut self) {
+        let mut txn = Transaction::new();
+        self.build_display_list(&mut txn);
+        txn.generate_frame(self.next_frame_id(), RenderReasons::empty());
+        self.api.send_transaction(self.document_id, txn);
+    }
+
+    /// Lays out the built-in or loaded scene for the current size.
+    fn generate_model(&mut self) {
+        // Laid out in layout pixels, the window's device pixels divided by
+        // the scale factor, so that the scene ends exactly at the window's
+        // right and bottom edges at any scale. Device pixels would overshoot
+        // them at scales above 1 and fall short below.
+        let size = self.coords.scene_size();
+        let mut model = match &self.scene_file {
+            Some(file) => file.to_model(size),
+            None => self.scene.model(size, self.palette),
+        };
+        model.keep_visibility(&self.model);
+        self.model = model;
+        self.editor.clear();
+        self.hover = None;
+        self.hits.clear_hover();
+
+        if self.optimize {
+            let before = self.model.rects.len();
+            self.model.rects = merge_rects(&self.model.rects);
+            info!(
+                "optimize: merged {} rects into {}",
+                before,
+                self.model.rects.len()
+            );
+        }
+    }
+
+    fn build_display_list(&mut self, txn: &mut Transaction) {
+        let started = Instant::now();
+        let layout_size = self.coords.layout_size();
+        let scene_size = self.coords.scene_size();
+        self.builder.begin();
+        self.hits.clear();
+        self.clips.clear();
+
+        let root_space_and_clip = SpaceAndClipInfo::root_scroll(self.pipeline_id);
+        // A color filter wraps everything in one stacking context, so that it
+        // applies to what is drawn after compositing, whatever the items.
+        if let Some(filter) = self.color_filter {
+            self.builder.push_simple_stacking_context_with_filters(
+                LayoutPoint::zero(),
+                root_space_and_clip.spatial_id,
+                PrimitiveFlags::IS_BACKFACE_VISIBLE,
+                &[FilterOp::ColorMatrix(filter.matrix())],
+                &[],
+                &[],
+            );
+        }
+        // With `--design-size` the scene is drawn at that size in its own
+        // reference frame, which scales it to fit the window, over its own
+        // background between bars of the letterbox color.
+        let letterboxed = self.coords.design_size.is_some();
+        let scene_root = if letterboxed {
+            let scene_root = self.builder.push_reference_frame(
+                LayoutPoint::zero(),
+                root_space_and_clip.spatial_id,
+                TransformStyle::Flat,
+                PropertyBinding::Value(self.coords.letterbox().transform()),
+                ReferenceFrameKind::Transform {
+                    is_2d_scale_translation: true,
+                    should_snap: false,
+                    paired_with_perspective: false,
+                },
+                SpatialTreeItemKey::new(0, 2),
+            );
+            if !self.no_clear {
+                let design = LayoutRect::from_size(scene_size);
+                self.builder.push_rect(
+                    &CommonItemProperties::new(
+                        design,
+                        SpaceAndClipInfo {
+                            spatial_id: scene_root,
+                            clip_id: root_space_and_clip.clip_id,
+                        },
+                    ),
+                    design,
+                    self.background_color(),
+                );
+            }
+            scene_root
+        } else {
+            root_space_and_clip.spatial_id
+        };
+        let backdrop_space_and_clip = SpaceAndClipInfo {
+            spatial_id: scene_root,
+            clip_id: root_space_and_clip.clip_id,
+        };
+        self.push_backdrop(scene_size, backdrop_space_and_clip);
+        if self.ramp {
+            push_ramp(
+                &mut self.builder,
+                LayoutRect::from_size(scene_size),
+                backdrop_space_and_clip,
+            );
+        }
+
+        // `--clip-rect` and a running wipe clip the scene, but not the
+        // overlays drawn over it, and the letterbox clips it to the design
+        // area.
+        let mut scene_clip = self
+            .clip_rect
+            .map(|clip_rect| clip_rect.resolve(scene_size));
+        if letterboxed {
+            let design = LayoutRect::from_size(scene_size);
+            scene_clip = Some(scene_clip.map_or(design, |clip| {
+                clip.intersection(&design).unwrap_or_else(LayoutRect::zero)
+            }));
+        }
+        if self.wipe_progress < 1.0 {
+            // The revealed edge is snapped to a device pixel so it stays
+            // crisp while it moves.
+            let edge = ease_in_out(self.wipe_progress) * scene_size.width;
+            let edge = self
+                .coords
+                .snap_design_to_device(LayoutPoint::new(edge, 0.0))
+                .x;
+            let revealed = LayoutRect::from_size(LayoutSize::new(edge, scene_size.height));
+            scene_clip = Some(match scene_clip {
+                Some(clip) => clip
+                    .intersection(&revealed)
+                    .unwrap_or_else(LayoutRect::zero),
+                None => revealed,
+            });
+        }
+        let scene_clip_id = match scene_clip {
+            Some(clip) => self
+                .clips
+                .define_clip_rect(&mut self.builder, scene_root, clip),
+            None => root_space_and_clip.clip_id,
+        };
+        let view_frame = self.builder.push_reference_frame(
+            LayoutPoint::zero(),
+            scene_root,
+            TransformStyle::Flat,
+            PropertyBinding::Value(self.coords.view.transform()),
+            ReferenceFrameKind::Transform {
+                is_2d_scale_translation: true,
+                should_snap: false,
+                paired_with_perspective: false,
+            },
+            SpatialTreeItemKey::new(0, 0),
+        );
+        let space_and_clip = SpaceAndClipInfo {
+            spatial_id: view_frame,
+            clip_id: scene_clip_id,
+        };
+
+        // The shadow filter applies to the whole stacking context, so the
+        // rects cast one shadow together instead of each shadowing the ones
+        // below.
+        if self.group_shadow {
+            self.builder.push_simple_stacking_context_with_filters(
+                LayoutPoint::zero(),
+                view_frame,
+                PrimitiveFlags::IS_BACKFACE_VISIBLE,
+                &[FilterOp::DropShadow(Shadow {
+                    offset: LayoutVector2D::new(GROUP_SHADOW_OFFSET, GROUP_SHADOW_OFFSET),
+                    color: GROUP_SHADOW_COLOR,
+                    blur_radius: GROUP_SHADOW_BLUR,
+                })],
+                &[],
+                &[],
+            );
+        }
+        match &mut self.retained {
+            Some(retained) => {
+                if retained.model.as_ref() != Some(&self.model)
+                    || retained.wireframe != self.wireframe
+                {
+                    retained.build(
+                        &self.model,
+                        self.wireframe,
+                        txn,
+                        &mut self.pipeline_tracker,
+                        self.document_id,
+                    );
+                }
+                // Centered on the origin, so rects at negative coordinates
+                // aren't clipped; `RetainedScene::build` moves the content
+                // back from the iframe's top left corner.
+                let extent = LayoutRect::new(
+                    LayoutPoint::new(-RETAINED_EXTENT, -RETAINED_EXTENT),
+                    LayoutPoint::new(RETAINED_EXTENT, RETAINED_EXTENT),
+                );
+                self.builder.push_iframe(
+                    extent,
+                    extent,
+                    &space_and_clip,
+                    RETAINED_PIPELINE_ID,
+                    true,
+                );
+            }
+            None => {
+                // Outlines aren't bound, so wireframes always rebuild.
+                let rect_keys = self
+                    .incremental
+                    .as_ref()
+                    .filter(|_| !self.wireframe)
+                    .map(IncrementalUpdates::first_key);
+                self.model.push(
+                    &mut self.builder,
+                    self.wireframe,
+                    &mut self.clips,
+                    rect_keys,
+                    space_and_clip,
+                );
+                if let Some(incremental) = &mut self.incremental {
+                    incremental.set_built(Some(&self.model).filter(|_| !self.wireframe));
+                }
+            }
+        }
+        if self.group_shadow {
+            self.builder.pop_stacking_context();
+        }
+        self.hits.set_space(self.coords.world_to_layout());
+        push_path(
+            &mut self.builder,
+            &self.path_spans,
+            PATH_COLOR,
+            space_and_clip,
+        );
+        for &span in &self.path_spans {
+            self.hits.push(
+                &mut self.builder,
+                (0, PATH_KIND),
+                "path",
+                span,
+                space_and_clip,
+            );
+        }
+
+        let highlight_transform = self.highlight_transform();
+        let highlight_frame = self.builder.push_reference_frame(
+            LayoutPoint::zero(),
+            view_frame,
+            TransformStyle::Flat,
+            if self.hover_rebuild {
+                PropertyBinding::Value(highlight_transform)
+            } else {
+                PropertyBinding::Binding(self.highlight_key, highlight_transform)
+            },
+            // The bound transform turns with the hovered rect, so it isn't
+            // always a scale and translation.
+            ReferenceFrameKind::Transform {
+                is_2d_scale_translation: false,
+                should_snap: false,
+                paired_with_perspective: false,
+            },
+            SpatialTreeItemKey::new(0, 1),
+        );
+        let unit = LayoutRect::from_size(LayoutSize::new(1.0, 1.0));
+        self.builder.push_rect(
+            &CommonItemProperties::new(
+                unit,
+                SpaceAndClipInfo {
+                    spatial_id: highlight_frame,
+                    clip_id: scene_clip_id,
+                },
+            ),
+            unit,
+            HIGHLIGHT_COLOR,
+        );
+        self.builder.pop_reference_frame();
+
+        self.editor.push(
+            &mut self.builder,
+            &self.model,
+            &mut self.hits,
+            space_and_clip,
+        );
+        self.builder.pop_reference_frame();
+
+        self.hits.set_space(self.coords.letterbox());
+        let space_and_clip = SpaceAndClipInfo {
+            spatial_id: scene_root,
+            clip_id: scene_clip_id,
+        };
+
+        match self.image {
+            ImageState::None => (),
+            ImageState::Loading => {
+                let center = centered(scene_size, LayoutSize::zero());
+                push_spinner(
+                    &mut self.builder,
+                    center,
+                    24.0,
+                    ColorF::new(0.5, 0.5, 0.5, 1.0),
+                    self.spinner_angle,
+                    space_and_clip,
+                );
+                self.hits.push(
+                    &mut self.builder,
+                    (0, SPINNER_KIND),
+                    "spinner",
+                    centered_square(center, 48.0),
+                    space_and_clip,
+                );
+            }
+            ImageState::Ready { size, ref tiles } => {
+                let size = self.coords.device_size_to_layout(size);
+                // `--fit-image` shrinks images larger than the window to fit.
+                let scale = if self.fit_image {
+                    (scene_size.width / size.width)
+                        .min(scene_size.height / size.height)
+                        .min(1.0)
+                } else {
+                    1.0
+                };
+                // Snap the origin to a device pixel so tile edges, which are
+                // on whole image pixels, line up with the device grid.
+                let origin = self
+                    .coords
+                    .snap_design_to_device(centered(scene_size, size * scale));
+
+                for (index, &(key, rect)) in tiles.iter().enumerate() {
+                    let bounds = self
+                        .coords
+                        .device_rect_to_layout(rect)
+                        .scale(scale, scale)
+                        .translate(origin.to_vector());
+                    self.builder.push_image(
+                        &CommonItemProperties::new(bounds, space_and_clip),
+                        bounds,
+                        self.image_rendering,
+                        AlphaType::PremultipliedAlpha,
+                        key,
+                        ColorF::WHITE,
+                    );
+                    self.hits.push(
+                        &mut self.builder,
+                        (index as u64, IMAGE_TILE_KIND),
+                        "image tile",
+                        bounds,
+                        space_and_clip,
+                    );
+                }
+            }
+        }
+
+        if self.animate_gradient {
+            let size = LayoutSize::new(GRADIENT_PANEL_SIZE * 2.0, GRADIENT_PANEL_SIZE);
+            let origin = centered(scene_size, size);
+            let panel = LayoutSize::new(GRADIENT_PANEL_SIZE, GRADIENT_PANEL_SIZE);
+            let sweep = LayoutRect::from_origin_and_size(origin, panel);
+            let conic = sweep.translate(LayoutVector2D::new(GRADIENT_PANEL_SIZE, 0.0));
+            push_gradient_sweep(
+                &mut self.builder,
+                sweep,
+                self.gradient_phase,
+                space_and_clip,
+            );
+            push_conic_spin(
+                &mut self.builder,
+                conic,
+                self.gradient_phase * TAU,
+                space_and_clip,
+            );
+            for (index, bounds) in [sweep, conic].into_iter().enumerate() {
+                self.hits.push(
+                    &mut self.builder,
+                    (index as u64, GRADIENT_KIND),
+                    "gradient",
+                    bounds,
+                    space_and_clip,
+                );
+            }
+        }
+
+        if self.spinner {
+            let center = centered(scene_size, LayoutSize::zero());
+            push_spinner(
+                &mut self.builder,
+                center,
+                48.0,
+                ColorF::WHITE,
+                self.spinner_angle,
+                space_and_clip,
+            );
+            self.hits.push(
+                &mut self.builder,
+                (1, SPINNER_KIND),
+                "spinner",
+                centered_square(center, 96.0),
+                space_and_clip,
+            );
+        }
+
+        if let Some(nested_scroll) = &self.nested_scroll {
+            nested_scroll.push(
+                &mut self.builder,
+                &mut self.hits,
+                &mut self.clips,
+                space_and_clip,
+            );
+        }
+
+        if let Some(particles) = &self.particles {
+            particles.push(&mut self.builder, space_and_clip);
+        }
+
+        if letterboxed {
+            self.builder.pop_reference_frame();
+        }
+
+        self.hits.set_space(View::default());
+        let space_and_clip = root_space_and_clip;
+
+        if self.grid {
+            push_grid(
+                &mut self.builder,
+                &self.coords,
+                self.dpi_aware_lines,
+                GRID_COLOR,
+                space_and_clip,
+            );
+        }
+
+        self.ruler.push(
+            &mut self.builder,
+            &self.coords,
+            self.dpi_aware_lines,
+            space_and_clip,
+        );
+
+        if let Some(input) = &mut self.text_input {
+            input.move_to(hud_position(self.hud_corner, input.size(), layout_size));
+            input.push(
+                &mut self.builder,
+                &mut self.hits,
+                &mut self.clips,
+                space_and_clip,
+            );
+        }
+        // Every other overlay in the HUD corner stacks after those before it.
+        let mut stacked: Vec<LayoutSize> = self.text_input.iter().map(TextInput::size).collect();
+        if let Some(incremental) = &mut self.incremental {
+            let size = incremental.label_size();
+            let origin = stacked_hud_position(self.hud_corner, size, &stacked, layout_size);
+            incremental.push_label(&mut self.builder, origin, space_and_clip);
+            stacked.push(size);
+        }
+        self.update_status_lines();
+        self.status
+            .push_iframe(&mut self.builder, stacked, layout_size, space_and_clip);
+
+        if let Some(intensity) = self.vignette {
+            push_vignette(
+                &mut self.builder,
+                LayoutRect::from_size(layout_size),
+                intensity,
+                space_and_clip,
+            );
+        }
+
+        // Drawn over everything, vignette included. Neither the outline nor
+        // the full window overlays above have hit-test areas, so they don't
+        // hide the items under them.
+        if self.hit_test_debug {
+            self.hits
+                .push_outline(&mut self.builder, !self.hover_rebuild, space_and_clip);
+        }
+        self.clips
+            .push_outlines(&mut self.builder, space_and_clip.clip_id);
+        if let Some(readout) = &mut self.readout {
+            readout.push(
+                &mut self.builder,
+                &self.coords,
+                !self.hover_rebuild,
+                space_and_clip,
+            );
+        }
+
+        if self.color_filter.is_some() {
+            self.builder.pop_stacking_context();
+        }
+
+        // The background is cleared to rather than drawn, so it is filtered
+        // here instead.
+        let background = if letterboxed {
+            self.letterbox_color
+        } else {
+            self.background_color()
+        };
+        let background = match self.color_filter {
+            Some(filter) => filter.apply(background),
+            None => background,
+        };
+        txn.set_display_list(
+            self.epoch,
+            Some(background).filter(|_| !self.no_clear),
+            layout_size,
+            self.builder.end(),
+        );
+        self.status.send(txn);
+        // The bound rects are placed and colored by their values in the new
+        // display list only once the ones sent for an earlier update are
+        // replaced.
+        if self.incremental.is_some() {
+            txn.update_dynamic_properties(self.dynamic_properties());
+        }
+        self.pipeline_tracker
+            .submit(self.pipeline_id, self.document_id, self.epoch);
+        self.epoch = Epoch(self.epoch.0 + 1);
+        self.build_time += started.elapsed();
+    }
+}
+
+/// The scene's rects in a pipeline of their own, embedded in the main
+/// display list as an iframe.
+///
+/// Webrender only rebuilds the scene for a pipeline whose display list was
+/// sent again, so while the rects stay the same, a rebuild for the
+/// particles or the hover highlight leaves them alone. They are compared
+/// with the model they were built from rather than marked dirty, since
+/// so many paths change the model.
+struct RetainedScene {
+    builder: DisplayListBuilder,
+    epoch: Epoch,
+    model: Option<SceneModel>,
+    wireframe: bool,
+}
+
+impl RetainedScene {
+    fn build(
+        &mut self,
+        model: &SceneModel,
+        wireframe: bool,
+        txn: &mut Transaction,
+        pipeline_tracker: &mut PipelineTracker,
+        document_id: DocumentId,
+    ) {
+        self.builder.begin();
+        let root = SpaceAndClipInfo::root_scroll(RETAINED_PIPELINE_ID);
+        // The pipeline's origin is the iframe's top left corner, which is
+        // `RETAINED_EXTENT` above and left of the world origin.
+        let world_origin = self.builder.push_reference_frame(
+            LayoutPoint::new(RETAINED_EXTENT, RETAINED_EXTENT),
+            root.spatial_id,
+            TransformStyle::Flat,
+            PropertyBinding::Value(LayoutTransform::identity()),
+            ReferenceFrameKind::Transform {
+                is_2d_scale_translation: true,
+                should_snap: false,
+                paired_with_perspective: false,
+            },
+            SpatialTreeItemKey::new(4, 0),
+        );
+        model.push(
+            &mut self.builder,
+            wireframe,
+            &mut ClipLog::default(),
+            None,
+            SpaceAndClipInfo {
+                spatial_id: world_origin,
+                clip_id: root.clip_id,
+            },
+        );
+        self.builder.pop_reference_frame();
+        txn.set_display_list(
+            self.epoch,
+            None,
+            LayoutSize::new(2.0 * RETAINED_EXTENT, 2.0 * RETAINED_EXTENT),
+            self.builder.end(),
+        );
+        pipeline_tracker.submit(RETAINED_PIPELINE_ID, document_id, self.epoch);
+        self.epoch = Epoch(self.epoch.0 + 1);
+        self.model = Some(model.clone());
+        self.wireframe = wireframe;
+        debug!("rebuilt the retained scene, epoch {}", self.epoch.0);
+    }
+}
+
+/// Caps both sides of a document at `max`, warning when it has to. Larger
+/// documents need render targets past what GL can allocate, and crash the
+/// renderer.
+fn clamp_document_size(size: DeviceIntSize, max: i32) -> (DeviceIntSize, Option<String>) {
+    let clamped = DeviceIntSize::new(size.width.min(max), size.height.min(max));
+    if clamped == size {
+        return (size, None);
+    }
+    let report = format!(
+        "{}x{} document exceeds the {} pixel limit, using {}x{}",
+        size.width, size.height, max, clamped.width, clamped.height
+    );
+    warn!("{}", report);
+    (clamped, Some(report))
+}
+
+fn centered(container: LayoutSize, size: LayoutSize) -> LayoutPoint {
+    ((container - size) / 2.0).to_vector().to_point()
+}
@@ -0,0 +1,82 @@
+use std::time::{Duration, Instant};
+
+use webrender::api::units::DeviceIntSize;
+
+use crate::app::App;
+
+/// Fractions of the starting size the `--benchmark-resize` sequence cycles
+/// through, mixing growing and shrinking steps of different lengths.
+const RESIZE_STEPS: [f32; 8] = [1.0, 0.5, 0.75, 0.25, 0.9, 0.4, 0.6, 0.33];
+
+/// Resizes the document view through a fixed sequence of sizes as fast as
+/// the event loop turns, for `--benchmark-resize`.
+///
+/// Every step rebuilds and resubmits the display list, so the report covers
+/// the whole resize path, and display lists that a later resize replaced
+/// before they were rendered are counted as dropped frames.
+pub struct ResizeBenchmark {
+    iterations: usize,
+    done: usize,
+    base: DeviceIntSize,
+    started: Option<Instant>,
+    finished: Option<Instant>,
+    skipped_before: u64,
+}
+
+impl ResizeBenchmark {
+    pub fn new(iterations: usize, app: &App) -> ResizeBenchmark {
+        ResizeBenchmark {
+            iterations,
+            done: 0,
+            base: app.device_size(),
+            started: None,
+            finished: None,
+            skipped_before: app.skipped_display_lists(),
+        }
+    }
+
+    /// Applies the next resize. Returns `false` once the sequence is over
+    /// and the last size has been rendered, at which point the app is back
+    /// at its starting size.
+    pub fn update(&mut self, app: &mut App, now: Instant) -> bool {
+        self.started.get_or_insert(now);
+
+        if self.done < self.iterations {
+            let steps = RESIZE_STEPS.len();
+            // The last resize goes back to where the sequence started.
+            let fraction = if self.done + 1 == self.iterations {
+                1.0
+            } else {
+                RESIZE_STEPS[self.done % steps]
+            };
+            let size = DeviceIntSize::new(
+                ((self.base.width as f32 * fraction) as i32).max(1),
+                ((self.base.height as f32 * fraction) as i32).max(1),
+            );
+            app.resize(size);
+            self.done += 1;
+            return true;
+        }
+
+        if !app.is_up_to_date() {
+            return true;
+        }
+        self.finished.get_or_insert(now);
+        false
+    }
+
+    pub fn summary(&self, app: &App) -> String {
+        let elapsed = match (self.started, self.finished) {
+            (Some(started), Some(finished)) => finished - started,
+            _ => Duration::ZERO,
+        };
+        let each = elapsed.as_secs_f64() * 1000.0 / self.done.max(1) as f64;
+        format!(
+            "resize benchmark: {} resizes in {:.1} ms ({:.3} ms each), {} frames dropped",
+            self.done,
+            elapsed.as_secs_f64() * 1000.0,
+            each,
+            app.skipped_display_lists() - self.skipped_before
+        )
+    }
+}
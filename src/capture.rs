@@ -0,0 +1,71 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use webrender::api::units::DeviceIntSize;
+
+/// File formats frames read back with `readback::read_region` can be saved in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Png,
+    /// Binary PPM (`P6`), RGB only. Trivial to diff.
+    Ppm,
+    /// The RGBA bytes as read back, top row first, with no header.
+    Raw,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        match name {
+            "png" => Some(OutputFormat::Png),
+            "ppm" => Some(OutputFormat::Ppm),
+            "raw" | "rgba" => Some(OutputFormat::Raw),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<OutputFormat> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        OutputFormat::from_name(&extension)
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Ppm => "ppm",
+            OutputFormat::Raw => "rgba",
+        }
+    }
+
+    /// Writes top-down RGBA8 rows of `size` to `path`.
+    pub fn write(self, path: &Path, size: DeviceIntSize, rgba: &[u8]) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        match self {
+            OutputFormat::Png => write_png(&mut file, size, rgba)?,
+            OutputFormat::Ppm => write_ppm(&mut file, size, rgba)?,
+            OutputFormat::Raw => file.write_all(rgba)?,
+        }
+        file.flush()
+    }
+}
+
+fn write_png<W: Write>(out: W, size: DeviceIntSize, rgba: &[u8]) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(out, size.width as u32, size.height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(rgba))
+        .map_err(io::Error::other)
+}
+
+fn write_ppm<W: Write>(mut out: W, size: DeviceIntSize, rgba: &[u8]) -> io::Result<()> {
+    write!(out, "P6\n{} {}\n255\n", size.width, size.height)?;
+    let rgb: Vec<u8> = rgba
+        .chunks_exact(4)
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect();
+    out.write_all(&rgb)
+}
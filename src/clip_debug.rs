@@ -0,0 +1,156 @@
+use log::debug;
+use webrender::api::{
+    units::{LayoutRect, LayoutSideOffsets},
+    BorderDetails, BorderRadius, BorderSide, BorderStyle, ClipChainId, ClipId, ColorF,
+    CommonItemProperties, ComplexClipRegion, DisplayListBuilder, NormalBorder, SpaceAndClipInfo,
+    SpatialId,
+};
+
+/// Width of the outlines, in layout pixels.
+const OUTLINE_WIDTH: f32 = 1.0;
+
+const RECT_COLOR: ColorF = ColorF {
+    r: 0.0,
+    g: 1.0,
+    b: 1.0,
+    a: 1.0,
+};
+
+const ROUNDED_COLOR: ColorF = ColorF {
+    r: 1.0,
+    g: 0.0,
+    b: 1.0,
+    a: 1.0,
+};
+
+/// A clip as it was defined: its rect in the space it was defined in, and
+/// its corners if rounded.
+struct LoggedClip {
+    spatial_id: SpatialId,
+    rect: LayoutRect,
+    radii: Option<BorderRadius>,
+}
+
+/// The clips defined in the display list being built, for the clip debug
+/// overlay.
+///
+/// Clips go through `define_clip_rect`, `define_clip_rounded_rect` and
+/// `define_clip_chain` here instead of on the builder. While the overlay is
+/// on they are logged as well, and `push_outlines` draws each one where it
+/// clips: clip rects in cyan, rounded rects in magenta. A clip chain is the
+/// intersection of its clips, which are outlined themselves.
+#[derive(Default)]
+pub struct ClipLog {
+    enabled: bool,
+    clips: Vec<LoggedClip>,
+    chains: usize,
+}
+
+impl ClipLog {
+    pub fn new(enabled: bool) -> ClipLog {
+        ClipLog {
+            enabled,
+            ..ClipLog::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    /// Forgets the clips of the last display list.
+    pub fn clear(&mut self) {
+        self.clips.clear();
+        self.chains = 0;
+    }
+
+    pub fn define_clip_rect(
+        &mut self,
+        builder: &mut DisplayListBuilder,
+        spatial_id: SpatialId,
+        rect: LayoutRect,
+    ) -> ClipId {
+        if self.enabled {
+            self.clips.push(LoggedClip {
+                spatial_id,
+                rect,
+                radii: None,
+            });
+        }
+        builder.define_clip_rect(spatial_id, rect)
+    }
+
+    pub fn define_clip_rounded_rect(
+        &mut self,
+        builder: &mut DisplayListBuilder,
+        spatial_id: SpatialId,
+        region: ComplexClipRegion,
+    ) -> ClipId {
+        if self.enabled {
+            self.clips.push(LoggedClip {
+                spatial_id,
+                rect: region.rect,
+                radii: Some(region.radii),
+            });
+        }
+        builder.define_clip_rounded_rect(spatial_id, region)
+    }
+
+    pub fn define_clip_chain<I>(
+        &mut self,
+        builder: &mut DisplayListBuilder,
+        parent: Option<ClipChainId>,
+        clips: I,
+    ) -> ClipChainId
+    where
+        I: IntoIterator<Item = ClipId>,
+        I::IntoIter: ExactSizeIterator + Clone,
+    {
+        self.chains += 1;
+        builder.define_clip_chain(parent, clips)
+    }
+
+    /// Outlines every logged clip, unclipped by anything but `clip_id`.
+    pub fn push_outlines(&self, builder: &mut DisplayListBuilder, clip_id: ClipId) {
+        if !self.enabled {
+            return;
+        }
+        debug!(
+            "clip debug: {} clips, {} clip chains",
+            self.clips.len(),
+            self.chains
+        );
+        for clip in &self.clips {
+            let side = BorderSide {
+                color: match clip.radii {
+                    Some(_) => ROUNDED_COLOR,
+                    None => RECT_COLOR,
+                },
+                style: BorderStyle::Solid,
+            };
+            builder.push_border(
+                &CommonItemProperties::new(
+                    clip.rect,
+                    SpaceAndClipInfo {
+                        spatial_id: clip.spatial_id,
+                        clip_id,
+                    },
+                ),
+                clip.rect,
+                LayoutSideOffsets::new_all_same(OUTLINE_WIDTH),
+                BorderDetails::Normal(NormalBorder {
+                    left: side,
+                    right: side,
+                    top: side,
+                    bottom: side,
+                    radius: clip.radii.unwrap_or_else(BorderRadius::zero),
+                    do_aa: true,
+                }),
+            );
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use webrender::api::ColorF;
+
+/// Sky colors `day_night` cycles through, evenly spaced over a day.
+const DAY_NIGHT: [ColorF; 4] = [
+    // Morning.
+    ColorF {
+        r: 1.0,
+        g: 0.78,
+        b: 0.55,
+        a: 1.0,
+    },
+    // Noon.
+    ColorF {
+        r: 0.55,
+        g: 0.75,
+        b: 1.0,
+        a: 1.0,
+    },
+    // Dusk.
+    ColorF {
+        r: 0.25,
+        g: 0.2,
+        b: 0.45,
+        a: 1.0,
+    },
+    // Night.
+    ColorF {
+        r: 0.03,
+        g: 0.04,
+        b: 0.1,
+        a: 1.0,
+    },
+];
+
+/// Hue in degrees from 0 to 360, saturation, value and alpha from 0 to 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hsv {
+    pub hue: f32,
+    pub saturation: f32,
+    pub value: f32,
+    pub alpha: f32,
+}
+
+impl Hsv {
+    /// Grays, including black and white, have no hue and come out as 0.
+    pub fn from_rgb(color: ColorF) -> Hsv {
+        let max = color.r.max(color.g).max(color.b);
+        let min = color.r.min(color.g).min(color.b);
+        let chroma = max - min;
+
+        let hue = if chroma == 0.0 {
+            0.0
+        } else if max == color.r {
+            60.0 * ((color.g - color.b) / chroma).rem_euclid(6.0)
+        } else if max == color.g {
+            60.0 * ((color.b - color.r) / chroma + 2.0)
+        } else {
+            60.0 * ((color.r - color.g) / chroma + 4.0)
+        };
+        let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+
+        Hsv {
+            hue,
+            saturation,
+            value: max,
+            alpha: color.a,
+        }
+    }
+
+    pub fn to_rgb(self) -> ColorF {
+        let chroma = self.value * self.saturation;
+        let sector = self.hue.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+        let (r, g, b) = match sector as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = self.value - chroma;
+        ColorF::new(r + m, g + m, b + m, self.alpha)
+    }
+}
+
+/// Turns the hue of `color` by `degrees`, keeping saturation, value and
+/// alpha. Grays are left as they are.
+pub fn rotate_hue(color: ColorF, degrees: f32) -> ColorF {
+    let mut hsv = Hsv::from_rgb(color);
+    hsv.hue = (hsv.hue + degrees).rem_euclid(360.0);
+    hsv.to_rgb()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-5;
+
+    fn assert_close(a: ColorF, b: ColorF) {
+        let channels = |c: ColorF| [c.r, c.g, c.b, c.a];
+        for (x, y) in channels(a).into_iter().zip(channels(b)) {
+            assert!((x - y).abs() < EPSILON, "{:?} is not {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn grays_have_no_hue_or_saturation() {
+        for gray in [0.0, 0.5, 1.0] {
+            let hsv = Hsv::from_rgb(ColorF::new(gray, gray, gray, 1.0));
+            assert_eq!(hsv.hue, 0.0);
+            assert_eq!(hsv.saturation, 0.0);
+            assert_eq!(hsv.value, gray);
+        }
+    }
+
+    #[test]
+    fn primaries_sit_a_third_of_a_turn_apart() {
+        let primaries = [
+            (ColorF::new(1.0, 0.0, 0.0, 1.0), 0.0),
+            (ColorF::new(0.0, 1.0, 0.0, 1.0), 120.0),
+            (ColorF::new(0.0, 0.0, 1.0, 1.0), 240.0),
+        ];
+        for (color, hue) in primaries {
+            let hsv = Hsv::from_rgb(color);
+            assert_eq!(hsv.hue, hue, "{:?}", color);
+            assert_eq!(hsv.saturation, 1.0, "{:?}", color);
+            assert_eq!(hsv.value, 1.0, "{:?}", color);
+            assert_close(hsv.to_rgb(), color);
+        }
+    }
+
+    #[test]
+    fn round_trips_colors_through_every_sector() {
+        let colors = [
+            ColorF::new(0.9, 0.6, 0.1, 1.0),
+            ColorF::new(0.4, 0.8, 0.2, 1.0),
+            ColorF::new(0.1, 0.7, 0.5, 1.0),
+            ColorF::new(0.2, 0.3, 0.9, 1.0),
+            ColorF::new(0.6, 0.1, 0.8, 1.0),
+            ColorF::new(0.7, 0.2, 0.4, 1.0),
+        ];
+        for color in colors {
+            assert_close(Hsv::from_rgb(color).to_rgb(), color);
+        }
+    }
+
+    #[test]
+    fn hue_rotation_wraps_at_a_full_turn() {
+        let red = ColorF::new(1.0, 0.0, 0.0, 1.0);
+        assert_close(rotate_hue(red, 360.0), red);
+        assert_close(rotate_hue(red, -120.0), ColorF::new(0.0, 0.0, 1.0, 1.0));
+        assert_close(rotate_hue(red, 480.0), ColorF::new(0.0, 1.0, 0.0, 1.0));
+
+        let hsv = Hsv::from_rgb(rotate_hue(ColorF::new(1.0, 0.0, 0.5, 1.0), 60.0));
+        assert!((hsv.hue - 30.0).abs() < 1e-3, "{:?}", hsv);
+    }
+
+    #[test]
+    fn alpha_passes_through() {
+        for alpha in [0.0, 0.25, 0.5, 1.0] {
+            let color = ColorF::new(0.2, 0.6, 0.4, alpha);
+            assert_eq!(Hsv::from_rgb(color).alpha, alpha);
+            assert_eq!(Hsv::from_rgb(color).to_rgb().a, alpha);
+            assert_eq!(rotate_hue(color, 90.0).a, alpha);
+            assert_eq!(rotate_hue(ColorF::new(0.5, 0.5, 0.5, alpha), 90.0).a, alpha);
+        }
+    }
+}
+
+/// How colors given on the command line, in scene files and over the
+/// control socket are written.
+///
+/// Webrender takes straight alpha: a `ColorF` of `(1, 0, 0, 0.5)` is a half
+/// transparent pure red, premultiplied by the shaders when it is drawn.
+/// Colors written premultiplied, as `(0.5, 0, 0, 0.5)` for the same red,
+/// have to be divided out first or they come out too dark.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlphaMode {
+    Straight,
+    Premultiplied,
+}
+
+impl AlphaMode {
+    pub fn from_name(name: &str) -> Option<AlphaMode> {
+        match name {
+            "straight" => Some(AlphaMode::Straight),
+            "pre" => Some(AlphaMode::Premultiplied),
+            _ => None,
+        }
+    }
+
+    /// Converts a color written in this mode to what webrender takes.
+    pub fn decode(self, color: ColorF) -> ColorF {
+        match self {
+            AlphaMode::Straight => color,
+            AlphaMode::Premultiplied if color.a == 0.0 => ColorF::TRANSPARENT,
+            AlphaMode::Premultiplied => ColorF::new(
+                color.r / color.a,
+                color.g / color.a,
+                color.b / color.a,
+                color.a,
+            ),
+        }
+    }
+
+    /// Writes a straight alpha color in this mode, the inverse of `decode`.
+    pub fn encode(self, color: ColorF) -> ColorF {
+        match self {
+            AlphaMode::Straight => color,
+            AlphaMode::Premultiplied => color.premultiplied(),
+        }
+    }
+}
+
+/// Composites straight-alpha `source` over `destination` with the usual
+/// source-over blend, giving a straight-alpha result.
+pub fn over(source: ColorF, destination: ColorF) -> ColorF {
+    let a = source.a + destination.a * (1.0 - source.a);
+    if a == 0.0 {
+        return ColorF::TRANSPARENT;
+    }
+    let channel = |s: f32, d: f32| (s * source.a + d * destination.a * (1.0 - source.a)) / a;
+    ColorF::new(
+        channel(source.r, destination.r),
+        channel(source.g, destination.g),
+        channel(source.b, destination.b),
+        a,
+    )
+}
+
+/// The `--daynight` sky at `phase` through the day, from 0 to 1: warm in the
+/// morning, cool at noon, dark at night and back, blending linearly between
+/// the colors of `DAY_NIGHT`.
+pub fn day_night(phase: f32) -> ColorF {
+    let position = phase.rem_euclid(1.0) * DAY_NIGHT.len() as f32;
+    let index = (position as usize).min(DAY_NIGHT.len() - 1);
+    let t = position - index as f32;
+    let (from, to) = (DAY_NIGHT[index], DAY_NIGHT[(index + 1) % DAY_NIGHT.len()]);
+    let lerp = |a: f32, b: f32| a + (b - a) * t;
+    ColorF::new(
+        lerp(from.r, to.r),
+        lerp(from.g, to.g),
+        lerp(from.b, to.b),
+        1.0,
+    )
+}
+
+/// A color matrix applied over the whole scene, for `--invert`, `--sepia`
+/// and `--grayscale`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorFilter {
+    Invert,
+    Sepia,
+    Grayscale,
+}
+
+impl ColorFilter {
+    pub fn flag(self) -> &'static str {
+        match self {
+            ColorFilter::Invert => "--invert",
+            ColorFilter::Sepia => "--sepia",
+            ColorFilter::Grayscale => "--grayscale",
+        }
+    }
+
+    /// The matrix one row per output channel, red, green, blue and alpha,
+    /// over the input channels and a constant offset, as SVG writes it.
+    #[rustfmt::skip]
+    fn rows(self) -> [[f32; 5]; 4] {
+        match self {
+            ColorFilter::Invert => [
+                [-1.0, 0.0, 0.0, 0.0, 1.0],
+                [0.0, -1.0, 0.0, 0.0, 1.0],
+                [0.0, 0.0, -1.0, 0.0, 1.0],
+                [0.0, 0.0, 0.0, 1.0, 0.0],
+            ],
+            ColorFilter::Sepia => [
+                [0.393, 0.769, 0.189, 0.0, 0.0],
+                [0.349, 0.686, 0.168, 0.0, 0.0],
+                [0.272, 0.534, 0.131, 0.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0, 0.0],
+            ],
+            // Rec. 709 luma.
+            ColorFilter::Grayscale => [
+                [0.2126, 0.7152, 0.0722, 0.0, 0.0],
+                [0.2126, 0.7152, 0.0722, 0.0, 0.0],
+                [0.2126, 0.7152, 0.0722, 0.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0, 0.0],
+            ],
+        }
+    }
+
+    /// The matrix as `FilterOp::ColorMatrix` takes it: column by column,
+    /// each input channel's weight in every output, then the offsets.
+    pub fn matrix(self) -> [f32; 20] {
+        let rows = self.rows();
+        let mut matrix = [0.0; 20];
+        for (column, weights) in matrix.chunks_mut(4).enumerate() {
+            for (output, weight) in weights.iter_mut().enumerate() {
+                *weight = rows[output][column];
+            }
+        }
+        matrix
+    }
+
+    /// Filters one straight-alpha color the way the shader does, for the
+    /// background colors that are cleared to rather than drawn.
+    pub fn apply(self, color: ColorF) -> ColorF {
+        let input = [color.r, color.g, color.b, color.a, 1.0];
+        let channel = |row: [f32; 5]| {
+            let sum: f32 = row
+                .iter()
+                .zip(input)
+                .map(|(weight, value)| weight * value)
+                .sum();
+            sum.clamp(0.0, 1.0)
+        };
+        let [r, g, b, a] = self.rows().map(channel);
+        ColorF::new(r, g, b, a)
+    }
+}
@@ -0,0 +1,798 @@
+use std::{path::PathBuf, time::Duration};
+
+use glutin::Api;
+use webrender::api::{
+    units::{DeviceIntPoint, DeviceIntRect, DeviceIntSize, LayoutSize},
+    ColorF,
+};
+
+use crate::{
+    capture::OutputFormat,
+    color::{AlphaMode, ColorFilter},
+    coords::ClipRect,
+    easing::Easing,
+    hud::Corner,
+    image::BackdropFit,
+    nested_scroll::MAX_SCROLL_FRAMES,
+    palette::Palette,
+    path::SvgPath,
+    scene::BuiltinScene,
+};
+
+/// Largest accepted `--window-size` along either axis.
+const MAX_WINDOW_SIZE: i32 = 16384;
+
+/// Corner alpha of `--vignette` without `--vignette-intensity`.
+const DEFAULT_VIGNETTE: f32 = 0.6;
+
+/// Length of a `--daynight` day without `--daynight-period`.
+const DEFAULT_DAYNIGHT_PERIOD: Duration = Duration::from_secs(60);
+
+/// Longest accepted `--daynight-period`, a day, well short of what
+/// `Duration` can hold.
+const MAX_PERIOD_SECS: f64 = 86_400.0;
+
+/// Design size of `--aspect-lock` without `--design-size`.
+const DEFAULT_DESIGN_SIZE: (f32, f32) = (1280.0, 720.0);
+
+/// Largest accepted `--design-size` along either axis.
+const MAX_DESIGN_SIZE: f32 = 16384.0;
+
+/// Command line options.
+pub struct Config {
+    /// Root background color passed to `set_display_list`.
+    pub background: ColorF,
+    /// Cycles the background through a day's sky colors over this long,
+    /// replacing `background`.
+    pub daynight: Option<Duration>,
+    /// Passes no background to `set_display_list`, so nothing fills the
+    /// areas the scene doesn't draw over.
+    pub no_clear: bool,
+    /// Turns on webrender's overdraw view, which draws every primitive in
+    /// one translucent color so that pixels get brighter the more layers
+    /// cover them.
+    pub show_overdraw: bool,
+    /// Turns on webrender's gradient dithering, an ordered dither of about
+    /// one 8-bit step that breaks up banding.
+    pub dither: bool,
+    /// Draws a black to white vertical gradient over the whole scene, under
+    /// its rects, where banding shows most.
+    pub ramp: bool,
+    /// PNG decoded in the background and drawn centered over the scene.
+    pub image: Option<PathBuf>,
+    /// Sizes the window to the `--image` once decoded, shrinking the image
+    /// to fit when it is larger than the screen.
+    pub fit_image: bool,
+    /// PNG drawn under the scene, above the background color, placed by
+    /// `bg_fit`.
+    pub bg_image: Option<PathBuf>,
+    pub bg_fit: BackdropFit,
+    /// Draws an animated spinner over the scene.
+    pub spinner: bool,
+    /// Inner size of the window in physical pixels, instead of the
+    /// platform's default, so that captures come out the same size on
+    /// every machine.
+    pub window_size: Option<DeviceIntSize>,
+    /// Draws a sweeping linear gradient and a spinning conic gradient.
+    pub animate_gradient: bool,
+    /// Renders at this many times the window resolution and downsamples.
+    pub supersample: Option<i32>,
+    /// Device pixel ratio to use instead of the one reported by the OS.
+    pub scale: Option<f32>,
+    /// Clips the scene to this area with a display list clip, leaving the
+    /// background showing around it.
+    pub clip_rect: Option<ClipRect>,
+    /// Lays the scene out at this size and scales it uniformly to fit the
+    /// window, keeping its aspect ratio.
+    pub design_size: Option<LayoutSize>,
+    /// Fills the letterbox or pillarbox bars around the design area.
+    pub letterbox_color: ColorF,
+    /// Prints how the display list of the second scene file differs from
+    /// the first's, then exits.
+    pub diff: Option<(PathBuf, PathBuf)>,
+    /// JSON scene drawn instead of the built-in ones.
+    pub scene: Option<PathBuf>,
+    /// SVG document whose rects and circles are drawn instead of the
+    /// built-in scenes.
+    pub svg: Option<PathBuf>,
+    /// Where Ctrl+Shift+S saves the current scene.
+    pub export_path: PathBuf,
+    /// Prints the scene as JSON and exits without opening the event loop.
+    pub dump_scene: bool,
+    /// Reloads the `--scene` whenever the file changes.
+    pub watch: bool,
+    /// Replaces the default scene with a grid of this many rects.
+    pub rects: Option<usize>,
+    /// Built-in scene to start on, picked by name.
+    pub scene_name: Option<BuiltinScene>,
+    /// Prints the built-in scenes' names and exits.
+    pub list_scenes: bool,
+    /// Colors of the generated `--rects` grid, instead of red, green and
+    /// blue.
+    pub palette: Option<Palette>,
+    /// Merges adjacent same-colored rects before building the display list.
+    pub optimize: bool,
+    /// GL or GLES version to ask for instead of the latest.
+    pub gl_version: Option<(Api, (u8, u8))>,
+    /// Caps the texture size used for images below the GL limit, so that
+    /// image tiling can be exercised with smaller files.
+    pub max_texture_size: Option<i32>,
+    /// Caps the document's width and height in device pixels, below the
+    /// GL limits it is always capped at.
+    pub max_document_size: Option<i32>,
+    /// Filled path drawn over the scene, from `--svg-path` data.
+    pub svg_path: Option<SvgPath>,
+    /// Largest distance, in layout pixels, between a curve and the lines
+    /// it is flattened into.
+    pub path_tolerance: f32,
+    /// Rebuilds the display list for hover highlights instead of updating a
+    /// property binding, to compare the cost of the two in the profiler.
+    pub hover_rebuild: bool,
+    /// Keeps the scene's rects in a display list of their own, sent again
+    /// only when they change, instead of rebuilding them with everything
+    /// else drawn over them.
+    pub retain_display_list: bool,
+    /// Sends a reloaded scene that only moves rects or changes their colors
+    /// as property binding updates instead of a new display list.
+    pub incremental_updates: bool,
+    /// Outlines the front item under the pointer, whatever its kind, and
+    /// logs its hit-test tag.
+    pub hit_test_debug: bool,
+    /// Outlines every clip the display list defines, clip rects and rounded
+    /// rects in different colors.
+    pub clip_debug: bool,
+    /// Labels the cursor with its coordinates.
+    pub coords: bool,
+    /// Snaps the debug grid and ruler lines to device pixels so 1px lines
+    /// stay crisp at fractional scale factors.
+    pub dpi_aware_lines: bool,
+    /// Easing of the Shift+1-9 scroll to a rect.
+    pub scroll_easing: Easing,
+    /// Number of bouncing particles drawn over the scene.
+    pub particles: Option<usize>,
+    /// Number of scroll frames nested in each other, scrolled by the wheel.
+    pub nested_scroll: Option<usize>,
+    /// Seeds the particles' starting positions and velocities.
+    pub particle_seed: u64,
+    /// Reveals every new scene with a left to right wipe.
+    pub wipe_transitions: bool,
+    /// Drops a single blurred shadow under the scene's rects as a group.
+    pub group_shadow: bool,
+    /// Color matrix applied over everything drawn.
+    pub color_filter: Option<ColorFilter>,
+    /// Darkens the window towards its corners, to this alpha, over
+    /// everything else.
+    pub vignette: Option<f32>,
+    /// Resizes the document view this many times as fast as possible,
+    /// reports the time taken and frames dropped, then exits.
+    pub benchmark_resize: Option<usize>,
+    /// File each rendered frame's timings and counters are written to, as
+    /// newline-delimited JSON.
+    pub profile_json: Option<PathBuf>,
+    /// Logs the renderer's memory report every this many frames.
+    pub mem_report: Option<u64>,
+    /// Sleeps this long before rendering each frame, to simulate a slow
+    /// renderer.
+    pub frame_delay: Option<Duration>,
+    /// Advances the clock animations follow by this much per rendered
+    /// frame instead of following the wall clock, so that `--record` and
+    /// `--screenshot` capture the same frames however fast they render.
+    pub fixed_timestep: Option<Duration>,
+    /// Exits after rendering this many frames, whatever else is running.
+    pub max_frames: Option<u64>,
+    /// How alpha is written in input colors; converted to the straight
+    /// alpha webrender takes before drawing.
+    pub alpha_mode: AlphaMode,
+    /// Draws the alpha overlap scene and prints the expected and rendered
+    /// color at its center.
+    pub alpha_test: bool,
+    /// Shows a text field; Enter loads the path typed into it as a scene.
+    pub text_input: bool,
+    /// Window corner the HUD overlays, such as the text field, sit in.
+    pub hud_corner: Corner,
+    /// Cycles through the built-in scenes and scripted interactions, then
+    /// exits.
+    pub tour: bool,
+    /// Repeats the `--tour` until the window is closed.
+    pub looping: bool,
+    /// Directory every rendered frame is saved into.
+    pub record: Option<PathBuf>,
+    /// File the first rendered frame is saved to before exiting.
+    pub screenshot: Option<PathBuf>,
+    /// Part of the frame, in device pixels, that `--screenshot`, `--record`
+    /// and `screenshot` commands save instead of all of it.
+    pub capture_rect: Option<DeviceIntRect>,
+    /// Format of `--record` and `--screenshot` files. Without
+    /// `--output-format` it follows the `--screenshot` extension, then PNG.
+    pub output_format: OutputFormat,
+    /// File every input-driven change is logged to, for `--replay`. Also
+    /// `--record-input`.
+    pub record_transactions: Option<PathBuf>,
+    /// Log written by `--record-transactions` to play back, exiting at the
+    /// end. Also `--replay-input`.
+    pub replay: Option<PathBuf>,
+    /// Plays the `--replay` this many times faster than it was recorded.
+    pub replay_speed: f64,
+    /// Scene file the `--replay` has to end on, as saved with
+    /// Ctrl+Shift+S, or the demo exits with an error.
+    pub expect_scene: Option<PathBuf>,
+    /// Exits with an error on the first fallback the demo would otherwise
+    /// log and carry on past: a scene or image that doesn't load, SVG
+    /// content left out, or a document size capped.
+    pub strict: bool,
+    /// Number of windows showing the scene, each with its own renderer.
+    /// Input goes to the first.
+    pub windows: usize,
+    /// Local TCP port accepting line-based commands that drive the demo.
+    pub control_port: Option<u16>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            background: ColorF::new(1.0, 0.0, 0.0, 1.0),
+            daynight: None,
+            no_clear: false,
+            show_overdraw: false,
+            dither: false,
+            ramp: false,
+            image: None,
+            fit_image: false,
+            bg_image: None,
+            bg_fit: BackdropFit::Center,
+            spinner: false,
+            window_size: None,
+            animate_gradient: false,
+            supersample: None,
+            scale: None,
+            clip_rect: None,
+            design_size: None,
+            diff: None,
+            letterbox_color: ColorF::BLACK,
+            scene: None,
+            svg: None,
+            scene_name: None,
+            list_scenes: false,
+            export_path: PathBuf::from("scene-export.json"),
+            dump_scene: false,
+            watch: false,
+            rects: None,
+            palette: None,
+            optimize: false,
+            gl_version: None,
+            max_texture_size: None,
+            max_document_size: None,
+            svg_path: None,
+            path_tolerance: 0.25,
+            hover_rebuild: false,
+            retain_display_list: false,
+            incremental_updates: false,
+            hit_test_debug: false,
+            clip_debug: false,
+            coords: false,
+            dpi_aware_lines: false,
+            scroll_easing: Easing::EaseInOut,
+            particles: None,
+            nested_scroll: None,
+            particle_seed: 1,
+            wipe_transitions: false,
+            group_shadow: false,
+            color_filter: None,
+            vignette: None,
+            benchmark_resize: None,
+            profile_json: None,
+            mem_report: None,
+            frame_delay: None,
+            fixed_timestep: None,
+            max_frames: None,
+            alpha_mode: AlphaMode::Straight,
+            alpha_test: false,
+            text_input: false,
+            hud_corner: Corner::TopLeft,
+            tour: false,
+            looping: false,
+            record: None,
+            screenshot: None,
+            capture_rect: None,
+            output_format: OutputFormat::Png,
+            record_transactions: None,
+            replay: None,
+            replay_speed: 1.0,
+            expect_scene: None,
+            strict: false,
+            windows: 1,
+            control_port: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<Config, String> {
+        let mut config = Config::default();
+        let mut output_format = None;
+        let mut bg_fit = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--background" => config.background = parse_color(&value(&arg, args.next())?)?,
+                "--daynight" => {
+                    config.daynight = Some(config.daynight.unwrap_or(DEFAULT_DAYNIGHT_PERIOD))
+                }
+                "--daynight-period" => {
+                    config.daynight = Some(parse_period(&value(&arg, args.next())?)?)
+                }
+                "--no-clear" => config.no_clear = true,
+                "--show-overdraw" => config.show_overdraw = true,
+                "--dither" => config.dither = true,
+                "--ramp" => config.ramp = true,
+                "--image" => config.image = Some(value(&arg, args.next())?.into()),
+                "--fit-image" => config.fit_image = true,
+                "--hud-corner" => config.hud_corner = parse_hud_corner(&value(&arg, args.next())?)?,
+                "--bg-image" => config.bg_image = Some(value(&arg, args.next())?.into()),
+                "--bg-fit" => bg_fit = Some(parse_bg_fit(&value(&arg, args.next())?)?),
+                "--spinner" => config.spinner = true,
+                "--window-size" => {
+                    config.window_size = Some(parse_window_size(&value(&arg, args.next())?)?)
+                }
+                "--animate-gradient" => config.animate_gradient = true,
+                "--supersample" => {
+                    config.supersample = match value(&arg, args.next())?.as_str() {
+                        "1" => None,
+                        "2" => Some(2),
+                        "4" => Some(4),
+                        other => {
+                            return Err(format!(
+                                "invalid supersample factor `{}`, expected 2 or 4",
+                                other
+                            ))
+                        }
+                    }
+                }
+                "--scale" => config.scale = Some(parse_scale(&value(&arg, args.next())?)?),
+                "--clip-rect" => {
+                    config.clip_rect = Some(ClipRect::parse(&value(&arg, args.next())?)?)
+                }
+                "--aspect-lock" => {
+                    let (width, height) = DEFAULT_DESIGN_SIZE;
+                    config.design_size =
+                        Some(config.design_size.unwrap_or(LayoutSize::new(width, height)))
+                }
+                "--design-size" => {
+                    config.design_size = Some(parse_design_size(&value(&arg, args.next())?)?)
+                }
+                "--letterbox-color" => {
+                    config.letterbox_color = parse_color(&value(&arg, args.next())?)?
+                }
+                "--diff" => {
+                    let a = value(&arg, args.next())?;
+                    let b = value(&arg, args.next())?;
+                    config.diff = Some((a.into(), b.into()))
+                }
+                "--gl-version" => {
+                    config.gl_version = Some(parse_gl_version(&value(&arg, args.next())?)?)
+                }
+                "--scene" => config.scene = Some(value(&arg, args.next())?.into()),
+                "--svg" => config.svg = Some(value(&arg, args.next())?.into()),
+                "--scene-name" => {
+                    config.scene_name = Some(BuiltinScene::from_name(&value(&arg, args.next())?)?)
+                }
+                "--list-scenes" => config.list_scenes = true,
+                "--export-path" => config.export_path = value(&arg, args.next())?.into(),
+                "--dump-scene" => config.dump_scene = true,
+                "--watch" => config.watch = true,
+                "--rects" => config.rects = Some(parse_count(&value(&arg, args.next())?)?),
+                "--palette" => config.palette = Some(parse_palette(&value(&arg, args.next())?)?),
+                "--optimize" => config.optimize = true,
+                "--max-texture-size" => {
+                    config.max_texture_size = Some(parse_size(&value(&arg, args.next())?)?)
+                }
+                "--max-document-size" => {
+                    config.max_document_size = Some(parse_size(&value(&arg, args.next())?)?)
+                }
+                "--svg-path" => config.svg_path = Some(SvgPath::parse(&value(&arg, args.next())?)?),
+                "--path-tolerance" => {
+                    config.path_tolerance = parse_tolerance(&value(&arg, args.next())?)?
+                }
+                "--hover-rebuild" => config.hover_rebuild = true,
+                "--retain-display-list" => config.retain_display_list = true,
+                "--incremental-updates" => config.incremental_updates = true,
+                "--hit-test-debug" => config.hit_test_debug = true,
+                "--clip-debug" => config.clip_debug = true,
+                "--coords" => config.coords = true,
+                "--dpi-aware-lines" => config.dpi_aware_lines = true,
+                "--scroll-easing" => {
+                    config.scroll_easing = parse_easing(&value(&arg, args.next())?)?
+                }
+                "--nested-scroll" => {
+                    config.nested_scroll = Some(parse_scroll_frames(&value(&arg, args.next())?)?)
+                }
+                "--particles" => config.particles = Some(parse_count(&value(&arg, args.next())?)?),
+                "--particle-seed" => {
+                    let seed = value(&arg, args.next())?;
+                    config.particle_seed = seed
+                        .parse()
+                        .map_err(|_| format!("invalid seed `{}`, expected a whole number", seed))?
+                }
+                "--wipe-transitions" => config.wipe_transitions = true,
+                "--group-shadow" => config.group_shadow = true,
+                "--invert" => set_color_filter(&mut config, ColorFilter::Invert)?,
+                "--sepia" => set_color_filter(&mut config, ColorFilter::Sepia)?,
+                "--grayscale" => set_color_filter(&mut config, ColorFilter::Grayscale)?,
+                "--vignette" => config.vignette = Some(config.vignette.unwrap_or(DEFAULT_VIGNETTE)),
+                "--vignette-intensity" => {
+                    config.vignette = Some(parse_intensity(&value(&arg, args.next())?)?)
+                }
+                "--benchmark-resize" => {
+                    config.benchmark_resize = Some(parse_count(&value(&arg, args.next())?)?)
+                }
+                "--profile-json" => config.profile_json = Some(value(&arg, args.next())?.into()),
+                "--mem-report" => {
+                    config.mem_report = match parse_count(&value(&arg, args.next())?)? {
+                        0 => return Err("`--mem-report` needs at least 1 frame".to_string()),
+                        frames => Some(frames as u64),
+                    }
+                }
+                "--frame-delay" => {
+                    config.frame_delay = Some(parse_delay(&value(&arg, args.next())?)?)
+                }
+                "--fixed-timestep" => {
+                    config.fixed_timestep = Some(parse_timestep(&value(&arg, args.next())?)?)
+                }
+                "--max-frames" => {
+                    config.max_frames = Some(parse_count(&value(&arg, args.next())?)? as u64)
+                }
+                "--alpha-mode" => config.alpha_mode = parse_alpha_mode(&value(&arg, args.next())?)?,
+                "--alpha-test" => config.alpha_test = true,
+                "--text-input" => config.text_input = true,
+                "--tour" => config.tour = true,
+                "--loop" => config.looping = true,
+                "--record" => config.record = Some(value(&arg, args.next())?.into()),
+                "--screenshot" => config.screenshot = Some(value(&arg, args.next())?.into()),
+                "--capture-rect" => {
+                    config.capture_rect = Some(parse_capture_rect(&value(&arg, args.next())?)?)
+                }
+                "--output-format" => {
+                    output_format = Some(parse_output_format(&value(&arg, args.next())?)?)
+                }
+                "--record-transactions" | "--record-input" => {
+                    config.record_transactions = Some(value(&arg, args.next())?.into())
+                }
+                "--replay" | "--replay-input" => {
+                    config.replay = Some(value(&arg, args.next())?.into())
+                }
+                "--replay-speed" => config.replay_speed = parse_speed(&value(&arg, args.next())?)?,
+                "--expect-scene" => config.expect_scene = Some(value(&arg, args.next())?.into()),
+                "--strict" => config.strict = true,
+                "--windows" => {
+                    config.windows = match parse_count(&value(&arg, args.next())?)? {
+                        0 => return Err("`--windows` needs at least 1 window".to_string()),
+                        windows => windows,
+                    }
+                }
+                "--control-port" => {
+                    config.control_port = Some(parse_port(&value(&arg, args.next())?)?)
+                }
+                _ => return Err(format!("unknown argument `{}`", arg)),
+            }
+        }
+
+        if config.replay.is_none() {
+            if config.replay_speed != 1.0 {
+                return Err("`--replay-speed` needs a `--replay` to play".to_string());
+            }
+            if config.expect_scene.is_some() {
+                return Err("`--expect-scene` needs a `--replay` to check".to_string());
+            }
+        }
+        if bg_fit.is_some() && config.bg_image.is_none() {
+            return Err("`--bg-fit` needs a `--bg-image` to place".to_string());
+        }
+        config.bg_fit = bg_fit.unwrap_or(BackdropFit::Center);
+        if config.scene_name.is_some() {
+            let conflicting = [
+                ("--rects", config.rects.is_some()),
+                ("--alpha-test", config.alpha_test),
+                ("--scene", config.scene.is_some()),
+                ("--svg", config.svg.is_some()),
+            ];
+            if let Some((flag, _)) = conflicting.iter().find(|(_, given)| *given) {
+                return Err(format!("`--scene-name` and `{}` can't be combined", flag));
+            }
+        }
+        if config.scene.is_some() && config.svg.is_some() {
+            return Err("`--scene` and `--svg` can't be combined".to_string());
+        }
+        if config.incremental_updates && config.retain_display_list {
+            return Err(
+                "`--incremental-updates` and `--retain-display-list` can't be combined".to_string(),
+            );
+        }
+        if config.watch && config.scene.is_none() {
+            return Err("`--watch` needs a `--scene` to watch".to_string());
+        }
+        if config.watch && config.replay.is_some() {
+            return Err("`--replay` plays back the recorded reloads, drop `--watch`".to_string());
+        }
+        if config.supersample.is_some() && config.windows > 1 {
+            return Err(
+                "`--supersample` only renders the main window, drop `--windows`".to_string(),
+            );
+        }
+        if let (Some(factor), Some(size), Some(max)) = (
+            config.supersample,
+            config.window_size,
+            config.max_document_size,
+        ) {
+            if size.width.max(size.height) * factor > max {
+                return Err(format!(
+                    "`--supersample {}` at {}x{} goes past `--max-document-size {}`",
+                    factor, size.width, size.height, max
+                ));
+            }
+        }
+
+        let extension_format = config.screenshot.as_deref().map(OutputFormat::from_path);
+        config.output_format = match (output_format, extension_format) {
+            (Some(format), Some(Some(from_path))) if format != from_path => {
+                return Err(format!(
+                    "`--output-format {}` doesn't match the extension of `{}`",
+                    format.extension(),
+                    config.screenshot.unwrap().display()
+                ))
+            }
+            (Some(format), _) => format,
+            (None, Some(Some(from_path))) => from_path,
+            (None, Some(None)) => {
+                return Err(format!(
+                    "can't tell the format of `{}` from its extension, pass `--output-format`",
+                    config.screenshot.unwrap().display()
+                ))
+            }
+            (None, None) => OutputFormat::Png,
+        };
+
+        Ok(config)
+    }
+}
+
+fn value(flag: &str, value: Option<String>) -> Result<String, String> {
+    value.ok_or_else(|| format!("`{}` expects a value", flag))
+}
+
+/// Parses `WIDTHxHEIGHT` in physical pixels.
+fn parse_window_size(s: &str) -> Result<DeviceIntSize, String> {
+    let parsed = s
+        .split_once('x')
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)));
+    match parsed {
+        Some((width, height))
+            if (1..=MAX_WINDOW_SIZE).contains(&width)
+                && (1..=MAX_WINDOW_SIZE).contains(&height) =>
+        {
+            Ok(DeviceIntSize::new(width, height))
+        }
+        _ => Err(format!(
+            "invalid window size `{}`, expected WIDTHxHEIGHT between 1 and {}",
+            s, MAX_WINDOW_SIZE
+        )),
+    }
+}
+
+fn set_color_filter(config: &mut Config, filter: ColorFilter) -> Result<(), String> {
+    match config.color_filter {
+        Some(other) if other != filter => Err(format!(
+            "`{}` can't be combined with `{}`",
+            filter.flag(),
+            other.flag()
+        )),
+        _ => {
+            config.color_filter = Some(filter);
+            Ok(())
+        }
+    }
+}
+
+fn parse_count(s: &str) -> Result<usize, String> {
+    s.parse()
+        .map_err(|_| format!("invalid count `{}`, expected a whole number", s))
+}
+
+fn parse_scroll_frames(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(count) if (1..=MAX_SCROLL_FRAMES).contains(&count) => Ok(count),
+        _ => Err(format!(
+            "invalid scroll frame count `{}`, expected 1 to {}",
+            s, MAX_SCROLL_FRAMES
+        )),
+    }
+}
+
+fn parse_size(s: &str) -> Result<i32, String> {
+    match s.parse::<i32>() {
+        Ok(size) if size > 0 => Ok(size),
+        _ => Err(format!("invalid size `{}`, expected a positive number", s)),
+    }
+}
+
+/// Parses `x,y,w,h` in device pixels, from the top left of the window.
+fn parse_capture_rect(s: &str) -> Result<DeviceIntRect, String> {
+    let invalid = || {
+        format!(
+            "invalid capture rect `{}`, expected x,y,w,h in device pixels",
+            s
+        )
+    };
+    let values = s
+        .split(',')
+        .map(|part| part.trim().parse::<i32>().map_err(|_| invalid()))
+        .collect::<Result<Vec<_>, _>>()?;
+    match values[..] {
+        [x, y, width, height] if x >= 0 && y >= 0 && width > 0 && height > 0 => {
+            Ok(DeviceIntRect::from_origin_and_size(
+                DeviceIntPoint::new(x, y),
+                DeviceIntSize::new(width, height),
+            ))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Parses `WIDTHxHEIGHT` in layout pixels, like `1280x720`.
+fn parse_design_size(s: &str) -> Result<LayoutSize, String> {
+    let invalid = || {
+        format!(
+            "invalid design size `{}`, expected WIDTHxHEIGHT like 1280x720",
+            s
+        )
+    };
+    let (width, height) = s.split_once('x').ok_or_else(invalid)?;
+    let parse = |value: &str| match value.trim().parse::<f32>() {
+        Ok(value) if value > 0.0 && value <= MAX_DESIGN_SIZE => Ok(value),
+        _ => Err(invalid()),
+    };
+    Ok(LayoutSize::new(parse(width)?, parse(height)?))
+}
+
+/// Parses `MAJOR.MINOR` for desktop GL, or `es` then `MAJOR.MINOR` for
+/// GLES, like `3.3` or `es3.0`.
+fn parse_gl_version(s: &str) -> Result<(Api, (u8, u8)), String> {
+    let (api, version) = match s.strip_prefix("es") {
+        Some(version) => (Api::OpenGlEs, version),
+        None => (Api::OpenGl, s),
+    };
+    let parsed = version
+        .split_once('.')
+        .and_then(|(major, minor)| Some((major.parse().ok()?, minor.parse().ok()?)));
+    match parsed {
+        Some((major, minor)) if major > 0 => Ok((api, (major, minor))),
+        _ => Err(format!(
+            "invalid GL version `{}`, expected MAJOR.MINOR like 3.3, or es3.0 for GLES",
+            s
+        )),
+    }
+}
+
+/// Parses milliseconds, written `50ms` or just `50`.
+fn parse_delay(s: &str) -> Result<Duration, String> {
+    match s.strip_suffix("ms").unwrap_or(s).parse::<u64>() {
+        Ok(millis) => Ok(Duration::from_millis(millis)),
+        _ => Err(format!(
+            "invalid delay `{}`, expected milliseconds like 50ms",
+            s
+        )),
+    }
+}
+
+/// Parses a positive number of milliseconds, written `16ms` or just `16`.
+fn parse_timestep(s: &str) -> Result<Duration, String> {
+    match s.strip_suffix("ms").unwrap_or(s).parse::<u64>() {
+        Ok(millis) if millis > 0 => Ok(Duration::from_millis(millis)),
+        _ => Err(format!(
+            "invalid timestep `{}`, expected positive milliseconds like 16ms",
+            s
+        )),
+    }
+}
+
+/// Parses a positive number of seconds, written `90s` or just `90`.
+fn parse_period(s: &str) -> Result<Duration, String> {
+    match s.strip_suffix('s').unwrap_or(s).parse::<f64>() {
+        Ok(secs) if secs > 0.0 && secs <= MAX_PERIOD_SECS => Ok(Duration::from_secs_f64(secs)),
+        _ => Err(format!(
+            "invalid period `{}`, expected a positive number of seconds like 90s",
+            s
+        )),
+    }
+}
+
+fn parse_easing(s: &str) -> Result<Easing, String> {
+    Easing::from_name(s).ok_or_else(|| {
+        format!(
+            "invalid easing `{}`, expected linear, ease-in, ease-out, ease-in-out or \
+             cubic-bezier(x1,y1,x2,y2)",
+            s
+        )
+    })
+}
+
+fn parse_palette(s: &str) -> Result<Palette, String> {
+    Palette::from_name(s).ok_or_else(|| {
+        format!(
+            "invalid palette `{}`, expected random, random:SEED, golden or grayscale",
+            s
+        )
+    })
+}
+
+fn parse_intensity(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(intensity) if (0.0..=1.0).contains(&intensity) => Ok(intensity),
+        _ => Err(format!("invalid intensity `{}`, expected 0 to 1", s)),
+    }
+}
+
+fn parse_alpha_mode(s: &str) -> Result<AlphaMode, String> {
+    AlphaMode::from_name(s)
+        .ok_or_else(|| format!("invalid alpha mode `{}`, expected pre or straight", s))
+}
+
+fn parse_hud_corner(s: &str) -> Result<Corner, String> {
+    Corner::from_name(s).ok_or_else(|| format!("invalid corner `{}`, expected tl, tr, bl or br", s))
+}
+
+fn parse_bg_fit(s: &str) -> Result<BackdropFit, String> {
+    BackdropFit::from_name(s)
+        .ok_or_else(|| format!("invalid fit `{}`, expected center, stretch or tile", s))
+}
+
+fn parse_port(s: &str) -> Result<u16, String> {
+    match s.parse::<u16>() {
+        Ok(port) if port > 0 => Ok(port),
+        _ => Err(format!("invalid port `{}`, expected 1 to 65535", s)),
+    }
+}
+
+fn parse_output_format(s: &str) -> Result<OutputFormat, String> {
+    OutputFormat::from_name(s)
+        .ok_or_else(|| format!("invalid output format `{}`, expected png, ppm or raw", s))
+}
+
+fn parse_tolerance(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(tolerance) if tolerance.is_finite() && tolerance > 0.0 => Ok(tolerance),
+        _ => Err(format!(
+            "invalid tolerance `{}`, expected a positive number",
+            s
+        )),
+    }
+}
+
+fn parse_speed(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(speed) if speed.is_finite() && speed > 0.0 => Ok(speed),
+        _ => Err(format!("invalid speed `{}`, expected a positive number", s)),
+    }
+}
+
+fn parse_scale(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(scale) if scale.is_finite() && scale > 0.0 => Ok(scale),
+        _ => Err(format!("invalid scale `{}`, expected a positive number", s)),
+    }
+}
+
+/// Parses `#rrggbb` or `#rrggbbaa`, with or without the leading `#`.
+pub fn parse_color(s: &str) -> Result<ColorF, String> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!(
+            "invalid color `{}`, expected #rrggbb or #rrggbbaa",
+            s
+        ));
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap() as f32 / 255.0;
+    let alpha = if hex.len() == 8 { channel(6) } else { 1.0 };
+
+    Ok(ColorF::new(channel(0), channel(2), channel(4), alpha))
+}
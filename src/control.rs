@@ -0,0 +1,169 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    sync::mpsc,
+    thread,
+};
+
+use glutin::event_loop::EventLoopProxy;
+use log::{error, info, warn};
+use webrender::api::{
+    units::{LayoutPoint, LayoutRect, LayoutSize},
+    ColorF,
+};
+
+use crate::{config::parse_color, UserEvent};
+
+/// A line received on the `--control-port` socket.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// `add_rect x y w h #rrggbb`, in scene coordinates.
+    AddRect(LayoutRect, ColorF),
+    /// `clear` removes every rect from the scene.
+    Clear,
+    /// `screenshot path` saves the next up to date frame.
+    Screenshot(PathBuf),
+    /// `zoom factor` zooms about the window center.
+    Zoom(f32),
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let mut words = line.split_whitespace();
+        let name = words.next().ok_or("empty command")?;
+        let args: Vec<&str> = words.collect();
+        let expect = |count: usize| {
+            if args.len() == count {
+                Ok(())
+            } else {
+                Err(format!(
+                    "`{}` expects {} arguments, found {}",
+                    name,
+                    count,
+                    args.len()
+                ))
+            }
+        };
+        let number = |s: &str| match s.parse::<f32>() {
+            Ok(value) if value.is_finite() => Ok(value),
+            _ => Err(format!("invalid number `{}`", s)),
+        };
+
+        match name {
+            "add_rect" => {
+                expect(5)?;
+                let origin = LayoutPoint::new(number(args[0])?, number(args[1])?);
+                let size = LayoutSize::new(number(args[2])?, number(args[3])?);
+                if size.is_empty() {
+                    return Err("the rect is empty".to_string());
+                }
+                Ok(Command::AddRect(
+                    LayoutRect::from_origin_and_size(origin, size),
+                    parse_color(args[4])?,
+                ))
+            }
+            "clear" => {
+                expect(0)?;
+                Ok(Command::Clear)
+            }
+            "screenshot" => {
+                expect(1)?;
+                Ok(Command::Screenshot(args[0].into()))
+            }
+            "zoom" => {
+                expect(1)?;
+                match number(args[0])? {
+                    zoom if zoom > 0.0 => Ok(Command::Zoom(zoom)),
+                    zoom => Err(format!(
+                        "invalid zoom `{}`, expected a positive number",
+                        zoom
+                    )),
+                }
+            }
+            _ => Err(format!("unknown command `{}`", name)),
+        }
+    }
+}
+
+/// A parsed command waiting for the event loop to apply it.
+pub struct Request {
+    pub command: Command,
+    reply: mpsc::Sender<String>,
+}
+
+impl Request {
+    /// Sends the response line back to the connection that asked.
+    pub fn reply(self, result: Result<(), String>) {
+        let line = match result {
+            Ok(()) => "ok".to_string(),
+            Err(err) => format!("error: {}", err),
+        };
+        // The client may have hung up already.
+        let _ = self.reply.send(line);
+    }
+}
+
+/// Listens on `127.0.0.1:port` and posts every command received as a
+/// `UserEvent::Control`, answering each line with `ok` or `error: ...`
+/// once the event loop has applied it.
+pub fn listen(port: u16, events_proxy: EventLoopProxy<UserEvent>) -> Result<(), String> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .map_err(|err| format!("can't listen on port {}: {}", port, err))?;
+    info!("control socket listening on 127.0.0.1:{}", port);
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let events_proxy = events_proxy.clone();
+                    thread::spawn(move || serve(stream, events_proxy));
+                }
+                Err(err) => warn!("control connection failed: {}", err),
+            }
+        }
+    });
+    Ok(())
+}
+
+fn serve(stream: TcpStream, events_proxy: EventLoopProxy<UserEvent>) {
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(err) => {
+            error!("control connection failed: {}", err);
+            return;
+        }
+    };
+
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match Command::parse(&line) {
+            Ok(command) => {
+                let (reply, response) = mpsc::channel();
+                let request = Request { command, reply };
+                if events_proxy
+                    .send_event(UserEvent::Control(request))
+                    .is_err()
+                {
+                    // The event loop is gone.
+                    return;
+                }
+                match response.recv() {
+                    Ok(response) => response,
+                    Err(_) => return,
+                }
+            }
+            Err(err) => format!("error: {}", err),
+        };
+        if writeln!(writer, "{}", response).is_err() {
+            return;
+        }
+    }
+}
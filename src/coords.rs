@@ -0,0 +1,402 @@
+use webrender::api::units::{
+    DeviceIntPoint, DeviceIntRect, DeviceIntSize, DevicePoint, LayoutPoint, LayoutRect, LayoutSize,
+};
+
+use crate::view::View;
+
+/// The `--clip-rect` area, either fixed or relative to the window size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClipRect {
+    /// In layout pixels.
+    Layout(LayoutRect),
+    /// In fractions of the window, so it follows resizes.
+    Relative(LayoutRect),
+}
+
+impl ClipRect {
+    /// Parses `x,y,w,h` in layout pixels, or with every value a percentage
+    /// of the window like `10%,10%,80%,80%`.
+    pub fn parse(s: &str) -> Result<ClipRect, String> {
+        let invalid = || {
+            format!(
+                "invalid clip rect `{}`, expected x,y,w,h in pixels or all in percent",
+                s
+            )
+        };
+        let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+        if parts.len() != 4 {
+            return Err(invalid());
+        }
+        let relative = parts.iter().all(|part| part.ends_with('%'));
+        let mut values = [0.0; 4];
+        for (value, part) in values.iter_mut().zip(&parts) {
+            let number = if relative {
+                part.trim_end_matches('%')
+            } else {
+                part
+            };
+            *value = match number.parse::<f32>() {
+                Ok(number) if number.is_finite() => number,
+                _ => return Err(invalid()),
+            };
+        }
+        if values[2] <= 0.0 || values[3] <= 0.0 {
+            return Err(invalid());
+        }
+
+        if relative {
+            let [x, y, w, h] = values.map(|value| value / 100.0);
+            Ok(ClipRect::Relative(LayoutRect::from_origin_and_size(
+                LayoutPoint::new(x, y),
+                LayoutSize::new(w, h),
+            )))
+        } else {
+            let [x, y, w, h] = values;
+            Ok(ClipRect::Layout(LayoutRect::from_origin_and_size(
+                LayoutPoint::new(x, y),
+                LayoutSize::new(w, h),
+            )))
+        }
+    }
+
+    /// The clip in layout pixels for a window of `layout_size`.
+    pub fn resolve(self, layout_size: LayoutSize) -> LayoutRect {
+        match self {
+            ClipRect::Layout(rect) => rect,
+            ClipRect::Relative(rect) => rect.scale(layout_size.width, layout_size.height),
+        }
+    }
+}
+
+/// Converts between the coordinate spaces of the demo:
+///
+/// - device pixels, as reported by the window and used for readback;
+/// - layout pixels, device pixels divided by the scale factor, which is
+///   what webrender calls world space for the root pipeline and what hit
+///   testing takes;
+/// - design pixels, the `--design-size` area scaled uniformly to fit the
+///   window and centered in it, or just layout pixels without one;
+/// - world coordinates, the layout space of the scene model, which the
+///   view's pan and zoom map into design pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CoordinateConverter {
+    pub device_size: DeviceIntSize,
+    pub scale_factor: f32,
+    pub view: View,
+    pub design_size: Option<LayoutSize>,
+}
+
+impl CoordinateConverter {
+    pub fn new(device_size: DeviceIntSize, scale_factor: f32) -> CoordinateConverter {
+        CoordinateConverter {
+            device_size,
+            scale_factor,
+            view: View::default(),
+            design_size: None,
+        }
+    }
+
+    /// The window size in layout pixels.
+    pub fn layout_size(&self) -> LayoutSize {
+        self.device_size_to_layout(self.device_size)
+    }
+
+    /// The size the scene is laid out at: the design size, or the window's
+    /// without one.
+    pub fn scene_size(&self) -> LayoutSize {
+        self.design_size.unwrap_or_else(|| self.layout_size())
+    }
+
+    /// Maps design pixels to layout pixels, scaling the design area to fit
+    /// the window and centering it between bars along the other axis.
+    pub fn letterbox(&self) -> View {
+        let design = match self.design_size {
+            Some(design) => design,
+            None => return View::default(),
+        };
+        let window = self.layout_size();
+        let zoom = (window.width / design.width).min(window.height / design.height);
+        View {
+            pan: ((window - design * zoom) / 2.0).to_vector(),
+            zoom,
+        }
+    }
+
+    /// Maps world coordinates to layout pixels, through the view and then
+    /// the letterbox.
+    pub fn world_to_layout(&self) -> View {
+        self.view.then(self.letterbox())
+    }
+
+    pub fn device_size_to_layout(&self, size: DeviceIntSize) -> LayoutSize {
+        size.to_f32().cast_unit() / self.scale_factor
+    }
+
+    pub fn device_to_layout(&self, point: DeviceIntPoint) -> LayoutPoint {
+        point.to_f32().cast_unit() / self.scale_factor
+    }
+
+    pub fn layout_to_device(&self, point: LayoutPoint) -> DevicePoint {
+        (point * self.scale_factor).cast_unit()
+    }
+
+    pub fn layout_to_design(&self, point: LayoutPoint) -> LayoutPoint {
+        self.letterbox().to_scene(point)
+    }
+
+    /// The inverse is `world_to_layout`, which webrender applies.
+    pub fn layout_to_world(&self, point: LayoutPoint) -> LayoutPoint {
+        self.view.to_scene(self.layout_to_design(point))
+    }
+
+    /// Moves `point` to the nearest device pixel corner.
+    pub fn snap_to_device(&self, point: LayoutPoint) -> LayoutPoint {
+        self.layout_to_device(point).round().cast_unit() / self.scale_factor
+    }
+
+    /// Moves `point`, in design pixels, to where the nearest device pixel
+    /// corner is in the letterboxed design area.
+    pub fn snap_design_to_device(&self, point: LayoutPoint) -> LayoutPoint {
+        let letterbox = self.letterbox();
+        letterbox.to_scene(self.snap_to_device(letterbox.to_layout(point)))
+    }
+
+    /// Rounds a line width to whole device pixels, at least one.
+    pub fn snap_line_width(&self, width: f32) -> f32 {
+        (width * self.scale_factor).round().max(1.0) / self.scale_factor
+    }
+
+    /// Moves the center of a line `width` wide, as returned by
+    /// `snap_line_width`, so that the line covers whole device pixels:
+    /// lines an odd number of device pixels wide are centered on a pixel
+    /// center, even ones on a pixel edge.
+    pub fn snap_line_center(&self, center: f32, width: f32) -> f32 {
+        let device_width = (width * self.scale_factor).round();
+        let device_center = center * self.scale_factor;
+        let snapped = if device_width % 2.0 == 1.0 {
+            device_center.floor() + 0.5
+        } else {
+            device_center.round()
+        };
+        snapped / self.scale_factor
+    }
+
+    pub fn device_rect_to_layout(&self, rect: DeviceIntRect) -> LayoutRect {
+        let rect: LayoutRect = rect.to_f32().cast_unit();
+        rect.scale(1.0 / self.scale_factor, 1.0 / self.scale_factor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use webrender::api::units::LayoutVector2D;
+
+    use super::*;
+
+    const EPSILON: f32 = 1e-3;
+
+    const SCALES: [f32; 5] = [1.0, 1.25, 1.5, 2.0, 3.0];
+
+    fn assert_close(a: LayoutPoint, b: LayoutPoint) {
+        assert!((a - b).length() < EPSILON, "{:?} is not {:?}", a, b);
+    }
+
+    /// A window of an odd device size at `scale`, panned and zoomed.
+    fn converter(scale: f32, design_size: Option<LayoutSize>) -> CoordinateConverter {
+        CoordinateConverter {
+            view: View {
+                pan: LayoutVector2D::new(-37.5, 12.25),
+                zoom: 2.5,
+            },
+            design_size,
+            ..CoordinateConverter::new(DeviceIntSize::new(1023, 767), scale)
+        }
+    }
+
+    #[test]
+    fn device_to_layout_round_trips() {
+        for scale in SCALES {
+            let coords = converter(scale, None);
+            for point in [(0, 0), (1, 1), (511, 383), (1023, 767)] {
+                let device = DeviceIntPoint::new(point.0, point.1);
+                let back = coords.layout_to_device(coords.device_to_layout(device));
+                assert!(
+                    (back - device.to_f32()).length() < EPSILON,
+                    "{:?} came back as {:?} at scale {}",
+                    device,
+                    back,
+                    scale
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn layout_size_covers_the_device_size() {
+        for scale in SCALES {
+            let coords = converter(scale, None);
+            let layout = coords.layout_size();
+            let device = coords.layout_to_device(LayoutPoint::new(layout.width, layout.height));
+            assert!((device.x - 1023.0).abs() < EPSILON, "scale {}", scale);
+            assert!((device.y - 767.0).abs() < EPSILON, "scale {}", scale);
+        }
+    }
+
+    #[test]
+    fn layout_to_world_round_trips_through_pan_and_zoom() {
+        for scale in SCALES {
+            for design_size in [None, Some(LayoutSize::new(1280.0, 720.0))] {
+                let coords = converter(scale, design_size);
+                for point in [(0.0, 0.0), (100.5, 33.25), (511.5, 383.5)] {
+                    let layout = LayoutPoint::new(point.0, point.1);
+                    let world = coords.layout_to_world(layout);
+                    assert_close(coords.world_to_layout().to_layout(world), layout);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn device_to_world_round_trips() {
+        for scale in SCALES {
+            let coords = converter(scale, Some(LayoutSize::new(800.0, 600.0)));
+            let device = DeviceIntPoint::new(700, 301);
+            let world = coords.layout_to_world(coords.device_to_layout(device));
+            let layout = coords.world_to_layout().to_layout(world);
+            let back = coords.layout_to_device(layout);
+            assert!(
+                (back - device.to_f32()).length() < EPSILON,
+                "scale {}",
+                scale
+            );
+        }
+    }
+
+    #[test]
+    fn device_rects_convert_like_their_corners() {
+        for scale in SCALES {
+            let coords = converter(scale, None);
+            let rect = DeviceIntRect::new(DeviceIntPoint::new(3, 5), DeviceIntPoint::new(101, 77));
+            let layout = coords.device_rect_to_layout(rect);
+            assert_close(layout.min, coords.device_to_layout(rect.min));
+            assert_close(layout.max, coords.device_to_layout(rect.max));
+        }
+    }
+
+    #[test]
+    fn snapped_points_land_on_device_pixels() {
+        for scale in SCALES {
+            let coords = converter(scale, None);
+            let snapped = coords.snap_to_device(LayoutPoint::new(10.3, 20.7));
+            let device = coords.layout_to_device(snapped);
+            assert!(
+                (device.x - device.x.round()).abs() < EPSILON,
+                "scale {}",
+                scale
+            );
+            assert!(
+                (device.y - device.y.round()).abs() < EPSILON,
+                "scale {}",
+                scale
+            );
+        }
+    }
+
+    /// Scales the line snapping is checked at.
+    const LINE_SCALES: [f32; 4] = [1.0, 1.25, 1.5, 2.0];
+
+    #[test]
+    fn line_widths_round_to_whole_device_pixels() {
+        // Scale, layout width, and the device pixels it should cover.
+        let table = [
+            (1.0, 1.0, 1.0),
+            (1.0, 0.3, 1.0),
+            (1.0, 1.6, 2.0),
+            (1.0, 2.4, 2.0),
+            (1.25, 1.0, 1.0),
+            (1.25, 2.0, 3.0),
+            (1.25, 0.2, 1.0),
+            (1.5, 1.0, 2.0),
+            (1.5, 3.0, 5.0),
+            (1.5, 0.5, 1.0),
+            (2.0, 1.0, 2.0),
+            (2.0, 0.5, 1.0),
+            (2.0, 0.2, 1.0),
+        ];
+        for (scale, width, device_width) in table {
+            let coords = CoordinateConverter::new(DeviceIntSize::new(1023, 767), scale);
+            let snapped = coords.snap_line_width(width);
+            assert!(
+                (snapped * scale - device_width).abs() < EPSILON,
+                "{} wide at scale {} snapped to {} device pixels, not {}",
+                width,
+                scale,
+                snapped * scale,
+                device_width
+            );
+        }
+    }
+
+    #[test]
+    fn line_centers_snap_by_the_width_in_device_pixels() {
+        // Scale, layout center, width in device pixels, and the device
+        // position the center should snap to: odd widths on a pixel's
+        // center, even ones on an edge.
+        let table = [
+            (1.0, 10.3, 1.0, 10.5),
+            (1.0, 10.7, 1.0, 10.5),
+            (1.0, 10.3, 2.0, 10.0),
+            (1.0, 10.6, 2.0, 11.0),
+            (1.25, 10.0, 1.0, 12.5),
+            (1.25, 10.3, 1.0, 12.5),
+            (1.25, 10.3, 2.0, 13.0),
+            (1.5, 10.0, 2.0, 15.0),
+            (1.5, 10.2, 2.0, 15.0),
+            (1.5, 10.2, 1.0, 15.5),
+            // On a pixel edge already, but an odd width has to move half a
+            // pixel to cover whole pixels.
+            (1.5, 10.0, 3.0, 15.5),
+            (2.0, 10.0, 1.0, 20.5),
+            (2.0, 10.3, 1.0, 20.5),
+            (2.0, 10.3, 2.0, 21.0),
+            (2.0, 10.25, 3.0, 20.5),
+        ];
+        for (scale, center, device_width, device_center) in table {
+            let coords = CoordinateConverter::new(DeviceIntSize::new(1023, 767), scale);
+            let snapped = coords.snap_line_center(center, device_width / scale);
+            assert!(
+                (snapped * scale - device_center).abs() < EPSILON,
+                "{} at scale {} and {} device pixels wide snapped to {}, not {}",
+                center,
+                scale,
+                device_width,
+                snapped * scale,
+                device_center
+            );
+        }
+    }
+
+    #[test]
+    fn snapped_lines_cover_whole_device_pixels() {
+        for scale in LINE_SCALES {
+            let coords = CoordinateConverter::new(DeviceIntSize::new(1023, 767), scale);
+            for width in [0.2, 0.5, 1.0, 1.5, 2.0, 3.7] {
+                let width = coords.snap_line_width(width);
+                for center in [0.0, 10.1, 10.25, 33.5, 99.9] {
+                    let center = coords.snap_line_center(center, width);
+                    for edge in [center - width / 2.0, center + width / 2.0] {
+                        let device = edge * scale;
+                        assert!(
+                            (device - device.round()).abs() < EPSILON,
+                            "line {} wide at {} has an edge at {} at scale {}",
+                            width,
+                            center,
+                            device,
+                            scale
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use webrender::api::{
+    units::{LayoutPoint, LayoutRect, LayoutSize, LayoutTransform, LayoutVector2D},
+    ColorF, CommonItemProperties, DisplayListBuilder, PropertyBinding, PropertyBindingKey,
+    PropertyValue, ReferenceFrameKind, SpaceAndClipInfo, SpatialTreeItemKey, TransformStyle,
+};
+
+use crate::{
+    coords::CoordinateConverter,
+    text::{push_text, text_size},
+};
+
+/// Size of the font cells, in layout pixels.
+const TEXT_CELL: f32 = 2.0;
+
+/// Space between the label's edge and its text.
+const PADDING: f32 = 4.0;
+
+/// Distance from the cursor to the label's nearest corner.
+const CURSOR_OFFSET: f32 = 16.0;
+
+const FILL_COLOR: ColorF = ColorF {
+    r: 0.0,
+    g: 0.0,
+    b: 0.0,
+    a: 0.7,
+};
+
+/// The `--coords` label next to the cursor, showing where it is in window
+/// layout pixels and, when the view is panned, zoomed or letterboxed, in
+/// world space too.
+///
+/// The label is drawn at the origin of a reference frame whose transform
+/// is a property binding, so following the cursor only updates that
+/// binding. The display list is only rebuilt when the text itself changes.
+pub struct CursorReadout {
+    key: PropertyBindingKey<LayoutTransform>,
+    /// The cursor, in window layout pixels, once it has been seen.
+    pointer: Option<LayoutPoint>,
+    /// The text in the last display list.
+    built: String,
+}
+
+impl CursorReadout {
+    pub fn new(key: PropertyBindingKey<LayoutTransform>) -> CursorReadout {
+        CursorReadout {
+            key,
+            pointer: None,
+            built: String::new(),
+        }
+    }
+
+    pub fn set_pointer(&mut self, point: LayoutPoint) {
+        self.pointer = Some(point);
+    }
+
+    /// Whether the text for the cursor differs from the one in the display
+    /// list, so that moving the label isn't enough.
+    pub fn needs_rebuild(&self, coords: &CoordinateConverter) -> bool {
+        self.label(coords) != self.built
+    }
+
+    fn label(&self, coords: &CoordinateConverter) -> String {
+        let point = match self.pointer {
+            Some(point) => point,
+            None => return String::new(),
+        };
+        let world = coords.layout_to_world(point);
+        let mut label = format!("{:.0}, {:.0}", point.x, point.y);
+        if (world - point).square_length() > 1e-6 {
+            label += &format!(" world {:.1}, {:.1}", world.x, world.y);
+        }
+        label
+    }
+
+    /// The label's size, text and padding included.
+    fn size(&self) -> LayoutSize {
+        text_size(&self.built, TEXT_CELL) + LayoutSize::new(PADDING, PADDING) * 2.0
+    }
+
+    /// Puts the label below and to the right of the cursor, or on the other
+    /// side where it would run off the window.
+    pub fn binding(&self, coords: &CoordinateConverter) -> PropertyValue<LayoutTransform> {
+        let transform = match self.pointer {
+            Some(point) => {
+                let size = self.size();
+                let window = coords.layout_size();
+                let mut origin = point + LayoutVector2D::new(CURSOR_OFFSET, CURSOR_OFFSET);
+                if origin.x + size.width > window.width {
+                    origin.x = point.x - CURSOR_OFFSET - size.width;
+                }
+                if origin.y + size.height > window.height {
+                    origin.y = point.y - CURSOR_OFFSET - size.height;
+                }
+                LayoutTransform::translation(origin.x, origin.y, 0.0)
+            }
+            None => LayoutTransform::scale(0.0, 0.0, 1.0),
+        };
+        self.key.with(transform)
+    }
+
+    /// Pushes the label with the text for the cursor now, its position
+    /// bound to the readout's key unless `bind` is false.
+    pub fn push(
+        &mut self,
+        builder: &mut DisplayListBuilder,
+        coords: &CoordinateConverter,
+        bind: bool,
+        space_and_clip: SpaceAndClipInfo,
+    ) {
+        self.built = self.label(coords);
+        let PropertyValue { key, value } = self.binding(coords);
+        let frame = builder.push_reference_frame(
+            LayoutPoint::zero(),
+            space_and_clip.spatial_id,
+            TransformStyle::Flat,
+            if bind {
+                PropertyBinding::Binding(key, value)
+            } else {
+                PropertyBinding::Value(value)
+            },
+            ReferenceFrameKind::Transform {
+                is_2d_scale_translation: true,
+                should_snap: false,
+                paired_with_perspective: false,
+            },
+            SpatialTreeItemKey::new(0, 3),
+        );
+        let space_and_clip = SpaceAndClipInfo {
+            spatial_id: frame,
+            clip_id: space_and_clip.clip_id,
+        };
+        let bounds = LayoutRect::from_size(self.size());
+        builder.push_rect(
+            &CommonItemProperties::new(bounds, space_and_clip),
+            bounds,
+            FILL_COLOR,
+        );
+        push_text(
+            builder,
+            LayoutPoint::new(PADDING, PADDING),
+            &self.built,
+            TEXT_CELL,
+            ColorF::WHITE,
+            space_and_clip,
+        );
+        builder.pop_reference_frame();
+    }
+}
@@ -0,0 +1,352 @@
+use std::{fmt::Write, path::Path};
+
+use serde_json::Value;
+use webrender::api::{
+    units::LayoutSize, DisplayItem, DisplayListBuilder, PipelineId, SpaceAndClipInfo,
+};
+
+use crate::{clip_debug::ClipLog, scene_file};
+
+/// Size of the scene percentages are taken of, there being no window.
+const SCENE_SIZE: (f32, f32) = (1280.0, 720.0);
+
+/// Largest product of the two lists' lengths aligned by their longest
+/// common subsequence. Longer lists are compared index by index instead,
+/// which reports more changes but needs no table.
+const MAX_ALIGNMENT_CELLS: usize = 4_000_000;
+
+/// A display item flattened into its variant name and every leaf field,
+/// keyed by its dotted path, like `color.Value.r`.
+#[derive(Clone, Debug, PartialEq)]
+struct Item {
+    kind: String,
+    fields: Vec<(String, String)>,
+}
+
+impl Item {
+    fn new(item: &DisplayItem) -> Item {
+        let value = serde_json::to_value(item).unwrap_or(Value::Null);
+        let mut fields = Vec::new();
+        match value {
+            // Variants with data serialize as `{"Variant": data}`.
+            Value::Object(map) if map.len() == 1 => {
+                let (kind, data) = map.into_iter().next().unwrap();
+                flatten("", &data, &mut fields);
+                Item { kind, fields }
+            }
+            Value::String(kind) => Item { kind, fields },
+            other => Item {
+                kind: other.to_string(),
+                fields,
+            },
+        }
+    }
+
+    /// The item on one line, as `kind field=value ...`.
+    fn summary(&self) -> String {
+        let mut line = self.kind.clone();
+        for (path, value) in &self.fields {
+            let _ = write!(line, " {}={}", path, value);
+        }
+        line
+    }
+}
+
+fn flatten(path: &str, value: &Value, fields: &mut Vec<(String, String)>) {
+    let join = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                flatten(&join(key), value, fields);
+            }
+        }
+        Value::Array(values) => {
+            for (i, value) in values.iter().enumerate() {
+                flatten(&join(&i.to_string()), value, fields);
+            }
+        }
+        leaf => fields.push((path.to_string(), leaf.to_string())),
+    }
+}
+
+/// Builds the display list `SceneModel::push` makes for the scene at `path`,
+/// on its own with none of the demo's overlays, and flattens its items.
+fn scene_items(path: &Path) -> Result<Vec<Item>, String> {
+    let (width, height) = SCENE_SIZE;
+    let model = scene_file::load(path)?.to_model(LayoutSize::new(width, height));
+    let pipeline_id = PipelineId(0, 0);
+    let mut builder = DisplayListBuilder::new(pipeline_id);
+    builder.begin();
+    model.push(
+        &mut builder,
+        false,
+        &mut ClipLog::default(),
+        None,
+        SpaceAndClipInfo::root_scroll(pipeline_id),
+    );
+    let (_, list) = builder.end();
+
+    let mut items = Vec::new();
+    let mut iter = list.iter();
+    while let Some(item) = iter.next() {
+        items.push(Item::new(item.item()));
+    }
+    Ok(items)
+}
+
+/// One step of the alignment between the two lists.
+enum Step {
+    Same,
+    Removed(usize),
+    Added(usize),
+}
+
+/// Aligns `a` and `b` on their longest run of identical items in order, so
+/// that an inserted item shows up as one addition rather than changing
+/// every item after it.
+fn align(a: &[Item], b: &[Item]) -> Vec<Step> {
+    if a.len().saturating_mul(b.len()) > MAX_ALIGNMENT_CELLS {
+        let mut steps = Vec::new();
+        for i in 0..a.len().max(b.len()) {
+            match (a.get(i), b.get(i)) {
+                (Some(x), Some(y)) if x == y => steps.push(Step::Same),
+                (x, y) => {
+                    if x.is_some() {
+                        steps.push(Step::Removed(i));
+                    }
+                    if y.is_some() {
+                        steps.push(Step::Added(i));
+                    }
+                }
+            }
+        }
+        return steps;
+    }
+
+    // `lengths[i][j]` is the length of the longest common subsequence of
+    // `a[i..]` and `b[j..]`.
+    let width = b.len() + 1;
+    let mut lengths = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i * width + j] = if a[i] == b[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut steps = Vec::new();
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            steps.push(Step::Same);
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            steps.push(Step::Removed(i));
+            i += 1;
+        } else {
+            steps.push(Step::Added(j));
+            j += 1;
+        }
+    }
+    steps.extend((i..a.len()).map(Step::Removed));
+    steps.extend((j..b.len()).map(Step::Added));
+    steps
+}
+
+/// Loads both scenes, builds their display lists and describes how the
+/// second differs from the first, item by item and field by field, ending
+/// with a count of each kind of change.
+pub fn diff(a_path: &Path, b_path: &Path) -> Result<String, String> {
+    let a = scene_items(a_path)?;
+    let b = scene_items(b_path)?;
+
+    let mut report = format!(
+        "--- {} ({} items)\n+++ {} ({} items)\n",
+        a_path.display(),
+        a.len(),
+        b_path.display(),
+        b.len()
+    );
+    describe_items(&mut report, &a, &b);
+    Ok(report)
+}
+
+/// Appends to `report` how `b` differs from `a`, and the counts.
+fn describe_items(report: &mut String, a: &[Item], b: &[Item]) {
+    let steps = align(a, b);
+    let (mut added, mut removed, mut changed, mut same) = (0, 0, 0, 0);
+    // Items removed and added between two unchanged ones are paired up in
+    // order: a pair of the same kind is one item with some fields changed.
+    let (mut removed_run, mut added_run) = (Vec::new(), Vec::new());
+    for step in steps.iter().map(Some).chain([None]) {
+        match step {
+            Some(&Step::Removed(i)) => {
+                removed_run.push(i);
+                continue;
+            }
+            Some(&Step::Added(j)) => {
+                added_run.push(j);
+                continue;
+            }
+            Some(Step::Same) => same += 1,
+            None => (),
+        }
+
+        let pairs = removed_run.len().max(added_run.len());
+        for k in 0..pairs {
+            match (removed_run.get(k), added_run.get(k)) {
+                (Some(&i), Some(&j)) if a[i].kind == b[j].kind => {
+                    changed += 1;
+                    describe_change(report, i, j, &a[i], &b[j]);
+                }
+                (i, j) => {
+                    if let Some(&i) = i {
+                        removed += 1;
+                        let _ = writeln!(report, "- {} {}", i, a[i].summary());
+                    }
+                    if let Some(&j) = j {
+                        added += 1;
+                        let _ = writeln!(report, "+ {} {}", j, b[j].summary());
+                    }
+                }
+            }
+        }
+        removed_run.clear();
+        added_run.clear();
+    }
+    let _ = writeln!(
+        report,
+        "{} added, {} removed, {} changed, {} unchanged",
+        added, removed, changed, same
+    );
+}
+
+fn describe_change(report: &mut String, i: usize, j: usize, a: &Item, b: &Item) {
+    if i == j {
+        let _ = writeln!(report, "~ {} {}", i, a.kind);
+    } else {
+        let _ = writeln!(report, "~ {} -> {} {}", i, j, a.kind);
+    }
+    let value_of = |fields: &[(String, String)], path: &str| {
+        fields
+            .iter()
+            .find(|(p, _)| p == path)
+            .map(|(_, value)| value.clone())
+    };
+    for (path, old) in &a.fields {
+        match value_of(&b.fields, path) {
+            Some(new) if new == *old => (),
+            Some(new) => {
+                let _ = writeln!(report, "    {}: {} -> {}", path, old, new);
+            }
+            None => {
+                let _ = writeln!(report, "    {}: {} -> (none)", path, old);
+            }
+        }
+    }
+    for (path, new) in &b.fields {
+        if value_of(&a.fields, path).is_none() {
+            let _ = writeln!(report, "    {}: (none) -> {}", path, new);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(kind: &str, fields: &[(&str, &str)]) -> Item {
+        Item {
+            kind: kind.to_string(),
+            fields: fields
+                .iter()
+                .map(|&(path, value)| (path.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    fn rect(red: &str) -> Item {
+        item(
+            "Rectangle",
+            &[("color.Value.r", red), ("bounds.min.x", "0.0")],
+        )
+    }
+
+    fn describe(a: &[Item], b: &[Item]) -> String {
+        let mut report = String::new();
+        describe_items(&mut report, a, b);
+        report
+    }
+
+    #[test]
+    fn unchanged_items_are_only_counted() {
+        let items = [rect("1.0"), item("PopStackingContext", &[])];
+        assert_eq!(
+            describe(&items, &items),
+            "0 added, 0 removed, 0 changed, 2 unchanged\n"
+        );
+    }
+
+    #[test]
+    fn an_added_item_leaves_the_rest_unchanged() {
+        let a = [rect("1.0"), rect("0.0")];
+        let b = [rect("1.0"), item("HitTest", &[("tag", "1")]), rect("0.0")];
+        assert_eq!(
+            describe(&a, &b),
+            "+ 1 HitTest tag=1\n1 added, 0 removed, 0 changed, 2 unchanged\n"
+        );
+    }
+
+    #[test]
+    fn a_removed_item_leaves_the_rest_unchanged() {
+        let a = [rect("1.0"), item("HitTest", &[("tag", "1")]), rect("0.0")];
+        let b = [rect("1.0"), rect("0.0")];
+        assert_eq!(
+            describe(&a, &b),
+            "- 1 HitTest tag=1\n0 added, 1 removed, 0 changed, 2 unchanged\n"
+        );
+    }
+
+    #[test]
+    fn an_item_of_the_same_kind_is_changed_field_by_field() {
+        let a = [rect("1.0"), rect("1.0")];
+        let b = [rect("1.0"), rect("0.5")];
+        assert_eq!(
+            describe(&a, &b),
+            "~ 1 Rectangle\n    color.Value.r: 1.0 -> 0.5\n\
+             0 added, 0 removed, 1 changed, 1 unchanged\n"
+        );
+    }
+
+    #[test]
+    fn fields_only_one_item_has_are_shown_as_none() {
+        let a = [item("Rectangle", &[("clip", "1")])];
+        let b = [item("Rectangle", &[("spatial", "2")])];
+        assert_eq!(
+            describe(&a, &b),
+            "~ 0 Rectangle\n    clip: 1 -> (none)\n    spatial: (none) -> 2\n\
+             0 added, 0 removed, 1 changed, 0 unchanged\n"
+        );
+    }
+
+    #[test]
+    fn an_item_of_another_kind_is_removed_and_added() {
+        let a = [rect("1.0"), rect("0.0")];
+        let b = [rect("1.0"), item("Border", &[("width", "4")])];
+        assert_eq!(
+            describe(&a, &b),
+            "- 1 Rectangle color.Value.r=0.0 bounds.min.x=0.0\n+ 1 Border width=4\n\
+             1 added, 1 removed, 0 changed, 1 unchanged\n"
+        );
+    }
+}
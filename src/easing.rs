@@ -0,0 +1,188 @@
+/// Iterations of Newton's method `cubic_bezier` tries before bisecting.
+const NEWTON_ITERATIONS: usize = 8;
+
+/// How close, in t, `cubic_bezier` gets to the curve's x.
+const BEZIER_EPSILON: f32 = 1e-5;
+
+/// Goes at a constant speed.
+pub fn linear(t: f32) -> f32 {
+    t.clamp(0.0, 1.0)
+}
+
+/// Starts slowly and ends at full speed.
+pub fn ease_in(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    t * t
+}
+
+/// Starts at full speed and ends slowly.
+pub fn ease_out(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    t * (2.0 - t)
+}
+
+/// Starts and ends slowly, for animations between two rest positions.
+pub fn ease_in_out(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// The CSS `cubic-bezier()` timing function: the curve from (0, 0) to
+/// (1, 1) with control points (`p1x`, `p1y`) and (`p2x`, `p2y`), evaluated
+/// at x = `t`. The x coordinates must be between 0 and 1 so that the curve
+/// has a single y for every x.
+pub fn cubic_bezier(p1x: f32, p1y: f32, p2x: f32, p2y: f32, t: f32) -> f32 {
+    let x = t.clamp(0.0, 1.0);
+    // Coefficients of the polynomial form of each coordinate.
+    let coefficients = |p1: f32, p2: f32| {
+        let c = 3.0 * p1;
+        let b = 3.0 * (p2 - p1) - c;
+        (1.0 - c - b, b, c)
+    };
+    let (ax, bx, cx) = coefficients(p1x, p2x);
+    let (ay, by, cy) = coefficients(p1y, p2y);
+    let sample_x = |s: f32| ((ax * s + bx) * s + cx) * s;
+    let slope_x = |s: f32| (3.0 * ax * s + 2.0 * bx) * s + cx;
+
+    // Find the curve parameter `s` whose x is `x`, by Newton's method where
+    // it converges and by bisection, which always does, where it doesn't.
+    let mut s = x;
+    let mut solved = false;
+    for _ in 0..NEWTON_ITERATIONS {
+        let error = sample_x(s) - x;
+        if error.abs() < BEZIER_EPSILON {
+            solved = true;
+            break;
+        }
+        let slope = slope_x(s);
+        if slope.abs() < BEZIER_EPSILON {
+            break;
+        }
+        s -= error / slope;
+    }
+    if !solved || !(0.0..=1.0).contains(&s) {
+        let (mut low, mut high) = (0.0, 1.0);
+        s = x;
+        while high - low > BEZIER_EPSILON {
+            if sample_x(s) < x {
+                low = s;
+            } else {
+                high = s;
+            }
+            s = (low + high) / 2.0;
+        }
+    }
+    ((ay * s + by) * s + cy) * s
+}
+
+/// An easing function picked by name on the command line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    CubicBezier(f32, f32, f32, f32),
+}
+
+impl Easing {
+    /// Parses `linear`, `ease-in`, `ease-out`, `ease-in-out` or
+    /// `cubic-bezier(x1,y1,x2,y2)` with both x between 0 and 1.
+    pub fn from_name(name: &str) -> Option<Easing> {
+        match name {
+            "linear" => return Some(Easing::Linear),
+            "ease-in" => return Some(Easing::EaseIn),
+            "ease-out" => return Some(Easing::EaseOut),
+            "ease-in-out" => return Some(Easing::EaseInOut),
+            _ => (),
+        }
+
+        let args = name.strip_prefix("cubic-bezier(")?.strip_suffix(')')?;
+        let values = args
+            .split(',')
+            .map(|value| value.trim().parse::<f32>().ok().filter(|v| v.is_finite()))
+            .collect::<Option<Vec<f32>>>()?;
+        match values[..] {
+            [p1x, p1y, p2x, p2y] if (0.0..=1.0).contains(&p1x) && (0.0..=1.0).contains(&p2x) => {
+                Some(Easing::CubicBezier(p1x, p1y, p2x, p2y))
+            }
+            _ => None,
+        }
+    }
+
+    /// The eased progress at `t`, both running from 0 to 1.
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => linear(t),
+            Easing::EaseIn => ease_in(t),
+            Easing::EaseOut => ease_out(t),
+            Easing::EaseInOut => ease_in_out(t),
+            Easing::CubicBezier(p1x, p1y, p2x, p2y) => cubic_bezier(p1x, p1y, p2x, p2y, t),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Curves whose y control points are between 0 and 1, so they never
+    /// turn back, alongside the named ones.
+    const MONOTONE: [Easing; 8] = [
+        Easing::Linear,
+        Easing::EaseIn,
+        Easing::EaseOut,
+        Easing::EaseInOut,
+        Easing::CubicBezier(0.25, 0.1, 0.25, 1.0),
+        Easing::CubicBezier(0.42, 0.0, 0.58, 1.0),
+        Easing::CubicBezier(0.0, 1.0, 1.0, 0.0),
+        Easing::CubicBezier(1.0, 0.0, 0.0, 1.0),
+    ];
+
+    /// A curve that overshoots, which only has to meet its ends.
+    const OVERSHOOT: Easing = Easing::CubicBezier(0.3, -0.6, 0.7, 1.6);
+
+    const SAMPLES: usize = 1000;
+
+    #[test]
+    fn every_easing_starts_at_0_and_ends_at_1() {
+        for easing in MONOTONE.iter().chain([&OVERSHOOT]) {
+            assert!(easing.apply(0.0).abs() < 1e-5, "{:?}", easing);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-5, "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn monotone_easings_never_go_back() {
+        for easing in MONOTONE {
+            let mut last = easing.apply(0.0);
+            for i in 1..=SAMPLES {
+                let t = i as f32 / SAMPLES as f32;
+                let value = easing.apply(t);
+                // The bezier solver is only exact to within its epsilon.
+                assert!(value >= last - 1e-4, "{:?} went back at t = {}", easing, t);
+                last = value;
+            }
+        }
+    }
+
+    #[test]
+    fn clamps_t_outside_0_to_1() {
+        for easing in MONOTONE.iter().chain([&OVERSHOOT]) {
+            assert_eq!(easing.apply(-0.5), easing.apply(0.0), "{:?}", easing);
+            assert_eq!(easing.apply(1.5), easing.apply(1.0), "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn parses_names() {
+        assert_eq!(Easing::from_name("ease-in-out"), Some(Easing::EaseInOut));
+        assert_eq!(
+            Easing::from_name("cubic-bezier(0.25, 0.1, 0.25, 1)"),
+            Some(Easing::CubicBezier(0.25, 0.1, 0.25, 1.0))
+        );
+        assert_eq!(Easing::from_name("cubic-bezier(1.5, 0, 0, 1)"), None);
+        assert_eq!(Easing::from_name("cubic-bezier(0, 0, 1)"), None);
+        assert_eq!(Easing::from_name("bounce"), None);
+    }
+}
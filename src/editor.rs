@@ -0,0 +1,268 @@
+use webrender::api::{
+    units::{LayoutPoint, LayoutRect, LayoutSize, LayoutVector2D},
+    ColorF, CommonItemProperties, DisplayListBuilder, ItemTag, SpaceAndClipInfo,
+    SpatialTreeItemKey,
+};
+
+use crate::{
+    hit_map::HitMap,
+    scene::{push_rotation, SceneModel},
+};
+
+/// Drawn size of the resize handles.
+const HANDLE_SIZE: f32 = 8.0;
+
+/// Hit-test size of the resize handles, larger than drawn for tolerance.
+const HANDLE_HIT_SIZE: f32 = 16.0;
+
+/// Rects can't be resized below this in either dimension.
+const MIN_RECT_SIZE: f32 = 8.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomRight,
+    BottomLeft,
+}
+
+impl Corner {
+    const ALL: [Corner; 4] = [
+        Corner::TopLeft,
+        Corner::TopRight,
+        Corner::BottomRight,
+        Corner::BottomLeft,
+    ];
+
+    fn is_right(self) -> bool {
+        matches!(self, Corner::TopRight | Corner::BottomRight)
+    }
+
+    fn is_bottom(self) -> bool {
+        matches!(self, Corner::BottomRight | Corner::BottomLeft)
+    }
+
+    fn opposite(self) -> Corner {
+        Corner::ALL[(self as usize + 2) % 4]
+    }
+
+    fn of(self, rect: &LayoutRect) -> LayoutPoint {
+        let x = if self.is_right() {
+            rect.max.x
+        } else {
+            rect.min.x
+        };
+        let y = if self.is_bottom() {
+            rect.max.y
+        } else {
+            rect.min.y
+        };
+        LayoutPoint::new(x, y)
+    }
+}
+
+/// What a hit-test `ItemTag` pushed by the editor refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    Rect(usize),
+    Handle(usize, Corner),
+}
+
+impl Target {
+    fn tag(self) -> ItemTag {
+        match self {
+            Target::Rect(index) => (index as u64, 0),
+            Target::Handle(index, corner) => (index as u64, corner as u16 + 1),
+        }
+    }
+
+    pub fn from_tag((index, kind): ItemTag) -> Option<Target> {
+        let index = index as usize;
+        match kind {
+            0 => Some(Target::Rect(index)),
+            1..=4 => Some(Target::Handle(index, Corner::ALL[kind as usize - 1])),
+            _ => None,
+        }
+    }
+}
+
+enum Drag {
+    Move {
+        index: usize,
+        last: LayoutPoint,
+    },
+    Resize {
+        index: usize,
+        corner: Corner,
+        anchor: LayoutPoint,
+    },
+}
+
+/// Selection and pointer-driven move/resize of scene rects.
+#[derive(Default)]
+pub struct Editor {
+    selection: Option<usize>,
+    drag: Option<Drag>,
+}
+
+impl Editor {
+    /// Index of the selected rect.
+    pub fn selection(&self) -> Option<usize> {
+        self.selection
+    }
+
+    pub fn clear(&mut self) {
+        self.selection = None;
+        self.drag = None;
+    }
+
+    /// Starts a drag on whatever is under the pointer. Returns whether the
+    /// selection changed.
+    pub fn pointer_down(
+        &mut self,
+        model: &SceneModel,
+        target: Option<Target>,
+        point: LayoutPoint,
+    ) -> bool {
+        let previous = self.selection;
+
+        match target {
+            Some(Target::Handle(index, corner)) if index < model.rects.len() => {
+                let anchor = corner.opposite().of(&model.rects[index].bounds);
+                self.drag = Some(Drag::Resize {
+                    index,
+                    corner,
+                    anchor,
+                });
+            }
+            Some(Target::Rect(index)) if index < model.rects.len() => {
+                self.selection = Some(index);
+                self.drag = Some(Drag::Move { index, last: point });
+            }
+            _ => self.clear(),
+        }
+
+        self.selection != previous
+    }
+
+    /// Applies an in-progress drag. Returns whether `model` changed.
+    pub fn pointer_moved(&mut self, model: &mut SceneModel, point: LayoutPoint) -> bool {
+        match self.drag {
+            Some(Drag::Move {
+                index,
+                ref mut last,
+            }) => {
+                let rect = &mut model.rects[index];
+                rect.bounds = rect.bounds.translate(point - *last);
+                *last = point;
+                true
+            }
+            Some(Drag::Resize {
+                index,
+                corner,
+                anchor,
+            }) => {
+                let x = if corner.is_right() {
+                    point.x.max(anchor.x + MIN_RECT_SIZE)
+                } else {
+                    point.x.min(anchor.x - MIN_RECT_SIZE)
+                };
+                let y = if corner.is_bottom() {
+                    point.y.max(anchor.y + MIN_RECT_SIZE)
+                } else {
+                    point.y.min(anchor.y - MIN_RECT_SIZE)
+                };
+                let point = LayoutPoint::new(x, y);
+                model.rects[index].bounds = LayoutRect::new(point.min(anchor), point.max(anchor));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn pointer_up(&mut self) {
+        self.drag = None;
+    }
+
+    /// Pushes hit-test items for every visible rect, then the handles of
+    /// the selected one on top.
+    pub fn push(
+        &self,
+        builder: &mut DisplayListBuilder,
+        model: &SceneModel,
+        hits: &mut HitMap,
+        space_and_clip: SpaceAndClipInfo,
+    ) {
+        for (index, rect) in model.rects.iter().enumerate() {
+            if !model.is_visible(rect) {
+                continue;
+            }
+            // Turned rects are hit tested in a frame turned like the one
+            // they are drawn in, which webrender's hit testing follows.
+            let rotated = push_rotation(
+                builder,
+                rect,
+                space_and_clip.spatial_id,
+                SpatialTreeItemKey::new(3, index as u64),
+            );
+            hits.push_area(
+                builder,
+                Target::Rect(index).tag(),
+                "rect",
+                rect.bounds,
+                rect.footprint(),
+                SpaceAndClipInfo {
+                    spatial_id: rotated.unwrap_or(space_and_clip.spatial_id),
+                    ..space_and_clip
+                },
+            );
+            if rotated.is_some() {
+                builder.pop_reference_frame();
+            }
+        }
+
+        // The handles resize along the scene's axes, so turned rects don't
+        // get any.
+        let index = match self.selection {
+            Some(index)
+                if index < model.rects.len()
+                    && model.is_visible(&model.rects[index])
+                    && model.rects[index].rotation == 0.0 =>
+            {
+                index
+            }
+            _ => return,
+        };
+
+        for corner in Corner::ALL {
+            let center = corner.of(&model.rects[index].bounds);
+            let handle = centered_square(center, HANDLE_SIZE);
+            let outline = handle.inflate(1.0, 1.0);
+
+            builder.push_rect(
+                &CommonItemProperties::new(outline, space_and_clip),
+                outline,
+                ColorF::BLACK,
+            );
+            builder.push_rect(
+                &CommonItemProperties::new(handle, space_and_clip),
+                handle,
+                ColorF::WHITE,
+            );
+
+            let hit_area = centered_square(center, HANDLE_HIT_SIZE);
+            hits.push(
+                builder,
+                Target::Handle(index, corner).tag(),
+                "handle",
+                hit_area,
+                space_and_clip,
+            );
+        }
+    }
+}
+
+pub fn centered_square(center: LayoutPoint, size: f32) -> LayoutRect {
+    let half = LayoutVector2D::new(size / 2.0, size / 2.0);
+    LayoutRect::from_origin_and_size(center - half, LayoutSize::new(size, size))
+}
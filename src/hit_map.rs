@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+
+use log::info;
+use webrender::{
+    api::{
+        units::{LayoutPoint, LayoutRect, LayoutSize, LayoutTransform},
+        ColorF, CommonItemProperties, DisplayListBuilder, ItemTag, PropertyBinding,
+        PropertyBindingKey, ReferenceFrameKind, SpaceAndClipInfo, SpatialTreeItemKey,
+        TransformStyle,
+    },
+    euclid::vec3,
+};
+
+use crate::view::View;
+
+/// Tag kinds of the items `App` tags itself. The editor uses 0 to 4 for
+/// rects and their handles, and the text input 5.
+pub const IMAGE_TILE_KIND: u16 = 6;
+pub const GRADIENT_KIND: u16 = 7;
+pub const PATH_KIND: u16 = 8;
+pub const SPINNER_KIND: u16 = 9;
+pub const SCROLL_FRAME_KIND: u16 = 10;
+
+/// Width of the `--hit-test-debug` outline, in layout pixels.
+const OUTLINE_WIDTH: f32 = 2.0;
+
+const OUTLINE_COLOR: ColorF = ColorF {
+    r: 1.0,
+    g: 0.2,
+    b: 0.6,
+    a: 1.0,
+};
+
+/// What a tag was pushed for, in window layout pixels.
+#[derive(Clone, Copy, Debug)]
+pub struct HitItem {
+    pub kind: &'static str,
+    pub bounds: LayoutRect,
+}
+
+/// Every hit-test tag in the current display list and the item it belongs
+/// to, rebuilt along with it.
+///
+/// Areas are given in the space they are pushed in, which `set_space` maps
+/// to window layout pixels.
+///
+/// Tags are `(index, kind)`, with the index counting items of that kind in
+/// the order they are pushed, so the same scene always gets the same tags.
+/// An item made of several hit-test areas, such as the path's spans, has
+/// one tag covering all of them.
+pub struct HitMap {
+    items: HashMap<ItemTag, HitItem>,
+    space: View,
+    /// Tag of the front item under the pointer, for `--hit-test-debug`.
+    hovered: Option<ItemTag>,
+    outline_keys: [PropertyBindingKey<LayoutTransform>; 4],
+}
+
+impl HitMap {
+    /// `first_key` is the first of four consecutive property binding keys the
+    /// outline's edges are bound to.
+    pub fn new(first_key: u64) -> HitMap {
+        HitMap {
+            items: HashMap::new(),
+            space: View::default(),
+            hovered: None,
+            outline_keys: [0, 1, 2, 3].map(|i| PropertyBindingKey::new(first_key + i)),
+        }
+    }
+
+    /// Forgets every tag before a rebuild.
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.space = View::default();
+    }
+
+    /// Sets how areas pushed from now on map to window layout pixels.
+    pub fn set_space(&mut self, space: View) {
+        self.space = space;
+    }
+
+    pub fn push(
+        &mut self,
+        builder: &mut DisplayListBuilder,
+        tag: ItemTag,
+        kind: &'static str,
+        bounds: LayoutRect,
+        space_and_clip: SpaceAndClipInfo,
+    ) {
+        self.push_area(builder, tag, kind, bounds, bounds, space_and_clip);
+    }
+
+    /// Pushes a hit-test area at `bounds` in `space_and_clip`, recording
+    /// `extent` for it, given in the current space, for when the area is
+    /// in a frame of its own.
+    pub fn push_area(
+        &mut self,
+        builder: &mut DisplayListBuilder,
+        tag: ItemTag,
+        kind: &'static str,
+        bounds: LayoutRect,
+        extent: LayoutRect,
+        space_and_clip: SpaceAndClipInfo,
+    ) {
+        builder.push_hit_test(&CommonItemProperties::new(bounds, space_and_clip), tag);
+        let bounds = self.space.to_layout_rect(extent);
+        self.items
+            .entry(tag)
+            .and_modify(|item| item.bounds = item.bounds.union(&bounds))
+            .or_insert(HitItem { kind, bounds });
+    }
+
+    pub fn get(&self, tag: ItemTag) -> Option<&HitItem> {
+        self.items.get(&tag)
+    }
+
+    /// Records the front tag of a hit test, logging what it belongs to when
+    /// it changes. Returns whether it did.
+    pub fn hover(&mut self, tags: &[ItemTag]) -> bool {
+        let hovered = tags.first().copied();
+        if hovered == self.hovered {
+            return false;
+        }
+        self.hovered = hovered;
+        match hovered {
+            Some(tag) => match self.get(tag) {
+                Some(item) => info!("hovering {} {:?} at {:?}", item.kind, tag, item.bounds),
+                None => info!("hovering unknown tag {:?}", tag),
+            },
+            None => info!("hovering nothing"),
+        }
+        true
+    }
+
+    /// What is hovered, for the HUD's status panel: its kind and tag, and
+    /// where it is.
+    pub fn hover_lines(&self) -> Vec<String> {
+        match self.hovered {
+            Some(tag) => match self.get(tag) {
+                Some(item) => vec![
+                    format!("hover: {} {:?}", item.kind, tag),
+                    format!(
+                        "at {:.0},{:.0} size {:.0}x{:.0}",
+                        item.bounds.min.x,
+                        item.bounds.min.y,
+                        item.bounds.width(),
+                        item.bounds.height()
+                    ),
+                ],
+                None => vec![format!("hover: unknown {:?}", tag)],
+            },
+            None => vec!["hover: nothing".to_string()],
+        }
+    }
+
+    /// Forgets the hovered tag, when the items under the pointer have
+    /// changed.
+    pub fn clear_hover(&mut self) {
+        self.hovered = None;
+    }
+
+    /// Pushes the outline around the hovered item as four unit rects whose
+    /// transforms are bound to the outline keys, unless `bind` is false.
+    pub fn push_outline(
+        &self,
+        builder: &mut DisplayListBuilder,
+        bind: bool,
+        space_and_clip: SpaceAndClipInfo,
+    ) {
+        let unit = LayoutRect::from_size(LayoutSize::new(1.0, 1.0));
+        for (i, (key, transform)) in self.outline_transforms().into_iter().enumerate() {
+            let frame = builder.push_reference_frame(
+                LayoutPoint::zero(),
+                space_and_clip.spatial_id,
+                TransformStyle::Flat,
+                if bind {
+                    PropertyBinding::Binding(key, transform)
+                } else {
+                    PropertyBinding::Value(transform)
+                },
+                ReferenceFrameKind::Transform {
+                    is_2d_scale_translation: true,
+                    should_snap: false,
+                    paired_with_perspective: false,
+                },
+                SpatialTreeItemKey::new(1, i as u64),
+            );
+            builder.push_rect(
+                &CommonItemProperties::new(
+                    unit,
+                    SpaceAndClipInfo {
+                        spatial_id: frame,
+                        clip_id: space_and_clip.clip_id,
+                    },
+                ),
+                unit,
+                OUTLINE_COLOR,
+            );
+            builder.pop_reference_frame();
+        }
+    }
+
+    /// The transform of each outline edge, framing the hovered item from
+    /// just outside it, or scaling the edges away when there is none.
+    pub fn outline_transforms(
+        &self,
+    ) -> Vec<(PropertyBindingKey<LayoutTransform>, LayoutTransform)> {
+        let edges = match self.hovered.and_then(|tag| self.get(tag)) {
+            Some(item) => {
+                let outer = item.bounds.inflate(OUTLINE_WIDTH, OUTLINE_WIDTH);
+                let (min, max) = (outer.min, outer.max);
+                let edge = |x0: f32, y0: f32, x1: f32, y1: f32| {
+                    unit_transform(LayoutRect::new(
+                        LayoutPoint::new(x0, y0),
+                        LayoutPoint::new(x1, y1),
+                    ))
+                };
+                [
+                    edge(min.x, min.y, max.x, min.y + OUTLINE_WIDTH),
+                    edge(min.x, max.y - OUTLINE_WIDTH, max.x, max.y),
+                    edge(min.x, min.y, min.x + OUTLINE_WIDTH, max.y),
+                    edge(max.x - OUTLINE_WIDTH, min.y, max.x, max.y),
+                ]
+            }
+            None => [LayoutTransform::scale(0.0, 0.0, 1.0); 4],
+        };
+        self.outline_keys.into_iter().zip(edges).collect()
+    }
+}
+
+/// Maps the unit square onto `rect`.
+pub fn unit_transform(rect: LayoutRect) -> LayoutTransform {
+    let size = rect.size();
+    LayoutTransform::scale(size.width, size.height, 1.0)
+        .then_translate(vec3(rect.min.x, rect.min.y, 0.0))
+}
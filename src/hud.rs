@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+
+use webrender::{
+    api::{
+        units::{LayoutPoint, LayoutRect, LayoutSize, LayoutVector2D},
+        ColorF, CommonItemProperties, DisplayListBuilder, Epoch, PipelineId, SpaceAndClipInfo,
+    },
+    Transaction,
+};
+
+use crate::text::{push_text, text_size};
+
+/// Distance of the HUD overlays from the window's edges.
+pub const HUD_MARGIN: f32 = 16.0;
+
+/// Space between overlays stacked in the same corner.
+const HUD_SPACING: f32 = 8.0;
+
+/// Size of the status panel's font cells, in layout pixels.
+const TEXT_CELL: f32 = 2.0;
+
+/// Space between the status panel's edge and its text, and between lines.
+const PADDING: f32 = 4.0;
+
+const FILL_COLOR: ColorF = ColorF {
+    r: 0.0,
+    g: 0.0,
+    b: 0.0,
+    a: 0.7,
+};
+
+/// The window corner the HUD overlays are anchored to, for `--hud-corner`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    /// Parses `tl`, `tr`, `bl` or `br`.
+    pub fn from_name(name: &str) -> Option<Corner> {
+        match name {
+            "tl" => Some(Corner::TopLeft),
+            "tr" => Some(Corner::TopRight),
+            "bl" => Some(Corner::BottomLeft),
+            "br" => Some(Corner::BottomRight),
+            _ => None,
+        }
+    }
+}
+
+/// Where the top left of an overlay of `size` goes to sit in `corner` of a
+/// window `layout_size` in layout pixels, `HUD_MARGIN` in from both edges.
+///
+/// Overlays work this out on every build, so they stay in their corner as
+/// the window is resized. In a window too small for the overlay it keeps to
+/// the top or left edge rather than going off it.
+pub fn hud_position(corner: Corner, size: LayoutSize, layout_size: LayoutSize) -> LayoutPoint {
+    let far = |window: f32, overlay: f32| (window - HUD_MARGIN - overlay).max(HUD_MARGIN);
+    let (x, y) = match corner {
+        Corner::TopLeft => (HUD_MARGIN, HUD_MARGIN),
+        Corner::TopRight => (far(layout_size.width, size.width), HUD_MARGIN),
+        Corner::BottomLeft => (HUD_MARGIN, far(layout_size.height, size.height)),
+        Corner::BottomRight => (
+            far(layout_size.width, size.width),
+            far(layout_size.height, size.height),
+        ),
+    };
+    LayoutPoint::new(x, y)
+}
+
+/// Like `hud_position`, for an overlay of `size` stacked in `corner` after
+/// overlays of the `before` sizes: below them in the top corners and above
+/// them in the bottom ones.
+pub fn stacked_hud_position(
+    corner: Corner,
+    size: LayoutSize,
+    before: &[LayoutSize],
+    layout_size: LayoutSize,
+) -> LayoutPoint {
+    let mut position = hud_position(corner, size, layout_size);
+    let step: f32 = before.iter().map(|size| size.height + HUD_SPACING).sum();
+    match corner {
+        Corner::TopLeft | Corner::TopRight => position.y += step,
+        Corner::BottomLeft | Corner::BottomRight => {
+            position.y = (position.y - step).max(HUD_MARGIN)
+        }
+    }
+    position
+}
+
+/// Parts of the status panel, in the order they are shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Section {
+    /// Which scene layers are shown, while one is hidden or in debug mode.
+    Layers,
+    /// The item under the pointer, with `--hit-test-debug`.
+    Hover,
+    /// The epochs each pipeline rendered, in debug mode.
+    Pacing,
+    /// The renderer's memory use, in debug mode.
+    Memory,
+}
+
+/// Lines of status text in a panel in the HUD corner.
+///
+/// The panel is a pipeline of its own, embedded in the main display list
+/// as an iframe over the whole window. New text then only resends the
+/// panel's few items, since some of it changes between frames that
+/// otherwise rebuild nothing.
+pub struct StatusPanel {
+    pipeline_id: PipelineId,
+    builder: DisplayListBuilder,
+    epoch: Epoch,
+    sections: BTreeMap<Section, Vec<String>>,
+    corner: Corner,
+    /// Sizes of the overlays stacked before the panel in its corner.
+    before: Vec<LayoutSize>,
+    layout_size: LayoutSize,
+    /// Whether anything shown changed since the panel was last sent.
+    dirty: bool,
+}
+
+impl StatusPanel {
+    pub fn new(pipeline_id: PipelineId, corner: Corner) -> StatusPanel {
+        StatusPanel {
+            pipeline_id,
+            builder: DisplayListBuilder::new(pipeline_id),
+            epoch: Epoch(0),
+            sections: BTreeMap::new(),
+            corner,
+            before: Vec::new(),
+            layout_size: LayoutSize::zero(),
+            dirty: true,
+        }
+    }
+
+    /// Replaces the lines of `section`; with none it is left out.
+    pub fn set(&mut self, section: Section, lines: Vec<String>) {
+        if lines.is_empty() {
+            self.dirty |= self.sections.remove(&section).is_some();
+        } else if self.sections.get(&section) != Some(&lines) {
+            self.sections.insert(section, lines);
+            self.dirty = true;
+        }
+    }
+
+    /// The panel's size, padding included, or zero with nothing to show.
+    pub fn size(&self) -> LayoutSize {
+        let lines: Vec<LayoutSize> = self
+            .sections
+            .values()
+            .flatten()
+            .map(|line| text_size(line, TEXT_CELL))
+            .collect();
+        if lines.is_empty() {
+            return LayoutSize::zero();
+        }
+        let width = lines.iter().map(|size| size.width).fold(0.0, f32::max);
+        let height: f32 = lines.iter().map(|size| size.height + PADDING).sum();
+        LayoutSize::new(width + 2.0 * PADDING, height + PADDING)
+    }
+
+    /// Pushes the iframe the panel is shown in, over a window of
+    /// `layout_size`, with the panel stacked after overlays of the `before`
+    /// sizes. The panel places itself when it is sent, so it keeps to its
+    /// corner when its size changes without a rebuild.
+    pub fn push_iframe(
+        &mut self,
+        builder: &mut DisplayListBuilder,
+        before: Vec<LayoutSize>,
+        layout_size: LayoutSize,
+        space_and_clip: SpaceAndClipInfo,
+    ) {
+        if before != self.before || layout_size != self.layout_size {
+            self.before = before;
+            self.layout_size = layout_size;
+            self.dirty = true;
+        }
+        let bounds = LayoutRect::from_size(layout_size);
+        builder.push_iframe(bounds, bounds, &space_and_clip, self.pipeline_id, true);
+    }
+
+    /// Adds the panel's display list to `txn` if anything shown changed,
+    /// and returns whether it did.
+    pub fn send(&mut self, txn: &mut Transaction) -> bool {
+        if !self.dirty {
+            return false;
+        }
+        self.dirty = false;
+
+        let size = self.size();
+        self.builder.begin();
+        if size != LayoutSize::zero() {
+            let space_and_clip = SpaceAndClipInfo::root_scroll(self.pipeline_id);
+            let origin = stacked_hud_position(self.corner, size, &self.before, self.layout_size);
+            let bounds = LayoutRect::from_origin_and_size(origin, size);
+            self.builder.push_rect(
+                &CommonItemProperties::new(bounds, space_and_clip),
+                bounds,
+                FILL_COLOR,
+            );
+            let mut origin = origin + LayoutVector2D::new(PADDING, PADDING);
+            for line in self.sections.values().flatten() {
+                push_text(
+                    &mut self.builder,
+                    origin,
+                    line,
+                    TEXT_CELL,
+                    ColorF::WHITE,
+                    space_and_clip,
+                );
+                origin.y += text_size(line, TEXT_CELL).height + PADDING;
+            }
+        }
+        txn.set_display_list(self.epoch, None, self.layout_size, self.builder.end());
+        self.epoch = Epoch(self.epoch.0 + 1);
+        true
+    }
+}
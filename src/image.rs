@@ -0,0 +1,247 @@
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use glutin::event_loop::EventLoopProxy;
+use log::info;
+use webrender::api::units::{DeviceIntPoint, DeviceIntRect, DeviceIntSize};
+
+use crate::UserEvent;
+
+/// A decoded image in premultiplied BGRA8, ready for `Transaction::add_image`.
+pub struct DecodedImage {
+    pub width: i32,
+    pub height: i32,
+    pub data: Vec<u8>,
+}
+
+/// A piece of a decoded image that fits in a single texture.
+#[derive(Clone)]
+pub struct ImageTile {
+    /// Position of the tile within the full image, in pixels.
+    pub rect: DeviceIntRect,
+    pub data: Vec<u8>,
+}
+
+/// An image split into tiles no larger than the maximum texture size.
+#[derive(Clone)]
+pub struct TiledImage {
+    pub size: DeviceIntSize,
+    pub tiles: Vec<ImageTile>,
+}
+
+/// How `--bg-image` covers the scene.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackdropFit {
+    /// At its own size, centered.
+    Center,
+    /// Scaled on each axis to the scene's size.
+    Stretch,
+    /// Repeated at its own size from the scene's top left corner.
+    Tile,
+}
+
+impl BackdropFit {
+    pub fn from_name(name: &str) -> Option<BackdropFit> {
+        match name {
+            "center" => Some(BackdropFit::Center),
+            "stretch" => Some(BackdropFit::Stretch),
+            "tile" => Some(BackdropFit::Tile),
+            _ => None,
+        }
+    }
+}
+
+/// Decodes `path` on a worker thread, splits it into tiles of at most
+/// `max_texture_size` pixels a side and posts the result back to the event
+/// loop wrapped in `event`.
+pub fn load_async(
+    path: PathBuf,
+    max_texture_size: i32,
+    events_proxy: EventLoopProxy<UserEvent>,
+    event: fn(Result<TiledImage, String>) -> UserEvent,
+) {
+    thread::spawn(move || {
+        let result = decode_png(&path)
+            .map(|image| split_tiles(image, max_texture_size))
+            .map_err(|err| format!("{}: {}", path.display(), err));
+        let _ = events_proxy.send_event(event(result));
+    });
+}
+
+/// Splits `image` into a grid of tiles. Tiles are cut on whole pixels, so
+/// adjacent tiles share their edges exactly.
+pub fn split_tiles(image: DecodedImage, max_texture_size: i32) -> TiledImage {
+    let size = DeviceIntSize::new(image.width, image.height);
+    if image.width <= max_texture_size && image.height <= max_texture_size {
+        return TiledImage {
+            size,
+            tiles: vec![ImageTile {
+                rect: DeviceIntRect::from_size(size),
+                data: image.data,
+            }],
+        };
+    }
+
+    let mut tiles = Vec::new();
+    for y in (0..image.height).step_by(max_texture_size as usize) {
+        for x in (0..image.width).step_by(max_texture_size as usize) {
+            let rect = DeviceIntRect::new(
+                DeviceIntPoint::new(x, y),
+                DeviceIntPoint::new(
+                    (x + max_texture_size).min(image.width),
+                    (y + max_texture_size).min(image.height),
+                ),
+            );
+
+            let mut data = Vec::with_capacity(rect.area() as usize * 4);
+            for row in rect.min.y..rect.max.y {
+                let start = (row * image.width + rect.min.x) as usize * 4;
+                data.extend_from_slice(&image.data[start..start + rect.width() as usize * 4]);
+            }
+            tiles.push(ImageTile { rect, data });
+        }
+    }
+
+    info!(
+        "image is {}x{}, larger than the max texture size {}: split into {} tiles",
+        image.width,
+        image.height,
+        max_texture_size,
+        tiles.len()
+    );
+
+    TiledImage { size, tiles }
+}
+
+pub fn decode_png(path: &Path) -> Result<DecodedImage, png::DecodingError> {
+    let mut decoder = png::Decoder::new(File::open(path)?);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    buf.truncate(info.buffer_size());
+
+    let channels = info.color_type.samples();
+    let mut data = Vec::with_capacity(info.width as usize * info.height as usize * 4);
+    for pixel in buf.chunks_exact(channels) {
+        let (r, g, b, a) = match *pixel {
+            [r, g, b, a] => (r, g, b, a),
+            [r, g, b] => (r, g, b, 255),
+            [l, a] => (l, l, l, a),
+            [l] => (l, l, l, 255),
+            _ => unreachable!(),
+        };
+        let premultiply = |c: u8| ((c as u16 * a as u16 + 127) / 255) as u8;
+        data.extend_from_slice(&[premultiply(b), premultiply(g), premultiply(r), a]);
+    }
+
+    Ok(DecodedImage {
+        width: info.width as i32,
+        height: info.height as i32,
+        data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use webrender::api::units::{DevicePoint, LayoutPoint};
+
+    use super::*;
+    use crate::coords::CoordinateConverter;
+
+    const EPSILON: f32 = 1e-3;
+
+    /// An image with sides that don't divide evenly by the texture size,
+    /// each pixel holding its own index so tiles can be checked against it.
+    fn image(width: i32, height: i32) -> DecodedImage {
+        let data = (0..width * height)
+            .flat_map(|index| (index as u32).to_le_bytes())
+            .collect();
+        DecodedImage {
+            width,
+            height,
+            data,
+        }
+    }
+
+    #[test]
+    fn tiles_cover_the_image_once() {
+        let tiled = split_tiles(image(150, 70), 64);
+        assert_eq!(tiled.size, DeviceIntSize::new(150, 70));
+        assert_eq!(tiled.tiles.len(), 6);
+
+        for y in 0..70 {
+            for x in 0..150 {
+                let point = DeviceIntPoint::new(x, y);
+                let covering: Vec<_> = tiled
+                    .tiles
+                    .iter()
+                    .filter(|tile| tile.rect.contains(point))
+                    .collect();
+                assert_eq!(covering.len(), 1, "pixel {:?}", point);
+
+                let tile = covering[0];
+                let start =
+                    ((y - tile.rect.min.y) * tile.rect.width() + x - tile.rect.min.x) as usize * 4;
+                let pixel = u32::from_le_bytes(tile.data[start..start + 4].try_into().unwrap());
+                assert_eq!(pixel, (y * 150 + x) as u32, "pixel {:?}", point);
+            }
+        }
+    }
+
+    #[test]
+    fn small_images_are_one_tile() {
+        let tiled = split_tiles(image(64, 20), 64);
+        assert_eq!(tiled.tiles.len(), 1);
+        assert_eq!(tiled.tiles[0].rect, DeviceIntRect::from_size(tiled.size));
+    }
+
+    /// Places tiles the way the scene does: each tile's device rect in
+    /// layout pixels, offset from an origin snapped to a device pixel.
+    #[test]
+    fn tile_seams_land_on_shared_device_pixel_edges() {
+        let tiled = split_tiles(image(150, 70), 64);
+        for scale in [1.0, 1.25, 1.5, 1.75, 2.0, 3.0] {
+            let coords = CoordinateConverter::new(DeviceIntSize::new(1023, 767), scale);
+            let origin = coords.snap_design_to_device(LayoutPoint::new(101.3, 47.9));
+            let bounds: Vec<_> = tiled
+                .tiles
+                .iter()
+                .map(|tile| {
+                    coords
+                        .device_rect_to_layout(tile.rect)
+                        .translate(origin.to_vector())
+                })
+                .collect();
+
+            for (a, tile_a) in bounds.iter().zip(&tiled.tiles) {
+                for (b, tile_b) in bounds.iter().zip(&tiled.tiles) {
+                    if tile_a.rect.max.x == tile_b.rect.min.x {
+                        assert_eq!(a.max.x, b.min.x, "scale {}", scale);
+                    }
+                    if tile_a.rect.max.y == tile_b.rect.min.y {
+                        assert_eq!(a.max.y, b.min.y, "scale {}", scale);
+                    }
+                    if tile_a.rect != tile_b.rect {
+                        assert!(!a.intersects(b), "tiles overlap at scale {}", scale);
+                    }
+                }
+
+                for corner in [a.min, a.max] {
+                    let device = coords.layout_to_device(corner);
+                    let rounded: DevicePoint = device.round();
+                    assert!(
+                        (device - rounded).length() < EPSILON,
+                        "tile corner {:?} is off the device grid at scale {}",
+                        device,
+                        scale
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use log::info;
+use webrender::api::{
+    units::{LayoutPoint, LayoutRect, LayoutSize, LayoutTransform},
+    ColorF, CommonItemProperties, DisplayListBuilder, PropertyBinding, PropertyBindingKey,
+    PropertyValue, ReferenceFrameKind, SpaceAndClipInfo, SpatialTreeItemKey, TransformStyle,
+};
+
+use crate::{
+    scene::{SceneModel, SceneRect},
+    text::{push_text, text_size},
+};
+
+/// Size of the font cells, in layout pixels.
+const TEXT_CELL: f32 = 2.0;
+
+/// Space between the label's edge and its text.
+const PADDING: f32 = 4.0;
+
+const FILL_COLOR: ColorF = ColorF {
+    r: 0.0,
+    g: 0.0,
+    b: 0.0,
+    a: 0.7,
+};
+
+/// How the last scene update reached the screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpdatePath {
+    /// Only rects' positions and colors changed, and only their bindings
+    /// were sent.
+    Bindings,
+    /// The display list was built and sent again.
+    Rebuild,
+}
+
+impl UpdatePath {
+    const ALL: [UpdatePath; 2] = [UpdatePath::Bindings, UpdatePath::Rebuild];
+
+    fn label(self) -> &'static str {
+        match self {
+            UpdatePath::Bindings => "update: bindings",
+            UpdatePath::Rebuild => "update: rebuild",
+        }
+    }
+}
+
+/// The `--incremental-updates` path for reloaded scenes.
+///
+/// Every rect is pushed at the origin of a frame placed by a bound
+/// transform, and filled with a bound color, keyed by its index. When a
+/// reload only moves rects or changes their colors, leaving everything else
+/// as it was in the last display list, the new values are sent as dynamic
+/// properties and the display list is left alone. Any other change falls
+/// back to a rebuild.
+///
+/// A label in the HUD corner says which path the last update took. Both
+/// labels are in every display list, each in a frame whose bound transform
+/// scales it away unless it is the one to show, so switching between them
+/// doesn't need a rebuild either.
+///
+/// Hit testing, and the editor's handles with it, keep the rects where they
+/// were on the last rebuild until the next one.
+pub struct IncrementalUpdates {
+    /// Key of the first rect's placement and color, each rect after it
+    /// taking the next.
+    first_key: u64,
+    label_keys: [PropertyBindingKey<LayoutTransform>; 2],
+    /// The model the display list was last built from, unless it was built
+    /// without the bindings.
+    built: Option<SceneModel>,
+    last: Option<UpdatePath>,
+    /// Where the label was put on the last build.
+    label_origin: LayoutPoint,
+}
+
+impl IncrementalUpdates {
+    /// Binds the labels to `label_first_key` and the one after it, and the
+    /// rects to keys from `first_key`.
+    pub fn new(label_first_key: u64, first_key: u64) -> IncrementalUpdates {
+        IncrementalUpdates {
+            first_key,
+            label_keys: [0, 1].map(|i| PropertyBindingKey::new(label_first_key + i)),
+            built: None,
+            last: None,
+            label_origin: LayoutPoint::zero(),
+        }
+    }
+
+    pub fn first_key(&self) -> u64 {
+        self.first_key
+    }
+
+    /// Keeps the model the display list was built from, or `None` if its
+    /// rects weren't bound.
+    pub fn set_built(&mut self, model: Option<&SceneModel>) {
+        self.built = model.cloned();
+    }
+
+    /// Picks the path that shows `model` in place of the one built, a
+    /// rebuild whenever `needs_rebuild` because something else changed too.
+    pub fn update(&mut self, model: &SceneModel, needs_rebuild: bool) -> UpdatePath {
+        let changed = match &self.built {
+            Some(built) if !needs_rebuild => bindable_changes(built, model),
+            _ => None,
+        };
+        let path = match changed {
+            Some(changed) => {
+                info!(
+                    "updated {} of {} rects through property bindings",
+                    changed,
+                    model.rects.len()
+                );
+                UpdatePath::Bindings
+            }
+            None => {
+                info!("scene changed beyond positions and colors, rebuilding");
+                UpdatePath::Rebuild
+            }
+        };
+        self.last = Some(path);
+        path
+    }
+
+    /// The placement of every rect and the label transforms, for a binding
+    /// update.
+    pub fn transforms(&self, model: &SceneModel) -> Vec<PropertyValue<LayoutTransform>> {
+        let rects = model.rects.iter().enumerate().map(|(index, rect)| {
+            PropertyBindingKey::new(self.first_key + index as u64).with(rect.placement())
+        });
+        let labels = UpdatePath::ALL
+            .iter()
+            .zip(self.label_keys)
+            .map(|(&path, key)| key.with(self.label_transform(path)));
+        rects.chain(labels).collect()
+    }
+
+    /// The fill color of every rect, for a binding update.
+    pub fn colors(&self, model: &SceneModel) -> Vec<PropertyValue<ColorF>> {
+        model
+            .rects
+            .iter()
+            .enumerate()
+            .map(|(index, rect)| {
+                PropertyBindingKey::new(self.first_key + index as u64).with(rect.color)
+            })
+            .collect()
+    }
+
+    fn label_transform(&self, path: UpdatePath) -> LayoutTransform {
+        if self.last == Some(path) {
+            LayoutTransform::translation(self.label_origin.x, self.label_origin.y, 0.0)
+        } else {
+            LayoutTransform::scale(0.0, 0.0, 1.0)
+        }
+    }
+
+    /// The size of the wider label, padding included.
+    pub fn label_size(&self) -> LayoutSize {
+        let text = UpdatePath::ALL
+            .iter()
+            .map(|path| text_size(path.label(), TEXT_CELL))
+            .fold(LayoutSize::zero(), |a, b| a.max(b));
+        text + LayoutSize::new(PADDING, PADDING) * 2.0
+    }
+
+    /// Pushes both labels at `origin`, only the one for the last update
+    /// showing.
+    pub fn push_label(
+        &mut self,
+        builder: &mut DisplayListBuilder,
+        origin: LayoutPoint,
+        space_and_clip: SpaceAndClipInfo,
+    ) {
+        self.label_origin = origin;
+        let bounds = LayoutRect::from_size(self.label_size());
+        for (i, (&path, key)) in UpdatePath::ALL.iter().zip(self.label_keys).enumerate() {
+            let frame = builder.push_reference_frame(
+                LayoutPoint::zero(),
+                space_and_clip.spatial_id,
+                TransformStyle::Flat,
+                PropertyBinding::Binding(key, self.label_transform(path)),
+                ReferenceFrameKind::Transform {
+                    is_2d_scale_translation: true,
+                    should_snap: false,
+                    paired_with_perspective: false,
+                },
+                SpatialTreeItemKey::new(0, 4 + i as u64),
+            );
+            let space_and_clip = SpaceAndClipInfo {
+                spatial_id: frame,
+                clip_id: space_and_clip.clip_id,
+            };
+            builder.push_rect(
+                &CommonItemProperties::new(bounds, space_and_clip),
+                bounds,
+                FILL_COLOR,
+            );
+            push_text(
+                builder,
+                LayoutPoint::new(PADDING, PADDING),
+                path.label(),
+                TEXT_CELL,
+                ColorF::WHITE,
+                space_and_clip,
+            );
+            builder.pop_reference_frame();
+        }
+    }
+}
+
+/// How many rects moved or changed color from `built` to `model`, if
+/// that is all that changed.
+fn bindable_changes(built: &SceneModel, model: &SceneModel) -> Option<usize> {
+    if built.layers != model.layers || built.rects.len() != model.rects.len() {
+        return None;
+    }
+    let mut changed = 0;
+    for (before, after) in built.rects.iter().zip(&model.rects) {
+        let rebound = SceneRect {
+            bounds: after.bounds,
+            color: after.color,
+            ..before.clone()
+        };
+        if rebound != *after || before.bounds.size() != after.bounds.size() {
+            return None;
+        }
+        if before != after {
+            changed += 1;
+        }
+    }
+    Some(changed)
+}
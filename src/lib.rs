@@ -0,0 +1,967 @@
+//! A demo of webrender's display lists, as a library: `run` opens the
+//! demo's window with a `Config` and drives it, optionally calling a
+//! `FrameHook` on every frame. See `examples/bounce.rs`.
+
+pub mod app;
+mod benchmark;
+mod capture;
+mod clip_debug;
+mod color;
+pub mod config;
+mod control;
+pub mod coords;
+mod cursor_readout;
+pub mod display_diff;
+mod easing;
+mod editor;
+mod hit_map;
+mod hud;
+mod image;
+mod incremental;
+mod memory;
+mod mirror;
+mod nested_scroll;
+mod pacing;
+mod palette;
+mod particles;
+mod path;
+mod primitives;
+mod profile;
+mod readback;
+mod ruler;
+pub mod scene;
+mod scene_file;
+mod session;
+mod supersample;
+mod svg_import;
+mod text;
+mod text_input;
+mod touch;
+mod tour;
+mod view;
+mod watch;
+
+use std::{
+    fs, mem, process, ptr, thread,
+    time::{Duration, Instant},
+};
+
+use gleam::gl;
+use glutin::{
+    dpi::PhysicalSize,
+    event,
+    event_loop::{self},
+    platform::run_return::EventLoopExtRunReturn,
+    window::{Window, WindowBuilder},
+    ContextBuilder, GlRequest,
+};
+use log::{error, info, warn};
+use webrender::{
+    api::{
+        units::{DeviceIntPoint, DeviceIntRect, DeviceIntSize},
+        DocumentId, RenderNotifier,
+    },
+    DebugFlags, Renderer, RendererOptions, Transaction,
+};
+
+use benchmark::ResizeBenchmark;
+use mirror::{GlWindow, MirrorWindow};
+use profile::{FrameProfile, ProfileWriter};
+use session::{Action, Recorder, Replay};
+use supersample::Supersampler;
+use text_input::TextEdit;
+use tour::Tour;
+
+pub use app::{App, FrameHook};
+pub use config::Config;
+
+/// Fraction of the screen `--fit-image` lets the window take up.
+const MAX_FIT_FRACTION: f64 = 0.9;
+
+/// Degrees the `[` and `]` keys turn the selected rect's hue by.
+const HUE_STEP: f32 = 15.0;
+
+/// Degrees the `,` and `.` keys turn the selected rect by.
+const ROTATION_STEP: f32 = 5.0;
+
+/// Layout pixels one line of a wheel turn scrolls by.
+const WHEEL_LINE_HEIGHT: f32 = 40.0;
+
+/// Rendered frames between updates of the memory figures the debug HUD
+/// shows.
+const HUD_MEMORY_FRAMES: u64 = 60;
+
+/// Events posted to the event loop from other threads.
+pub enum UserEvent {
+    /// Webrender has a new frame or needs the loop to run.
+    WakeUp,
+    /// The `--image` worker finished decoding.
+    ImageLoaded(Result<image::TiledImage, String>),
+    /// The `--bg-image` worker finished decoding.
+    BackdropLoaded(Result<image::TiledImage, String>),
+    /// The `--watch`ed scene file changed and was reparsed.
+    SceneReloaded(Result<scene_file::SceneFile, String>),
+    /// A command arrived on the `--control-port` socket.
+    Control(control::Request),
+}
+
+struct Notifier {
+    events_proxy: event_loop::EventLoopProxy<UserEvent>,
+}
+
+impl Notifier {
+    fn new(events_proxy: event_loop::EventLoopProxy<UserEvent>) -> Notifier {
+        Notifier { events_proxy }
+    }
+}
+
+impl RenderNotifier for Notifier {
+    fn clone(&self) -> Box<dyn RenderNotifier> {
+        Box::new(Notifier {
+            events_proxy: self.events_proxy.clone(),
+        })
+    }
+
+    fn wake_up(&self, _composite_needed: bool) {
+        #[cfg(not(target_os = "android"))]
+        let _ = self.events_proxy.send_event(UserEvent::WakeUp);
+    }
+
+    fn new_frame_ready(&self, _: DocumentId, _scrolled: bool, composite_needed: bool) {
+        self.wake_up(composite_needed);
+    }
+}
+
+/// Opens the demo's window and runs it with `config` until it's closed.
+/// `on_frame`, if given, is installed with `App::set_on_frame` before the
+/// first frame.
+///
+/// Problems the demo can't recover from, such as a `--record` directory it
+/// can't create, are printed and exit the process, as does a failed
+/// `--strict` or `--expect-scene` run.
+pub fn run(config: Config, on_frame: Option<FrameHook>) {
+    let mut events_loop = event_loop::EventLoop::with_user_event();
+    let mut window_builder = WindowBuilder::new()
+        .with_visible(false)
+        .with_transparent(true);
+    if let Some(size) = config.window_size {
+        window_builder = window_builder
+            .with_inner_size(PhysicalSize::new(size.width as u32, size.height as u32));
+    }
+
+    // A `--gl-version` that can't be had falls back to the latest the
+    // platform offers.
+    let context = match config.gl_version {
+        Some((api, (major, minor))) => ContextBuilder::new()
+            .with_gl(GlRequest::Specific(api, (major, minor)))
+            .build_windowed(window_builder.clone(), &events_loop)
+            .or_else(|err| {
+                warn!(
+                    "can't create a {:?} {}.{} context, using the latest: {}",
+                    api, major, minor, err
+                );
+                ContextBuilder::new()
+                    .with_gl(GlRequest::Latest)
+                    .build_windowed(window_builder, &events_loop)
+            }),
+        None => ContextBuilder::new().build_windowed(window_builder, &events_loop),
+    }
+    .unwrap();
+
+    let mut windowed_context = GlWindow::new(unsafe { context.make_current().unwrap() });
+    let main_window_id = windowed_context.window().id();
+
+    let notifier = Box::new(Notifier::new(events_loop.create_proxy()));
+
+    let gl = windowed_context.load_gl().unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(1);
+    });
+    info!(
+        "GL context: {:?} {}",
+        windowed_context.get_api(),
+        gl.get_string(gl::VERSION)
+    );
+
+    let (mut renderer, sender) =
+        Renderer::new(gl.clone(), notifier, renderer_options(&config), None).unwrap();
+    if config.show_overdraw {
+        renderer.toggle_debug_flags(DebugFlags::SHOW_OVERDRAW);
+    }
+
+    let mut window_size = {
+        let size = windowed_context.window().inner_size();
+        DeviceIntSize::new(size.width as i32, size.height as i32)
+    };
+
+    // With `--supersample` the document is laid out at the supersampled
+    // resolution, and window coordinates are scaled up to match.
+    let max_document_size = max_document_size(&*gl, &config);
+    let mut supersampler = config
+        .supersample
+        .and_then(|factor| Supersampler::new(&*gl, factor, window_size, max_document_size));
+    let factor = supersampler.as_ref().map_or(1, Supersampler::factor);
+    let device_pixel_ratio =
+        effective_scale(windowed_context.window().scale_factor(), &config) * factor as f32;
+
+    let mut app = App::new(
+        sender.create_api(),
+        render_size(supersampler.as_ref(), window_size),
+        device_pixel_ratio,
+        max_document_size,
+        &config,
+    );
+    if let Some(hook) = on_frame {
+        app.set_on_frame(hook);
+    }
+
+    if config.dump_scene {
+        println!("{}", app.export_scene());
+        renderer.deinit();
+        return;
+    }
+
+    // The other `--windows`, opened now since the event loop can't be
+    // borrowed once it runs.
+    let mut mirrors = Vec::new();
+    for index in 2..=config.windows {
+        let title = format!("webrender-demo window {}", index);
+        let notifier = Box::new(Notifier::new(events_loop.create_proxy()));
+        match MirrorWindow::new(&events_loop, &windowed_context, notifier, &title, &config) {
+            Ok(mirror) => mirrors.push(mirror),
+            Err(err) => error!("{}", err),
+        }
+    }
+    windowed_context.make_current();
+    // Whether the main window was closed while others stay open.
+    let mut main_closed = false;
+
+    let max_texture_size = {
+        let mut value = [0];
+        unsafe { gl.get_integer_v(gl::MAX_TEXTURE_SIZE, &mut value) };
+        info!("GL max texture size: {}", value[0]);
+        match config.max_texture_size {
+            Some(limit) if limit < value[0] => {
+                info!("limiting texture size to {}", limit);
+                limit
+            }
+            _ => value[0],
+        }
+    };
+
+    if let Some(path) = config.image.clone() {
+        image::load_async(
+            path,
+            max_texture_size,
+            events_loop.create_proxy(),
+            UserEvent::ImageLoaded,
+        );
+    }
+    if let Some(path) = config.bg_image.clone() {
+        image::load_async(
+            path,
+            max_texture_size,
+            events_loop.create_proxy(),
+            UserEvent::BackdropLoaded,
+        );
+    }
+
+    if let Some(path) = config.scene.clone().filter(|_| config.watch) {
+        watch::watch_scene(path, events_loop.create_proxy());
+    }
+
+    if let Some(port) = config.control_port {
+        if let Err(err) = control::listen(port, events_loop.create_proxy()) {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
+
+    if let Some(dir) = &config.record {
+        if let Err(err) = fs::create_dir_all(dir) {
+            eprintln!("can't create {}: {}", dir.display(), err);
+            process::exit(1);
+        }
+    }
+
+    let mut recorder = config.record_transactions.as_deref().map(|path| {
+        Recorder::create(path).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(1);
+        })
+    });
+    let mut replay = config.replay.as_deref().map(|path| {
+        Replay::load(path, config.replay_speed).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(1);
+        })
+    });
+
+    let mut profile = config.profile_json.as_deref().map(|path| {
+        ProfileWriter::create(path).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(1);
+        })
+    });
+    let mut first_frame = None;
+    let mut replay_ended = false;
+    // Set when the demo should exit with an error status.
+    let mut failed = false;
+
+    let mut tour = if config.tour {
+        Some(Tour::new(config.looping))
+    } else {
+        None
+    };
+    let mut benchmark = config
+        .benchmark_resize
+        .map(|iterations| ResizeBenchmark::new(iterations, &app));
+    let mut frames: u64 = 0;
+    let mut next_mem_report = config.mem_report;
+    let mut next_hud_memory = 0;
+
+    let mut cursor_position = DeviceIntPoint::zero();
+    let mut modifiers = event::ModifiersState::empty();
+    let mut eyedropper = false;
+    let mut measuring = false;
+    let mut pick_at = None;
+    // GPU memory in use when the document was last recreated, reported
+    // against the usage once the new document has rendered.
+    let mut memory_before_recreate = None;
+    let mut alpha_test = config.alpha_test;
+    // Whether rendering is paused for a zero-sized, usually minimized,
+    // window.
+    let mut minimized = false;
+    // `screenshot` commands, answered once their frame is saved.
+    let mut pending_screenshots = Vec::new();
+
+    // Under `--fixed-timestep` the clock is virtual, one step further on for
+    // every frame rendered since the loop started.
+    let clock_started = Instant::now();
+    events_loop.run_return(|global_event, _, control_flow| {
+        let now = match config.fixed_timestep {
+            Some(step) => {
+                clock_started + Duration::from_nanos((step.as_nanos() * frames as u128) as u64)
+            }
+            None => Instant::now(),
+        };
+        let mut deadline = app.animate(now);
+        for mirror in &mut mirrors {
+            if let Some(next) = mirror.animate(now) {
+                deadline = Some(deadline.map_or(next, |d| d.min(next)));
+            }
+        }
+        let mut finished = false;
+        if let Some(tour) = tour.as_mut() {
+            match tour.update(&mut app, now) {
+                Some(next) => deadline = Some(deadline.map_or(next, |d| d.min(next))),
+                None => finished = true,
+            }
+        }
+        if let Some(replay) = replay.as_mut() {
+            match replay.update(&mut app, now) {
+                Some(next) => deadline = Some(deadline.map_or(next, |d| d.min(next))),
+                None => {
+                    finished = true;
+                    // The loop can run again before it exits, so the scene is
+                    // only checked the first time.
+                    if let Some(path) = config.expect_scene.as_ref().filter(|_| !replay_ended) {
+                        replay_ended = true;
+                        match session::check_scene(&app, path) {
+                            Ok(()) => info!("replay ended on {}", path.display()),
+                            Err(err) => {
+                                error!("{}", err);
+                                failed = true;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        *control_flow = match deadline {
+            // Virtual deadlines mean nothing to the wall clock, so the loop
+            // keeps going until the animation stops.
+            Some(_) if config.fixed_timestep.is_some() => event_loop::ControlFlow::Poll,
+            Some(deadline) => event_loop::ControlFlow::WaitUntil(deadline),
+            // Keep rendering until `--max-frames` is reached.
+            None if config.max_frames.is_some() => event_loop::ControlFlow::Poll,
+            None => event_loop::ControlFlow::Wait,
+        };
+        if let Some(report) = app.strict_failure().filter(|_| !failed) {
+            eprintln!("--strict: {}", report);
+            failed = true;
+            finished = true;
+        }
+        if finished {
+            control_flow.set_exit();
+        }
+        if let Some(benchmark) = benchmark.as_mut() {
+            if benchmark.update(&mut app, now) {
+                control_flow.set_poll();
+            } else {
+                control_flow.set_exit();
+            }
+        }
+        let window = windowed_context.window();
+        let txn = Transaction::new();
+        // Makes an input-driven change, logging it for `--replay`.
+        let mut perform = |app: &mut App, action: Action| {
+            if let Some(recorder) = recorder.as_mut() {
+                recorder.record(now, &action);
+            }
+            action.apply(app);
+        };
+
+        // Set when a window other than the main one made its context current.
+        let mut context_switched = false;
+
+        match global_event {
+            event::Event::WindowEvent { window_id, event } if window_id != main_window_id => {
+                let index = match mirrors.iter().position(|mirror| mirror.id() == window_id) {
+                    Some(index) => index,
+                    None => return,
+                };
+                match event {
+                    event::WindowEvent::CloseRequested => {
+                        mirrors.remove(index).close();
+                        context_switched = true;
+                        if mirrors.is_empty() && main_closed {
+                            control_flow.set_exit();
+                        }
+                    }
+                    event::WindowEvent::Resized(size) => mirrors[index].resize(size),
+                    event::WindowEvent::ScaleFactorChanged { scale_factor, .. } => mirrors[index]
+                        .set_device_pixel_ratio(effective_scale(scale_factor, &config)),
+                    _ => (),
+                }
+            }
+            event::Event::WindowEvent { event, .. } => match event {
+                // The last window closed ends the demo; the main one is only
+                // hidden, since it holds everything else.
+                event::WindowEvent::CloseRequested if !mirrors.is_empty() => {
+                    window.set_visible(false);
+                    main_closed = true;
+                }
+                event::WindowEvent::CloseRequested => control_flow.set_exit(),
+                event::WindowEvent::Resized(size) => {
+                    window_size = DeviceIntSize::new(size.width as i32, size.height as i32);
+                    // Keep the document at its last real size until the
+                    // window is restored; there is nothing to lay out.
+                    if window_size.is_empty() {
+                        return;
+                    }
+                    windowed_context.resize(size);
+                    if let Some(supersampler) = supersampler.as_mut() {
+                        supersampler.resize(&*gl, window_size);
+                    }
+                    let size = render_size(supersampler.as_ref(), window_size);
+                    perform(
+                        &mut app,
+                        Action::Resize {
+                            width: size.width,
+                            height: size.height,
+                        },
+                    );
+                }
+                event::WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                    perform(
+                        &mut app,
+                        Action::DevicePixelRatio(
+                            effective_scale(scale_factor, &config) * factor as f32,
+                        ),
+                    );
+                }
+                event::WindowEvent::ModifiersChanged(state) => modifiers = state,
+                // While the text field has focus it gets every key.
+                event::WindowEvent::KeyboardInput {
+                    input:
+                        event::KeyboardInput {
+                            state: event::ElementState::Pressed,
+                            virtual_keycode: Some(key),
+                            ..
+                        },
+                    ..
+                } if app.text_input_focused() => match key {
+                    event::VirtualKeyCode::Return => perform(&mut app, Action::SubmitTextInput),
+                    event::VirtualKeyCode::Escape => perform(&mut app, Action::BlurTextInput),
+                    key => {
+                        if let Some(edit) = TextEdit::from_key(key) {
+                            perform(&mut app, Action::EditText(edit));
+                        }
+                    }
+                },
+                event::WindowEvent::ReceivedCharacter(c)
+                    if app.text_input_focused() && !c.is_control() =>
+                {
+                    perform(&mut app, Action::EditText(TextEdit::Insert(c)))
+                }
+                event::WindowEvent::KeyboardInput {
+                    input:
+                        event::KeyboardInput {
+                            state: event::ElementState::Pressed,
+                            virtual_keycode: Some(key),
+                            ..
+                        },
+                    ..
+                } => match key {
+                    // Escape clears the ruler if there is one, and exits otherwise.
+                    event::VirtualKeyCode::Escape if app.has_measurement() => {
+                        perform(&mut app, Action::ClearMeasurement)
+                    }
+                    event::VirtualKeyCode::Escape => control_flow.set_exit(),
+                    event::VirtualKeyCode::M => {
+                        measuring = !measuring;
+                        if !measuring && app.has_measurement() {
+                            perform(&mut app, Action::ClearMeasurement);
+                        }
+                        info!("measure {}", if measuring { "on" } else { "off" });
+                    }
+                    event::VirtualKeyCode::G => perform(&mut app, Action::ToggleGrid),
+                    event::VirtualKeyCode::O => perform(&mut app, Action::ToggleWireframe),
+                    event::VirtualKeyCode::C => perform(&mut app, Action::ToggleClipDebug),
+                    event::VirtualKeyCode::I => perform(&mut app, Action::ToggleImageSampling),
+                    event::VirtualKeyCode::W => perform(&mut app, Action::Wipe),
+                    event::VirtualKeyCode::R => {
+                        let report = renderer.report_memory(ptr::null_mut());
+                        memory_before_recreate = Some(memory::gpu_bytes(&report));
+                        perform(&mut app, Action::RecreateDocument);
+                    }
+                    event::VirtualKeyCode::LBracket => {
+                        perform(&mut app, Action::RotateHue(-HUE_STEP))
+                    }
+                    event::VirtualKeyCode::RBracket => {
+                        perform(&mut app, Action::RotateHue(HUE_STEP))
+                    }
+                    event::VirtualKeyCode::Comma => {
+                        perform(&mut app, Action::RotateRect(-ROTATION_STEP))
+                    }
+                    event::VirtualKeyCode::Period => {
+                        perform(&mut app, Action::RotateRect(ROTATION_STEP))
+                    }
+                    event::VirtualKeyCode::Key0
+                    | event::VirtualKeyCode::Numpad0
+                    | event::VirtualKeyCode::Home => perform(&mut app, Action::ResetView),
+                    event::VirtualKeyCode::S if modifiers.ctrl() && modifiers.shift() => {
+                        match fs::write(&config.export_path, app.export_scene()) {
+                            Ok(()) => info!("saved scene to {}", config.export_path.display()),
+                            Err(err) => {
+                                error!("failed to save {}: {}", config.export_path.display(), err)
+                            }
+                        }
+                    }
+                    event::VirtualKeyCode::P => {
+                        renderer.toggle_debug_flags(DebugFlags::PROFILER_DBG);
+                        app.toggle_debug_hud();
+                        next_hud_memory = frames;
+                    }
+                    event::VirtualKeyCode::E => {
+                        eyedropper = !eyedropper;
+                        info!("eyedropper {}", if eyedropper { "on" } else { "off" });
+                    }
+                    key => {
+                        // Shift scrolls to the rect with that number instead.
+                        match layer_key(key) {
+                            Some(index) if modifiers.shift() => {
+                                perform(&mut app, Action::ScrollTo(index))
+                            }
+                            Some(layer) => perform(&mut app, Action::ToggleLayer(layer)),
+                            None => (),
+                        }
+                    }
+                },
+                event::WindowEvent::CursorMoved { position, .. } => {
+                    cursor_position = DeviceIntPoint::new(position.x as i32, position.y as i32);
+                    let point = cursor_position * factor;
+                    perform(
+                        &mut app,
+                        Action::PointerMoved {
+                            x: point.x,
+                            y: point.y,
+                        },
+                    );
+                }
+                event::WindowEvent::MouseWheel { delta, .. } => {
+                    // Turning the wheel down, or swiping up, scrolls further
+                    // into the content.
+                    let (dx, dy) = match delta {
+                        event::MouseScrollDelta::LineDelta(x, y) => {
+                            let line = WHEEL_LINE_HEIGHT * app.coords().scale_factor;
+                            (-x * line, -y * line)
+                        }
+                        event::MouseScrollDelta::PixelDelta(position) => (
+                            -position.x as f32 * factor as f32,
+                            -position.y as f32 * factor as f32,
+                        ),
+                    };
+                    let point = cursor_position * factor;
+                    perform(
+                        &mut app,
+                        Action::Wheel {
+                            x: point.x,
+                            y: point.y,
+                            dx,
+                            dy,
+                        },
+                    );
+                }
+                event::WindowEvent::Touch(touch) => {
+                    let point =
+                        DeviceIntPoint::new(touch.location.x as i32, touch.location.y as i32)
+                            * factor;
+                    perform(
+                        &mut app,
+                        Action::Touch {
+                            id: touch.id,
+                            phase: touch.phase.into(),
+                            x: point.x,
+                            y: point.y,
+                        },
+                    );
+                }
+                event::WindowEvent::MouseInput {
+                    state,
+                    button: event::MouseButton::Left,
+                    ..
+                } => match state {
+                    event::ElementState::Pressed if eyedropper => pick_at = Some(cursor_position),
+                    event::ElementState::Pressed if measuring => {
+                        let point = cursor_position * factor;
+                        perform(
+                            &mut app,
+                            Action::MeasureClick {
+                                x: point.x,
+                                y: point.y,
+                            },
+                        )
+                    }
+                    event::ElementState::Pressed => {
+                        let point = cursor_position * factor;
+                        perform(
+                            &mut app,
+                            Action::PointerDown {
+                                x: point.x,
+                                y: point.y,
+                            },
+                        )
+                    }
+                    event::ElementState::Released => perform(&mut app, Action::PointerUp),
+                },
+                _ => (),
+            },
+            event::Event::UserEvent(UserEvent::ImageLoaded(image)) => {
+                for mirror in &mut mirrors {
+                    mirror.set_image(image.clone());
+                }
+                app.set_image(image);
+                if let Some(size) = app.image_size().filter(|_| config.fit_image) {
+                    // The image is drawn in document pixels, which
+                    // supersampling makes smaller than window pixels.
+                    fit_window(window, size / factor);
+                }
+            }
+            event::Event::UserEvent(UserEvent::BackdropLoaded(image)) => {
+                for mirror in &mut mirrors {
+                    mirror.set_backdrop(image.clone());
+                }
+                app.set_backdrop(image);
+            }
+            event::Event::UserEvent(UserEvent::SceneReloaded(Ok(file))) => {
+                for mirror in &mut mirrors {
+                    mirror.set_scene_file(Ok(file.clone()));
+                }
+                perform(&mut app, Action::reload_scene(&file))
+            }
+            event::Event::UserEvent(UserEvent::SceneReloaded(Err(report))) => {
+                for mirror in &mut mirrors {
+                    mirror.set_scene_file(Err(report.clone()));
+                }
+                app.set_scene_file(Err(report));
+            }
+            event::Event::UserEvent(UserEvent::Control(request)) => match request.command {
+                control::Command::AddRect(bounds, color) => {
+                    perform(
+                        &mut app,
+                        Action::AddRect {
+                            x: bounds.min.x,
+                            y: bounds.min.y,
+                            width: bounds.width(),
+                            height: bounds.height(),
+                            color: color.to_array(),
+                        },
+                    );
+                    request.reply(Ok(()));
+                }
+                control::Command::Clear => {
+                    perform(&mut app, Action::ClearScene);
+                    request.reply(Ok(()));
+                }
+                control::Command::Zoom(zoom) => {
+                    perform(&mut app, Action::Zoom(zoom));
+                    request.reply(Ok(()));
+                }
+                control::Command::Screenshot(_) => pending_screenshots.push(request),
+            },
+            event::Event::Resumed => {
+                window.set_visible(true);
+                window.focus_window();
+            }
+            _ => (),
+        }
+
+        app.send_transaction(txn);
+        if !mirrors.is_empty() || context_switched {
+            for mirror in &mut mirrors {
+                mirror.render();
+            }
+            windowed_context.make_current();
+        }
+        if main_closed {
+            return;
+        }
+        // Rendering to a zero-sized framebuffer fails, so the frame waits for
+        // the window to be restored.
+        if window_size.is_empty() {
+            if !minimized {
+                info!("window has zero size, pausing rendering");
+                minimized = true;
+            }
+            return;
+        }
+        if minimized {
+            info!("window restored, resuming rendering");
+            minimized = false;
+        }
+        if let Some(delay) = config.frame_delay {
+            // Events arriving meanwhile stay queued until the next iteration.
+            thread::sleep(delay);
+        }
+        let render_started = Instant::now();
+        renderer.update();
+        if let Some(supersampler) = &supersampler {
+            supersampler.bind(&*gl);
+        }
+        let results = renderer
+            .render(render_size(supersampler.as_ref(), window_size), 0)
+            .unwrap();
+        if let Some(supersampler) = &supersampler {
+            supersampler.resolve(&*gl);
+        }
+        let render_time = render_started.elapsed();
+        app.update_pipeline_info(renderer.flush_pipeline_info());
+        frames += 1;
+        if let Some(profile) = profile.as_mut() {
+            let (rects, image_tiles) = app.content_counts();
+            let first_frame = *first_frame.get_or_insert(render_started);
+            profile.write(&FrameProfile {
+                frame: frames,
+                time: render_started.duration_since(first_frame).as_secs_f64(),
+                epoch: app.epoch(),
+                build_ms: profile::millis(app.take_build_time()),
+                render_ms: profile::millis(render_time),
+                scene_build_ms: results.stats.scene_build_time,
+                frame_build_ms: results.stats.frame_build_time,
+                draw_calls: results.stats.total_draw_calls,
+                rects,
+                image_tiles,
+            });
+        }
+        if next_mem_report == Some(frames) {
+            next_mem_report = config.mem_report.map(|every| frames + every);
+            let (rects, tiles) = app.content_counts();
+            info!(
+                "frame {}: {} rects, {} image tiles; {}",
+                frames,
+                rects,
+                tiles,
+                memory::summary(&renderer.report_memory(ptr::null_mut()))
+            );
+        }
+        if app.debug_hud() && frames >= next_hud_memory {
+            next_hud_memory = frames + HUD_MEMORY_FRAMES;
+            app.set_memory_lines(memory::compact(&renderer.report_memory(ptr::null_mut())));
+        }
+        if config.max_frames.is_some_and(|max| frames >= max) {
+            control_flow.set_exit();
+        }
+
+        if let Some(before) = memory_before_recreate.filter(|_| app.is_up_to_date()) {
+            let after = memory::gpu_bytes(&renderer.report_memory(ptr::null_mut()));
+            info!(
+                "GPU memory across document recreation: {:.2} MiB before, {:.2} MiB after",
+                memory::mib(before),
+                memory::mib(after)
+            );
+            memory_before_recreate = None;
+        }
+
+        // The screenshot waits for a frame showing the initial display list.
+        let screenshot = config.screenshot.as_ref().filter(|_| app.is_up_to_date());
+        let requested = if app.is_up_to_date() {
+            mem::take(&mut pending_screenshots)
+        } else {
+            Vec::new()
+        };
+        if config.record.is_some() || screenshot.is_some() || !requested.is_empty() {
+            // `--capture-rect` crops every capture to the part of it inside
+            // the window.
+            let capture_rect = config
+                .capture_rect
+                .unwrap_or_else(|| DeviceIntRect::from_size(window_size));
+            let (capture_size, rgba) = readback::read_region(&*gl, capture_rect, window_size)
+                .unwrap_or_else(|| {
+                    error!(
+                        "capture rect {:?} is outside the {}x{} frame",
+                        capture_rect, window_size.width, window_size.height
+                    );
+                    (DeviceIntSize::zero(), Vec::new())
+                });
+            if let Some(dir) = &config.record {
+                let path = dir.join(format!(
+                    "frame-{:06}.{}",
+                    frames,
+                    config.output_format.extension()
+                ));
+                if let Err(err) = config.output_format.write(&path, capture_size, &rgba) {
+                    error!("failed to record {}: {}", path.display(), err);
+                }
+            }
+            if let Some(path) = screenshot {
+                match config.output_format.write(path, capture_size, &rgba) {
+                    Ok(()) => info!("saved screenshot to {}", path.display()),
+                    Err(err) => error!("failed to save {}: {}", path.display(), err),
+                }
+                control_flow.set_exit();
+            }
+            for request in requested {
+                if let control::Command::Screenshot(path) = &request.command {
+                    let result = config
+                        .output_format
+                        .write(path, capture_size, &rgba)
+                        .map_err(|err| format!("failed to save {}: {}", path.display(), err));
+                    request.reply(result);
+                }
+            }
+        }
+
+        if alpha_test && app.is_up_to_date() {
+            alpha_test = false;
+            let center = DeviceIntPoint::new(window_size.width / 2, window_size.height / 2);
+            println!(
+                "alpha test ({:?} input): expected {:?}, read back {:?}",
+                config.alpha_mode,
+                app.expected_center_color(),
+                readback::read_pixel(&*gl, center, window_size)
+            );
+        }
+
+        if let Some(point) = pick_at.take() {
+            if let Some(color) = readback::read_pixel(&*gl, point, window_size) {
+                info!("picked {:?} at {:?}", color, point);
+                perform(&mut app, Action::PickColor(color.to_array()));
+            }
+        }
+
+        windowed_context.swap_buffers().unwrap();
+    });
+    if let Some(profile) = profile.as_mut() {
+        profile.flush();
+    }
+    if !mirrors.is_empty() {
+        for mirror in mirrors {
+            mirror.close();
+        }
+        windowed_context.make_current();
+    }
+    if let Some(supersampler) = supersampler {
+        supersampler.deinit(&*gl);
+    }
+    renderer.deinit();
+
+    if let Some(tour) = &tour {
+        println!("{}", tour.summary(frames));
+    }
+    if let Some(benchmark) = &benchmark {
+        println!("{}", benchmark.summary(&app));
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// The index selected by a number key: 1 for the first up to 9.
+fn layer_key(key: event::VirtualKeyCode) -> Option<usize> {
+    use event::VirtualKeyCode::*;
+    [Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9]
+        .iter()
+        .position(|&k| k == key)
+}
+
+/// Resizes the window to show an image of `size` window pixels at 1:1, or
+/// as large as fits on the screen with the same aspect ratio.
+fn fit_window(window: &Window, size: DeviceIntSize) {
+    let (width, height) = (size.width as f64, size.height as f64);
+    let scale = match window.current_monitor() {
+        Some(monitor) => {
+            let screen = monitor.size();
+            let max_width = screen.width as f64 * MAX_FIT_FRACTION;
+            let max_height = screen.height as f64 * MAX_FIT_FRACTION;
+            (max_width / width).min(max_height / height).min(1.0)
+        }
+        None => 1.0,
+    };
+    if scale < 1.0 {
+        info!(
+            "image is {}x{}, larger than the screen; fitting it to the window at {:.0}%",
+            size.width,
+            size.height,
+            scale * 100.0
+        );
+    } else {
+        info!(
+            "sizing the window to the {}x{} image",
+            size.width, size.height
+        );
+    }
+    window.set_inner_size(PhysicalSize::new(
+        ((width * scale).round() as u32).max(1),
+        ((height * scale).round() as u32).max(1),
+    ));
+}
+
+/// The largest document width and height the current GL context can draw,
+/// or `--max-document-size` if that is smaller. Documents are capped at it
+/// while the framebuffer keeps the window's size.
+pub fn max_document_size(gl: &dyn gl::Gl, config: &Config) -> i32 {
+    let mut texture = [0];
+    let mut viewport = [0, 0];
+    unsafe {
+        gl.get_integer_v(gl::MAX_TEXTURE_SIZE, &mut texture);
+        gl.get_integer_v(gl::MAX_VIEWPORT_DIMS, &mut viewport);
+    }
+    let limit = texture[0].min(viewport[0]).min(viewport[1]);
+    match config.max_document_size {
+        Some(max) if max < limit => max,
+        _ => limit,
+    }
+}
+
+/// The size the main window's frames are rendered at: its own, or the
+/// supersampled target's.
+fn render_size(supersampler: Option<&Supersampler>, window_size: DeviceIntSize) -> DeviceIntSize {
+    supersampler.map_or(window_size, Supersampler::render_size)
+}
+
+/// Options for every window's renderer.
+pub fn renderer_options(config: &Config) -> RendererOptions {
+    RendererOptions {
+        enable_dithering: config.dither,
+        ..RendererOptions::default()
+    }
+}
+
+/// Applies the `--scale` override to the scale factor reported by the OS.
+fn effective_scale(os_scale: f64, config: &Config) -> f32 {
+    let scale = config.scale.unwrap_or(os_scale as f32);
+    info!("scale factor: OS {}, effective {}", os_scale, scale);
+    scale
+}
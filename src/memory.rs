@@ -0,0 +1,51 @@
+use webrender::api::MemoryReport;
+
+/// Bytes of GPU memory in `report`: textures, render targets and buffers,
+/// leaving out the CPU-side caches.
+pub fn gpu_bytes(report: &MemoryReport) -> usize {
+    report.gpu_cache_textures
+        + report.vertex_data_textures
+        + report.render_target_textures
+        + report.picture_tile_textures
+        + report.atlas_textures
+        + report.standalone_textures
+        + report.texture_cache_structures
+        + report.depth_target_textures
+        + report.texture_upload_pbos
+        + report.swap_chain
+        + report.render_texture_hosts
+        + report.upload_staging_textures
+}
+
+pub fn mib(bytes: usize) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+/// The GPU total and the largest parts of `report`, short enough for the
+/// HUD's status panel.
+pub fn compact(report: &MemoryReport) -> Vec<String> {
+    vec![
+        format!("GPU {:.1} MiB", mib(gpu_bytes(report))),
+        format!(
+            "textures {:.1}, targets {:.1}, tiles {:.1}",
+            mib(report.atlas_textures + report.standalone_textures),
+            mib(report.render_target_textures + report.depth_target_textures),
+            mib(report.picture_tile_textures),
+        ),
+    ]
+}
+
+/// One line covering the parts of `report` that grow with the scene.
+pub fn summary(report: &MemoryReport) -> String {
+    format!(
+        "GPU {:.2} MiB (texture cache {:.2}, render targets {:.2}, GPU cache {:.2}, \
+         picture tiles {:.2}), CPU display lists {:.2} MiB, images {:.2} MiB",
+        mib(gpu_bytes(report)),
+        mib(report.atlas_textures + report.standalone_textures),
+        mib(report.render_target_textures + report.depth_target_textures),
+        mib(report.gpu_cache_textures),
+        mib(report.picture_tile_textures),
+        mib(report.display_list),
+        mib(report.images),
+    )
+}
@@ -0,0 +1,188 @@
+use std::{ops::Deref, rc::Rc, time::Instant};
+
+use gleam::gl::{Gl, GlFns, GlesFns};
+use glutin::{
+    dpi::PhysicalSize,
+    event_loop::EventLoopWindowTarget,
+    window::{WindowBuilder, WindowId},
+    Api, ContextBuilder, PossiblyCurrent, WindowedContext,
+};
+use log::{error, info};
+use webrender::{api::units::DeviceIntSize, api::RenderNotifier, DebugFlags, Renderer};
+
+use crate::{
+    app::App, config::Config, image::TiledImage, max_document_size, renderer_options,
+    scene_file::SceneFile, UserEvent,
+};
+
+/// A window's GL context, which can be made current again through `&mut`
+/// where glutin's `make_current` wants to take it by value.
+pub struct GlWindow {
+    context: Option<WindowedContext<PossiblyCurrent>>,
+}
+
+impl GlWindow {
+    pub fn new(context: WindowedContext<PossiblyCurrent>) -> GlWindow {
+        GlWindow {
+            context: Some(context),
+        }
+    }
+
+    pub fn make_current(&mut self) {
+        let context = self.context.take().unwrap();
+        self.context = Some(match unsafe { context.make_current() } {
+            Ok(context) => context,
+            Err((context, err)) => {
+                error!("failed to make the GL context current: {:?}", err);
+                context
+            }
+        });
+    }
+
+    /// Loads the GL functions of this context, which must be current.
+    pub fn load_gl(&self) -> Result<Rc<dyn Gl>, String> {
+        match self.get_api() {
+            Api::OpenGl => Ok(unsafe { GlFns::load_with(|symbol| self.get_proc_address(symbol)) }),
+            Api::OpenGlEs => {
+                Ok(unsafe { GlesFns::load_with(|symbol| self.get_proc_address(symbol)) })
+            }
+            Api::WebGl => Err("WebGL contexts aren't supported".to_string()),
+        }
+    }
+}
+
+impl Deref for GlWindow {
+    type Target = WindowedContext<PossiblyCurrent>;
+
+    fn deref(&self) -> &WindowedContext<PossiblyCurrent> {
+        self.context.as_ref().unwrap()
+    }
+}
+
+/// An extra window opened by `--windows`, showing the same scene as the
+/// main one with its own renderer and document.
+///
+/// Its context shares objects with the main window's where the platform
+/// allows it. Input other than resizing and closing goes to the main window
+/// only; loaded images and reloaded scenes are passed on to every window.
+pub struct MirrorWindow {
+    context: GlWindow,
+    renderer: Option<Renderer>,
+    app: App,
+    size: DeviceIntSize,
+}
+
+impl MirrorWindow {
+    /// Opens the window and makes its context current.
+    pub fn new(
+        events_loop: &EventLoopWindowTarget<UserEvent>,
+        shared: &GlWindow,
+        notifier: Box<dyn RenderNotifier>,
+        title: &str,
+        config: &Config,
+    ) -> Result<MirrorWindow, String> {
+        let window_builder = WindowBuilder::new().with_title(title);
+        let context = ContextBuilder::new()
+            .with_shared_lists(shared.context())
+            .build_windowed(window_builder, events_loop)
+            .map_err(|err| format!("can't open {}: {}", title, err))?;
+        let context = match unsafe { context.make_current() } {
+            Ok(context) => GlWindow::new(context),
+            Err((_, err)) => return Err(format!("can't open {}: {:?}", title, err)),
+        };
+
+        let gl = context
+            .load_gl()
+            .map_err(|err| format!("can't load GL for {}: {}", title, err))?;
+        let max_document_size = max_document_size(&*gl, config);
+        let (mut renderer, sender) = Renderer::new(gl, notifier, renderer_options(config), None)
+            .map_err(|err| format!("can't create a renderer for {}: {:?}", title, err))?;
+        if config.show_overdraw {
+            renderer.toggle_debug_flags(DebugFlags::SHOW_OVERDRAW);
+        }
+        let size = {
+            let size = context.window().inner_size();
+            DeviceIntSize::new(size.width as i32, size.height as i32)
+        };
+        let device_pixel_ratio = config
+            .scale
+            .unwrap_or(context.window().scale_factor() as f32);
+        let app = App::new(
+            sender.create_api(),
+            size,
+            device_pixel_ratio,
+            max_document_size,
+            config,
+        );
+        info!("opened {}", title);
+
+        Ok(MirrorWindow {
+            context,
+            renderer: Some(renderer),
+            app,
+            size,
+        })
+    }
+
+    pub fn id(&self) -> WindowId {
+        self.context.window().id()
+    }
+
+    pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.size = DeviceIntSize::new(size.width as i32, size.height as i32);
+        if self.size.is_empty() {
+            return;
+        }
+        self.context.resize(size);
+        self.app.resize(self.size);
+    }
+
+    pub fn set_device_pixel_ratio(&mut self, device_pixel_ratio: f32) {
+        self.app.set_device_pixel_ratio(device_pixel_ratio);
+    }
+
+    pub fn set_image(&mut self, image: Result<TiledImage, String>) {
+        self.app.set_image(image);
+    }
+
+    pub fn set_backdrop(&mut self, image: Result<TiledImage, String>) {
+        self.app.set_backdrop(image);
+    }
+
+    pub fn set_scene_file(&mut self, file: Result<SceneFile, String>) {
+        self.app.set_scene_file(file);
+    }
+
+    /// Advances the window's animations; see `App::animate`.
+    pub fn animate(&mut self, now: Instant) -> Option<Instant> {
+        self.app.animate(now)
+    }
+
+    /// Makes the window's context current and renders into it.
+    pub fn render(&mut self) {
+        if self.size.is_empty() {
+            return;
+        }
+        let id = self.id();
+        self.context.make_current();
+        let renderer = self.renderer.as_mut().unwrap();
+        renderer.update();
+        if let Err(errors) = renderer.render(self.size, 0) {
+            error!("failed to render {:?}: {:?}", id, errors);
+        }
+        self.app
+            .update_pipeline_info(renderer.flush_pipeline_info());
+        if let Err(err) = self.context.swap_buffers() {
+            error!("failed to present {:?}: {:?}", id, err);
+        }
+    }
+
+    /// Frees the renderer with the window's context current, then closes
+    /// the window.
+    pub fn close(mut self) {
+        self.context.make_current();
+        if let Some(renderer) = self.renderer.take() {
+            renderer.deinit();
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use log::debug;
+use webrender::api::{
+    units::{LayoutPoint, LayoutRect, LayoutSize, LayoutTransform, LayoutVector2D},
+    ClipId, ColorF, CommonItemProperties, DisplayListBuilder, ItemTag, PropertyBinding,
+    PropertyBindingKey, PropertyValue, ReferenceFrameKind, SpaceAndClipInfo, SpatialTreeItemKey,
+    TransformStyle,
+};
+
+use crate::{
+    clip_debug::ClipLog,
+    hit_map::{HitMap, SCROLL_FRAME_KIND},
+    palette::Palette,
+};
+
+/// Most frames `--nested-scroll` nests, one property binding key each.
+pub const MAX_SCROLL_FRAMES: usize = 8;
+
+/// Largest share of the scene the outermost frame takes up.
+const OUTER_SHARE: f32 = 0.8;
+
+/// Each frame's content is this many times its size, in both directions.
+const CONTENT_SCALE: f32 = 2.0;
+
+/// Each inner frame is this share of its parent's size, this far from the
+/// top left corner of the parent's content.
+const INNER_SHARE: f32 = 0.6;
+const INNER_INSET: f32 = 48.0;
+
+/// Side of the checkerboard squares the content is painted with, so that
+/// scrolling shows.
+const CHECKER_SIZE: f32 = 40.0;
+
+/// How much darker the checkerboard's dark squares are.
+const CHECKER_SHADE: f32 = 0.7;
+
+/// One scroll frame: a window of `frame` size onto `content` scrolled by
+/// `offset`, both in its parent's content space.
+struct ScrollFrame {
+    frame: LayoutRect,
+    content: LayoutSize,
+    offset: LayoutVector2D,
+    color: ColorF,
+    key: PropertyBindingKey<LayoutTransform>,
+}
+
+impl ScrollFrame {
+    /// The offset clamped to keep the content covering the frame.
+    fn clamp(&self, offset: LayoutVector2D) -> LayoutVector2D {
+        let max = self.content - self.frame.size();
+        LayoutVector2D::new(
+            offset.x.max(0.0).min(max.width.max(0.0)),
+            offset.y.max(0.0).min(max.height.max(0.0)),
+        )
+    }
+
+    fn transform(&self) -> LayoutTransform {
+        LayoutTransform::translation(-self.offset.x, -self.offset.y, 0.0)
+    }
+}
+
+/// The `--nested-scroll` scene: scroll frames each nested in the content of
+/// the one before, scrolled by the wheel.
+///
+/// A frame's content sits in a reference frame whose transform is a
+/// property binding, so scrolling only updates bindings, in the same way
+/// as the hover highlight. Each frame has a hit-test area, tagged with its
+/// index, in its parent's content, so the front one under the cursor is
+/// the innermost. The wheel scrolls that one first, and whatever it can't
+/// take on each axis, at the end of its range, chains to its parent.
+pub struct NestedScroll {
+    frames: Vec<ScrollFrame>,
+}
+
+impl NestedScroll {
+    /// Lays out `count` frames, up to `MAX_SCROLL_FRAMES`, centered in a
+    /// scene of `size`, bound to consecutive keys from `first_key`.
+    pub fn new(count: usize, size: LayoutSize, first_key: u64, palette: Palette) -> NestedScroll {
+        let outer = LayoutSize::new(size.width * OUTER_SHARE, size.height * OUTER_SHARE);
+        let mut frame =
+            LayoutRect::from_origin_and_size(((size - outer) / 2.0).to_vector().to_point(), outer);
+        let frames = (0..count.min(MAX_SCROLL_FRAMES))
+            .map(|i| {
+                let scroll_frame = ScrollFrame {
+                    frame,
+                    content: frame.size() * CONTENT_SCALE,
+                    offset: LayoutVector2D::zero(),
+                    color: palette.color(i),
+                    key: PropertyBindingKey::new(first_key + i as u64),
+                };
+                frame = LayoutRect::from_origin_and_size(
+                    LayoutPoint::new(INNER_INSET, INNER_INSET),
+                    frame.size() * INNER_SHARE,
+                );
+                scroll_frame
+            })
+            .collect();
+        NestedScroll { frames }
+    }
+
+    /// Scrolls by `delta` the frame tagged first in `tags`, chaining what it
+    /// can't take to the frames outside it. Returns whether any offset
+    /// changed.
+    pub fn scroll(&mut self, tags: &[ItemTag], delta: LayoutVector2D) -> bool {
+        let innermost = tags
+            .iter()
+            .find(|&&(_, kind)| kind == SCROLL_FRAME_KIND)
+            .map(|&(index, _)| index as usize);
+        let innermost = match innermost {
+            Some(index) if index < self.frames.len() => index,
+            _ => return false,
+        };
+
+        let mut remaining = delta;
+        let mut changed = false;
+        for (index, frame) in self.frames[..=innermost].iter_mut().enumerate().rev() {
+            let offset = frame.clamp(frame.offset + remaining);
+            let taken = offset - frame.offset;
+            if taken != LayoutVector2D::zero() {
+                debug!("scroll frame {} to {:?}", index, offset);
+                frame.offset = offset;
+                changed = true;
+            }
+            remaining -= taken;
+            if remaining == LayoutVector2D::zero() {
+                break;
+            }
+        }
+        changed
+    }
+
+    /// The transform of each frame's content, for a binding update.
+    pub fn bindings(&self) -> Vec<PropertyValue<LayoutTransform>> {
+        self.frames
+            .iter()
+            .map(|frame| frame.key.with(frame.transform()))
+            .collect()
+    }
+
+    /// Pushes the frames, each clipped to itself and to the frames around
+    /// it. The hit map gets each frame's area where it was on the last
+    /// rebuild, since scrolling doesn't rebuild.
+    pub fn push(
+        &self,
+        builder: &mut DisplayListBuilder,
+        hits: &mut HitMap,
+        clips: &mut ClipLog,
+        space_and_clip: SpaceAndClipInfo,
+    ) {
+        let mut parent = space_and_clip;
+        let mut parent_chain = None;
+        // Where the current parent's content origin is in `space_and_clip`.
+        let mut origin = LayoutVector2D::zero();
+        for (index, frame) in self.frames.iter().enumerate() {
+            hits.push_area(
+                builder,
+                (index as u64, SCROLL_FRAME_KIND),
+                "scroll frame",
+                frame.frame,
+                frame.frame.translate(origin),
+                parent,
+            );
+
+            let clip = clips.define_clip_rect(builder, parent.spatial_id, frame.frame);
+            let chain_clips = match parent_chain {
+                Some(_) => vec![clip],
+                None => vec![space_and_clip.clip_id, clip],
+            };
+            let chain = clips.define_clip_chain(builder, parent_chain, chain_clips);
+            let content = builder.push_reference_frame(
+                frame.frame.min,
+                parent.spatial_id,
+                TransformStyle::Flat,
+                PropertyBinding::Binding(frame.key, frame.transform()),
+                ReferenceFrameKind::Transform {
+                    is_2d_scale_translation: true,
+                    should_snap: false,
+                    paired_with_perspective: false,
+                },
+                SpatialTreeItemKey::new(4, index as u64),
+            );
+            parent = SpaceAndClipInfo {
+                spatial_id: content,
+                clip_id: ClipId::ClipChain(chain),
+            };
+            parent_chain = Some(chain);
+            origin += frame.frame.min.to_vector() - frame.offset;
+            push_checkerboard(builder, frame.content, frame.color, parent);
+        }
+        for _ in &self.frames {
+            builder.pop_reference_frame();
+        }
+    }
+}
+
+/// Fills `size` from the origin with `color`, with every other square
+/// darker.
+fn push_checkerboard(
+    builder: &mut DisplayListBuilder,
+    size: LayoutSize,
+    color: ColorF,
+    space_and_clip: SpaceAndClipInfo,
+) {
+    let bounds = LayoutRect::from_size(size);
+    builder.push_rect(
+        &CommonItemProperties::new(bounds, space_and_clip),
+        bounds,
+        color,
+    );
+    let dark = ColorF::new(
+        color.r * CHECKER_SHADE,
+        color.g * CHECKER_SHADE,
+        color.b * CHECKER_SHADE,
+        color.a,
+    );
+    let columns = (size.width / CHECKER_SIZE).ceil() as usize;
+    let rows = (size.height / CHECKER_SIZE).ceil() as usize;
+    for row in 0..rows {
+        for column in (row & 1..columns).step_by(2) {
+            let square = LayoutRect::from_origin_and_size(
+                LayoutPoint::new(column as f32 * CHECKER_SIZE, row as f32 * CHECKER_SIZE),
+                LayoutSize::new(CHECKER_SIZE, CHECKER_SIZE),
+            )
+            .intersection(&bounds)
+            .unwrap_or(bounds);
+            builder.push_rect(
+                &CommonItemProperties::new(square, space_and_clip),
+                square,
+                dark,
+            );
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+
+use log::{debug, warn};
+use webrender::{
+    api::{DocumentId, Epoch, PipelineId},
+    PipelineInfo,
+};
+
+/// Number of rendered frames a submitted epoch may stay uncomposited before
+/// it is reported as stalled.
+pub const STALLED_FRAME_THRESHOLD: u32 = 30;
+
+struct Pending {
+    epoch: Epoch,
+    frames: u32,
+    warned: bool,
+}
+
+/// Tracks submitted vs. rendered epochs per pipeline, using the result of
+/// `Renderer::flush_pipeline_info` after each frame.
+pub struct PipelineTracker {
+    pending: HashMap<(PipelineId, DocumentId), Pending>,
+    rendered: HashMap<(PipelineId, DocumentId), Epoch>,
+    threshold: u32,
+    /// Epochs that were superseded before they were ever rendered.
+    skipped: u64,
+}
+
+impl PipelineTracker {
+    pub fn new(threshold: u32) -> PipelineTracker {
+        PipelineTracker {
+            pending: HashMap::new(),
+            rendered: HashMap::new(),
+            threshold,
+            skipped: 0,
+        }
+    }
+
+    /// Records that `epoch` has been sent for `pipeline_id` in `document_id`.
+    ///
+    /// While an earlier epoch is still waiting, its frame count carries
+    /// over: a pipeline that gets a new display list every frame has to
+    /// still be reported once none of them is composited.
+    pub fn submit(&mut self, pipeline_id: PipelineId, document_id: DocumentId, epoch: Epoch) {
+        self.pending
+            .entry((pipeline_id, document_id))
+            .and_modify(|pending| pending.epoch = epoch)
+            .or_insert(Pending {
+                epoch,
+                frames: 0,
+                warned: false,
+            });
+    }
+
+    /// Drops everything known about `document_id` once it has been deleted.
+    pub fn forget_document(&mut self, document_id: DocumentId) {
+        self.pending
+            .retain(|&(_, document), _| document != document_id);
+        self.rendered
+            .retain(|&(_, document), _| document != document_id);
+    }
+
+    /// Whether every submitted epoch has been rendered.
+    pub fn is_idle(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// How many submitted epochs were replaced by a later one before a frame
+    /// showed them. Epochs are expected to be numbered consecutively.
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// One line per pipeline, for the HUD: the last epoch rendered, and the
+    /// one waited on once it has stalled.
+    pub fn status_lines(&self) -> Vec<String> {
+        let mut keys: Vec<_> = self
+            .rendered
+            .keys()
+            .chain(self.pending.keys())
+            .copied()
+            .collect();
+        keys.sort();
+        keys.dedup();
+
+        let mut lines: Vec<String> = keys
+            .iter()
+            .map(|key| {
+                let rendered = match self.rendered.get(key) {
+                    Some(epoch) => format!("epoch {}", epoch.0),
+                    None => "nothing".to_string(),
+                };
+                let mut line = format!("pipeline {},{}: {}", key.0 .0, key.0 .1, rendered);
+                if let Some(pending) = self.pending.get(key).filter(|pending| pending.warned) {
+                    line.push_str(&format!(", epoch {} stalled", pending.epoch.0));
+                }
+                line
+            })
+            .collect();
+        lines.push(format!("skipped epochs: {}", self.skipped));
+        lines
+    }
+
+    /// Consumes the pipeline info of the frame that was just rendered.
+    pub fn update(&mut self, info: PipelineInfo) {
+        for (key, epoch) in info.epochs {
+            debug!("pipeline {:?} rendered {:?}", key, epoch);
+            if let Some(previous) = self.rendered.insert(key, epoch) {
+                self.skipped += epoch.0.saturating_sub(previous.0 + 1) as u64;
+            }
+            match self.pending.get_mut(&key) {
+                Some(pending) if pending.epoch <= epoch => {
+                    self.pending.remove(&key);
+                }
+                // An older epoch got through, so the pipeline isn't stuck,
+                // only behind.
+                Some(pending) => {
+                    pending.frames = 0;
+                    pending.warned = false;
+                }
+                None => (),
+            }
+        }
+
+        for key in info.removed_pipelines {
+            self.rendered.remove(&key);
+            self.pending.remove(&key);
+        }
+
+        for ((pipeline_id, document_id), pending) in self.pending.iter_mut() {
+            pending.frames += 1;
+            if pending.frames >= self.threshold && !pending.warned {
+                pending.warned = true;
+                warn!(
+                    "pipeline {:?} in {:?}: {:?} not composited after {} frames (last rendered {:?})",
+                    pipeline_id,
+                    document_id,
+                    pending.epoch,
+                    pending.frames,
+                    self.rendered.get(&(*pipeline_id, *document_id)),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use webrender::api::IdNamespace;
+
+    use super::*;
+
+    const PIPELINE: PipelineId = PipelineId(0, 0);
+    const DOCUMENT: DocumentId = DocumentId {
+        namespace_id: IdNamespace(0),
+        id: 0,
+    };
+
+    /// Pipeline info of a frame that rendered `epoch` of `PIPELINE`, or
+    /// nothing new.
+    fn frame(epoch: Option<u32>) -> PipelineInfo {
+        PipelineInfo {
+            epochs: epoch
+                .map(|epoch| ((PIPELINE, DOCUMENT), Epoch(epoch)))
+                .into_iter()
+                .collect(),
+            removed_pipelines: Vec::new(),
+        }
+    }
+
+    fn is_stalled(tracker: &PipelineTracker) -> bool {
+        tracker.pending.values().any(|pending| pending.warned)
+    }
+
+    #[test]
+    fn reports_an_epoch_left_waiting() {
+        let mut tracker = PipelineTracker::new(3);
+        tracker.submit(PIPELINE, DOCUMENT, Epoch(0));
+        tracker.update(frame(None));
+        tracker.update(frame(None));
+        assert!(!is_stalled(&tracker));
+        tracker.update(frame(None));
+        assert!(is_stalled(&tracker));
+        assert_eq!(
+            tracker.status_lines(),
+            [
+                "pipeline 0,0: nothing, epoch 0 stalled",
+                "skipped epochs: 0"
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_counting_while_new_epochs_are_sent() {
+        let mut tracker = PipelineTracker::new(3);
+        for epoch in 0..3 {
+            tracker.submit(PIPELINE, DOCUMENT, Epoch(epoch));
+            tracker.update(frame(None));
+        }
+        assert!(is_stalled(&tracker));
+    }
+
+    #[test]
+    fn starts_counting_again_once_the_latest_epoch_renders() {
+        let mut tracker = PipelineTracker::new(3);
+        tracker.submit(PIPELINE, DOCUMENT, Epoch(0));
+        tracker.update(frame(Some(0)));
+        tracker.submit(PIPELINE, DOCUMENT, Epoch(1));
+        tracker.update(frame(None));
+        tracker.submit(PIPELINE, DOCUMENT, Epoch(2));
+        tracker.update(frame(Some(2)));
+        assert!(tracker.is_idle());
+        assert_eq!(tracker.skipped(), 1);
+
+        tracker.submit(PIPELINE, DOCUMENT, Epoch(3));
+        tracker.update(frame(None));
+        tracker.update(frame(None));
+        assert!(!is_stalled(&tracker));
+        assert_eq!(
+            tracker.status_lines(),
+            ["pipeline 0,0: epoch 2", "skipped epochs: 1"]
+        );
+    }
+
+    #[test]
+    fn starts_counting_again_when_an_older_epoch_renders() {
+        let mut tracker = PipelineTracker::new(3);
+        tracker.submit(PIPELINE, DOCUMENT, Epoch(0));
+        tracker.update(frame(None));
+        tracker.submit(PIPELINE, DOCUMENT, Epoch(1));
+        tracker.update(frame(None));
+        tracker.submit(PIPELINE, DOCUMENT, Epoch(2));
+        tracker.update(frame(Some(0)));
+        tracker.update(frame(None));
+        assert!(!is_stalled(&tracker));
+        tracker.update(frame(None));
+        assert!(is_stalled(&tracker));
+    }
+}
@@ -0,0 +1,127 @@
+use webrender::api::ColorF;
+
+use crate::color::Hsv;
+
+/// The golden ratio's fractional part. Stepping by it never comes back to
+/// a value already used, and each new one lands in the largest gap left.
+const GOLDEN_STEP: f64 = 0.618_033_988_749_894_9;
+
+/// Seed of `--palette random` without one of its own.
+const DEFAULT_SEED: u64 = 1;
+
+/// Saturation and value of the golden palette's colors.
+const GOLDEN_SATURATION: f32 = 0.65;
+const GOLDEN_VALUE: f32 = 0.95;
+
+/// Darkest and lightest gray of the grayscale palette.
+const GRAY_RANGE: (f32, f32) = (0.15, 0.9);
+
+/// How generated scenes pick their colors, for `--palette`.
+///
+/// Every strategy gives the color for an index directly, so the same scene
+/// gets the same colors however many it needs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Palette {
+    /// Uniformly random RGB from a seeded generator.
+    Random(u64),
+    /// Hues a golden ratio turn apart, at one saturation and value.
+    Golden,
+    /// Grays stepped by the golden ratio between dark and light.
+    Grayscale,
+}
+
+impl Palette {
+    /// Parses `random`, `random:SEED`, `golden` or `grayscale`.
+    pub fn from_name(name: &str) -> Option<Palette> {
+        match name {
+            "random" => Some(Palette::Random(DEFAULT_SEED)),
+            "golden" => Some(Palette::Golden),
+            "grayscale" => Some(Palette::Grayscale),
+            _ => {
+                let seed = name.strip_prefix("random:")?;
+                seed.parse().ok().map(Palette::Random)
+            }
+        }
+    }
+
+    /// The opaque color at `index`.
+    pub fn color(self, index: usize) -> ColorF {
+        let step = golden_step(index);
+        match self {
+            Palette::Random(seed) => {
+                let bits = splitmix64(seed ^ splitmix64(index as u64));
+                let channel = |shift: u32| ((bits >> shift) & 0xff) as f32 / 255.0;
+                ColorF::new(channel(0), channel(8), channel(16), 1.0)
+            }
+            Palette::Golden => Hsv {
+                hue: step * 360.0,
+                saturation: GOLDEN_SATURATION,
+                value: GOLDEN_VALUE,
+                alpha: 1.0,
+            }
+            .to_rgb(),
+            Palette::Grayscale => {
+                let (dark, light) = GRAY_RANGE;
+                let gray = dark + step * (light - dark);
+                ColorF::new(gray, gray, gray, 1.0)
+            }
+        }
+    }
+}
+
+/// How far round, from 0 to 1, `index` golden ratio steps land. Worked
+/// out in f64, since in f32 the product loses its fractional digits
+/// within the first few million indices.
+fn golden_step(index: usize) -> f32 {
+    (index as f64 * GOLDEN_STEP).fract() as f32
+}
+
+/// One round of the SplitMix64 generator, a cheap, well mixed hash of `x`.
+pub fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A golden ratio turn is about 222.5 degrees, 137.5 the short way.
+    const MIN_HUE_GAP: f32 = 137.0;
+
+    fn hue_gap(a: f32, b: f32) -> f32 {
+        let gap = (a - b).rem_euclid(360.0);
+        gap.min(360.0 - gap)
+    }
+
+    #[test]
+    fn consecutive_golden_hues_are_far_apart() {
+        let hue = |index| Hsv::from_rgb(Palette::Golden.color(index)).hue;
+        for index in 0..1000 {
+            let gap = hue_gap(hue(index), hue(index + 1));
+            assert!(gap >= MIN_HUE_GAP, "{} degrees after {}", gap, index);
+        }
+    }
+
+    #[test]
+    fn golden_steps_stay_apart_at_large_indices() {
+        for start in [0, 1 << 20, 10_000_000, 1 << 30] {
+            for index in start..start + 1000 {
+                let gap = hue_gap(golden_step(index) * 360.0, golden_step(index + 1) * 360.0);
+                assert!(gap >= MIN_HUE_GAP, "{} degrees after {}", gap, index);
+            }
+        }
+    }
+
+    #[test]
+    fn colors_are_opaque_and_repeatable() {
+        for palette in [Palette::Random(7), Palette::Golden, Palette::Grayscale] {
+            for index in 0..100 {
+                assert_eq!(palette.color(index).a, 1.0);
+                assert_eq!(palette.color(index), palette.color(index));
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use webrender::api::{
+    units::{LayoutPoint, LayoutRect, LayoutSize, LayoutVector2D},
+    ColorF, CommonItemProperties, DisplayListBuilder, SpaceAndClipInfo,
+};
+
+use crate::palette::{splitmix64, Palette};
+
+/// Side of each particle, in layout pixels.
+const PARTICLE_SIZE: f32 = 4.0;
+
+/// Downward acceleration, in layout pixels per second squared.
+const GRAVITY: f32 = 900.0;
+
+/// Fastest a particle starts out, in layout pixels per second.
+const MAX_START_SPEED: f32 = 400.0;
+
+/// Longest step `ParticleSystem::step` takes at once, so that a stalled
+/// frame doesn't throw particles through the walls.
+const MAX_STEP: f32 = 0.05;
+
+struct Particle {
+    position: LayoutPoint,
+    velocity: LayoutVector2D,
+    color: ColorF,
+}
+
+/// The `--particles` scene: small rects falling under gravity and bouncing
+/// off the edges without losing speed, all pushed as items of their own.
+///
+/// The same seed and count always start the same way.
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+}
+
+impl ParticleSystem {
+    /// Scatters `count` particles over `size` with random velocities.
+    pub fn new(count: usize, seed: u64, size: LayoutSize, palette: Palette) -> ParticleSystem {
+        // A uniform value from 0 to 1 for each particle and field.
+        let random = |index: usize, field: u64| {
+            let bits = splitmix64(seed ^ splitmix64((index as u64) << 2 | field));
+            (bits >> 40) as f32 / (1u64 << 24) as f32
+        };
+        let particles = (0..count)
+            .map(|i| Particle {
+                position: LayoutPoint::new(
+                    random(i, 0) * (size.width - PARTICLE_SIZE).max(0.0),
+                    random(i, 1) * (size.height - PARTICLE_SIZE).max(0.0),
+                ),
+                velocity: LayoutVector2D::new(random(i, 2) * 2.0 - 1.0, random(i, 3) * 2.0 - 1.0)
+                    * MAX_START_SPEED,
+                color: palette.color(i),
+            })
+            .collect();
+        ParticleSystem { particles }
+    }
+
+    /// Moves every particle on by `elapsed` seconds within `size`.
+    pub fn step(&mut self, elapsed: f32, size: LayoutSize) {
+        let dt = elapsed.min(MAX_STEP);
+        let max = LayoutPoint::new(
+            (size.width - PARTICLE_SIZE).max(0.0),
+            (size.height - PARTICLE_SIZE).max(0.0),
+        );
+        for particle in &mut self.particles {
+            particle.velocity.y += GRAVITY * dt;
+            particle.position += particle.velocity * dt;
+            // Reflect off each edge, folding the overshoot back inside.
+            let bounce = |position: &mut f32, velocity: &mut f32, max: f32| {
+                if *position < 0.0 {
+                    *position = (-*position).min(max);
+                    *velocity = velocity.abs();
+                } else if *position > max {
+                    *position = (2.0 * max - *position).max(0.0);
+                    *velocity = -velocity.abs();
+                }
+            };
+            bounce(&mut particle.position.x, &mut particle.velocity.x, max.x);
+            bounce(&mut particle.position.y, &mut particle.velocity.y, max.y);
+        }
+    }
+
+    pub fn push(&self, builder: &mut DisplayListBuilder, space_and_clip: SpaceAndClipInfo) {
+        let size = LayoutSize::new(PARTICLE_SIZE, PARTICLE_SIZE);
+        for particle in &self.particles {
+            let bounds = LayoutRect::from_origin_and_size(particle.position, size);
+            builder.push_rect(
+                &CommonItemProperties::new(bounds, space_and_clip),
+                bounds,
+                particle.color,
+            );
+        }
+    }
+}
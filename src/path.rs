@@ -0,0 +1,418 @@
+use webrender::api::units::{LayoutPoint, LayoutRect, LayoutSize};
+
+/// Height of the horizontal bands a path is filled with.
+const SCANLINE_HEIGHT: f32 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Segment {
+    MoveTo(LayoutPoint),
+    LineTo(LayoutPoint),
+    CubicTo(LayoutPoint, LayoutPoint, LayoutPoint),
+    Close,
+}
+
+/// A parsed subset of SVG path data: `M`, `L`, `C` and `Z`, in absolute or
+/// relative form.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgPath {
+    segments: Vec<Segment>,
+}
+
+enum Token {
+    Command(char),
+    Number(f32),
+}
+
+fn tokenize(mut data: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+
+    while let Some(c) = data.chars().next() {
+        if c.is_whitespace() || c == ',' {
+            data = &data[c.len_utf8()..];
+        } else if c.is_ascii_alphabetic() {
+            tokens.push(Token::Command(c));
+            data = &data[1..];
+        } else {
+            let len = number_len(data);
+            if len == 0 {
+                return Err(format!("unexpected `{}` in path data", c));
+            }
+            let number = &data[..len];
+            tokens.push(Token::Number(
+                number
+                    .parse()
+                    .map_err(|_| format!("invalid number `{}` in path data", number))?,
+            ));
+            data = &data[len..];
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Length of the number at the start of `s`. Numbers can follow each other
+/// without a separator, as in `1.5.5` or `10-20`.
+fn number_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    if matches!(bytes.first(), Some(b'+' | b'-')) {
+        i += 1;
+    }
+    let mut seen_dot = false;
+    while i < bytes.len() && (bytes[i].is_ascii_digit() || (bytes[i] == b'.' && !seen_dot)) {
+        seen_dot |= bytes[i] == b'.';
+        i += 1;
+    }
+    if matches!(bytes.get(i), Some(b'e' | b'E')) {
+        let mut j = i + 1;
+        if matches!(bytes.get(j), Some(b'+' | b'-')) {
+            j += 1;
+        }
+        if bytes.get(j).is_some_and(u8::is_ascii_digit) {
+            i = j;
+            while i < bytes.len() && bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+        }
+    }
+    i
+}
+
+impl SvgPath {
+    pub fn parse(data: &str) -> Result<SvgPath, String> {
+        let tokens = tokenize(data)?;
+        let mut segments = Vec::new();
+        let mut i = 0;
+        let mut command = None;
+        let mut current = LayoutPoint::zero();
+        let mut subpath_start = LayoutPoint::zero();
+
+        while i < tokens.len() {
+            if let Token::Command(c) = tokens[i] {
+                command = Some(c);
+                i += 1;
+            }
+            let c = command.ok_or("expected a command before numbers in path data")?;
+            let relative = c.is_ascii_lowercase();
+
+            let arity = match c.to_ascii_uppercase() {
+                'M' | 'L' => 2,
+                'C' => 6,
+                'Z' => 0,
+                _ => return Err(format!("unsupported path command `{}`", c)),
+            };
+
+            let mut args = [0.0; 6];
+            for arg in &mut args[..arity] {
+                match tokens.get(i) {
+                    Some(&Token::Number(n)) => *arg = n,
+                    _ => return Err(format!("`{}` expects {} numbers", c, arity)),
+                }
+                i += 1;
+            }
+            let point = |x: f32, y: f32| {
+                let point = LayoutPoint::new(x, y);
+                if relative {
+                    point + current.to_vector()
+                } else {
+                    point
+                }
+            };
+
+            match c.to_ascii_uppercase() {
+                'M' => {
+                    current = point(args[0], args[1]);
+                    subpath_start = current;
+                    segments.push(Segment::MoveTo(current));
+                    // Further coordinate pairs after a move are lines.
+                    command = Some(if relative { 'l' } else { 'L' });
+                }
+                'L' => {
+                    current = point(args[0], args[1]);
+                    segments.push(Segment::LineTo(current));
+                }
+                'C' => {
+                    let (c1, c2) = (point(args[0], args[1]), point(args[2], args[3]));
+                    current = point(args[4], args[5]);
+                    segments.push(Segment::CubicTo(c1, c2, current));
+                }
+                _ => {
+                    current = subpath_start;
+                    segments.push(Segment::Close);
+                    command = None;
+                }
+            }
+        }
+
+        Ok(SvgPath { segments })
+    }
+
+    /// Flattens the path into closed polygons, approximating curves with
+    /// lines that stray no more than `tolerance` from them.
+    pub fn flatten(&self, tolerance: f32) -> Vec<Vec<LayoutPoint>> {
+        let mut polygons = Vec::new();
+        let mut polygon: Vec<LayoutPoint> = Vec::new();
+
+        for &segment in &self.segments {
+            match segment {
+                Segment::MoveTo(point) => {
+                    if polygon.len() > 2 {
+                        polygons.push(polygon);
+                    }
+                    polygon = vec![point];
+                }
+                Segment::LineTo(point) => polygon.push(point),
+                Segment::CubicTo(c1, c2, to) => {
+                    let from = polygon.last().copied().unwrap_or_default();
+                    flatten_cubic(&mut polygon, from, c1, c2, to, tolerance);
+                }
+                Segment::Close => {
+                    let start = polygon.first().copied();
+                    if polygon.len() > 2 {
+                        polygons.push(polygon);
+                    }
+                    polygon = start.into_iter().collect();
+                }
+            }
+        }
+        if polygon.len() > 2 {
+            polygons.push(polygon);
+        }
+
+        polygons
+    }
+}
+
+/// Appends points along a cubic bezier to `out`, with enough segments to
+/// stay within `tolerance` of the curve.
+fn flatten_cubic(
+    out: &mut Vec<LayoutPoint>,
+    p0: LayoutPoint,
+    p1: LayoutPoint,
+    p2: LayoutPoint,
+    p3: LayoutPoint,
+    tolerance: f32,
+) {
+    // The flatness bound from Wang's formula: the largest second difference
+    // of the control points limits how far a chord can drift from the curve.
+    let dd = |a: LayoutPoint, b: LayoutPoint, c: LayoutPoint| {
+        (a.to_vector() - b.to_vector() * 2.0 + c.to_vector()).length()
+    };
+    let deviation = dd(p0, p1, p2).max(dd(p1, p2, p3));
+    let steps = (0.75 * deviation / tolerance).sqrt().ceil().max(1.0) as usize;
+
+    for step in 1..=steps {
+        let t = step as f32 / steps as f32;
+        let mt = 1.0 - t;
+        let point = p0.to_vector() * (mt * mt * mt)
+            + p1.to_vector() * (3.0 * mt * mt * t)
+            + p2.to_vector() * (3.0 * mt * t * t)
+            + p3.to_vector() * (t * t * t);
+        out.push(point.to_point());
+    }
+}
+
+/// Covers the inside of `polygons` with one rect per filled span of each
+/// scanline, using the nonzero fill rule.
+pub fn fill_spans(polygons: &[Vec<LayoutPoint>]) -> Vec<LayoutRect> {
+    let edges: Vec<(LayoutPoint, LayoutPoint)> = polygons
+        .iter()
+        .flat_map(|polygon| {
+            polygon
+                .iter()
+                .zip(polygon.iter().cycle().skip(1))
+                .map(|(&a, &b)| (a, b))
+        })
+        .filter(|(a, b)| a.y != b.y)
+        .collect();
+
+    if edges.is_empty() {
+        return Vec::new();
+    }
+    let (min_y, max_y) = edges
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(min, max), (a, b)| {
+            (min.min(a.y).min(b.y), max.max(a.y).max(b.y))
+        });
+
+    let mut spans = Vec::new();
+    let mut crossings = Vec::new();
+    let mut y = (min_y / SCANLINE_HEIGHT).floor() * SCANLINE_HEIGHT;
+
+    while y < max_y {
+        let sample = y + SCANLINE_HEIGHT / 2.0;
+        crossings.clear();
+        for &(a, b) in &edges {
+            let (top, bottom, winding) = if a.y < b.y { (a, b, 1) } else { (b, a, -1) };
+            if top.y <= sample && sample < bottom.y {
+                let x = top.x + (sample - top.y) * (bottom.x - top.x) / (bottom.y - top.y);
+                crossings.push((x, winding));
+            }
+        }
+        crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut winding = 0;
+        for pair in crossings.windows(2) {
+            winding += pair[0].1;
+            if winding != 0 && pair[1].0 > pair[0].0 {
+                spans.push(LayoutRect::from_origin_and_size(
+                    LayoutPoint::new(pair[0].0, y),
+                    LayoutSize::new(pair[1].0 - pair[0].0, SCANLINE_HEIGHT),
+                ));
+            }
+        }
+
+        y += SCANLINE_HEIGHT;
+    }
+
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-3;
+
+    fn points(points: &[(f32, f32)]) -> Vec<LayoutPoint> {
+        points
+            .iter()
+            .map(|&(x, y)| LayoutPoint::new(x, y))
+            .collect()
+    }
+
+    fn area(spans: &[LayoutRect]) -> f32 {
+        spans.iter().map(|span| span.area()).sum()
+    }
+
+    #[test]
+    fn lines_flatten_to_their_vertices() {
+        let path = SvgPath::parse("M0,0 L100,0 L50,100 Z").unwrap();
+        assert_eq!(
+            path.flatten(0.25),
+            vec![points(&[(0.0, 0.0), (100.0, 0.0), (50.0, 100.0)])]
+        );
+    }
+
+    #[test]
+    fn relative_and_implicit_commands_flatten_like_absolute_ones() {
+        let absolute = SvgPath::parse("M10,10 L30,10 L30,30 L10,30 Z").unwrap();
+        let relative = SvgPath::parse("m10,10 20,0 0,20 -20,0 z").unwrap();
+        assert_eq!(relative.flatten(0.25), absolute.flatten(0.25));
+    }
+
+    #[test]
+    fn each_subpath_is_its_own_polygon() {
+        let path = SvgPath::parse("M0,0 L10,0 L10,10 Z M20,0 L30,0 L30,10 Z").unwrap();
+        assert_eq!(
+            path.flatten(0.25),
+            vec![
+                points(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)]),
+                points(&[(20.0, 0.0), (30.0, 0.0), (30.0, 10.0)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn curves_stay_within_the_tolerance() {
+        let path = SvgPath::parse("M0,0 C0,100 100,100 100,0 Z").unwrap();
+        for tolerance in [0.1, 1.0, 5.0] {
+            let polygons = path.flatten(tolerance);
+            assert_eq!(polygons.len(), 1);
+            let polygon = &polygons[0];
+            assert_eq!(polygon.last(), Some(&LayoutPoint::new(100.0, 0.0)));
+
+            // The curve is symmetric, peaking at y = 75 halfway along. The
+            // flattened points are on the curve, so the chords can only
+            // fall short of the peak, and by no more than the tolerance.
+            let peak = polygon.iter().map(|p| p.y).fold(f32::MIN, f32::max);
+            assert!(peak <= 75.0 + EPSILON, "tolerance {}", tolerance);
+            assert!(peak >= 75.0 - tolerance, "tolerance {}", tolerance);
+        }
+        assert!(path.flatten(0.1)[0].len() > path.flatten(5.0)[0].len());
+    }
+
+    #[test]
+    fn squares_fill_with_one_span_per_scanline() {
+        let spans = fill_spans(&[points(&[
+            (10.0, 20.0),
+            (40.0, 20.0),
+            (40.0, 30.0),
+            (10.0, 30.0),
+        ])]);
+        assert_eq!(spans.len(), 10);
+        for (row, span) in spans.iter().enumerate() {
+            assert_eq!(
+                *span,
+                LayoutRect::new(
+                    LayoutPoint::new(10.0, 20.0 + row as f32),
+                    LayoutPoint::new(40.0, 21.0 + row as f32),
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn triangles_fill_their_area() {
+        let spans = fill_spans(&[points(&[(0.0, 0.0), (100.0, 0.0), (50.0, 100.0)])]);
+        assert_eq!(spans.len(), 100);
+        assert!((area(&spans) - 5000.0).abs() < 1.0, "area {}", area(&spans));
+    }
+
+    #[test]
+    fn holes_wound_the_same_way_are_filled() {
+        let outer = points(&[(0.0, 0.0), (30.0, 0.0), (30.0, 30.0), (0.0, 30.0)]);
+        let inner = points(&[(10.0, 10.0), (20.0, 10.0), (20.0, 20.0), (10.0, 20.0)]);
+        let mut reversed = inner.clone();
+        reversed.reverse();
+
+        // The nonzero rule fills overlapping polygons wound the same way,
+        // and cuts a hole where one is wound against the other.
+        assert_eq!(area(&fill_spans(&[outer.clone(), inner])), 900.0);
+        assert_eq!(area(&fill_spans(&[outer, reversed])), 800.0);
+    }
+
+    #[test]
+    fn collinear_points_fill_nothing() {
+        for polygon in [
+            points(&[(0.0, 0.0), (10.0, 10.0), (20.0, 20.0)]),
+            points(&[(0.0, 5.0), (10.0, 5.0), (20.0, 5.0)]),
+            points(&[(5.0, 0.0), (5.0, 10.0), (5.0, 20.0)]),
+        ] {
+            assert_eq!(
+                fill_spans(std::slice::from_ref(&polygon)),
+                vec![],
+                "{:?}",
+                polygon
+            );
+        }
+    }
+
+    #[test]
+    fn fewer_than_three_points_make_no_polygon() {
+        for data in [
+            "",
+            "M10,10",
+            "M10,10 L20,20",
+            "M10,10 L20,20 Z",
+            "M0,0 Z M5,5 L6,6",
+        ] {
+            let path = SvgPath::parse(data).unwrap();
+            assert_eq!(
+                path.flatten(0.25),
+                Vec::<Vec<LayoutPoint>>::new(),
+                "{:?}",
+                data
+            );
+        }
+        assert_eq!(fill_spans(&[]), vec![]);
+        assert_eq!(fill_spans(&[points(&[(0.0, 0.0)])]), vec![]);
+    }
+
+    #[test]
+    fn malformed_path_data_is_rejected() {
+        for data in ["10,10", "M10", "M10,10 Q0,0 5,5", "M10,10 L20,x"] {
+            assert!(SvgPath::parse(data).is_err(), "{:?}", data);
+        }
+    }
+}
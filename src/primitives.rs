@@ -0,0 +1,362 @@
+use std::f32::consts::{SQRT_2, TAU};
+
+use webrender::api::{
+    units::{
+        DeviceIntSideOffsets, LayoutPoint, LayoutRect, LayoutSideOffsets, LayoutSize,
+        LayoutVector2D,
+    },
+    BorderDetails, ColorF, CommonItemProperties, DisplayListBuilder, ExtendMode, GradientStop,
+    NinePatchBorder, NinePatchBorderSource, RepeatMode, SpaceAndClipInfo,
+};
+
+use crate::{coords::CoordinateConverter, path::fill_spans};
+
+/// Distance between the lines of the debug grid, in layout pixels.
+const GRID_SPACING: f32 = 50.0;
+
+/// Fraction of the way from the center to the corners that the vignette
+/// leaves untouched.
+const VIGNETTE_CLEAR: f32 = 0.4;
+
+/// Number of dots making up the spinner ring.
+const SPINNER_DOTS: usize = 12;
+
+/// Length of one repeat of the sweeping linear gradient.
+const SWEEP_LENGTH: f32 = 160.0;
+
+/// Draws a ring of dots around `center` whose opacity fades out behind
+/// `angle` (in radians), so advancing `angle` over time makes it spin.
+///
+/// Dots are snapped to whole layout pixels, which keeps their edges on
+/// device pixel boundaries at integral scale factors.
+pub fn push_spinner(
+    builder: &mut DisplayListBuilder,
+    center: LayoutPoint,
+    radius: f32,
+    color: ColorF,
+    angle: f32,
+    space_and_clip: SpaceAndClipInfo,
+) {
+    let dot = (radius * 0.3).round().max(1.0);
+    let half_dot = LayoutVector2D::new(dot / 2.0, dot / 2.0);
+
+    for i in 0..SPINNER_DOTS {
+        let trail = i as f32 / SPINNER_DOTS as f32;
+        let theta = angle - trail * TAU;
+        let position = center + LayoutVector2D::new(theta.cos(), theta.sin()) * radius;
+        let bounds = LayoutRect::from_origin_and_size(
+            (position - half_dot).round(),
+            LayoutSize::new(dot, dot),
+        );
+
+        builder.push_rect(
+            &CommonItemProperties::new(bounds, space_and_clip),
+            bounds,
+            ColorF {
+                a: color.a * (1.0 - trail),
+                ..color
+            },
+        );
+    }
+}
+
+/// Draws a straight line of `width` from `from` to `to` at any angle.
+pub fn push_line_segment(
+    builder: &mut DisplayListBuilder,
+    from: LayoutPoint,
+    to: LayoutPoint,
+    width: f32,
+    color: ColorF,
+    space_and_clip: SpaceAndClipInfo,
+) {
+    let direction = (to - from)
+        .try_normalize()
+        .unwrap_or(LayoutVector2D::new(1.0, 0.0));
+    let normal = LayoutVector2D::new(-direction.y, direction.x) * (width / 2.0);
+    let quad = vec![from + normal, to + normal, to - normal, from - normal];
+    push_path(builder, &fill_spans(&[quad]), color, space_and_clip);
+}
+
+/// Draws a line like `push_line_segment`, snapped to the device pixel grid
+/// of `snap_to` if given.
+///
+/// Snapped lines are a whole number of device pixels wide. Horizontal and
+/// vertical ones are drawn as a single rect whose edges fall on device pixel
+/// boundaries, which keeps thin lines crisp at fractional scale factors
+/// where layout pixel coordinates land between device pixels.
+pub fn push_line(
+    builder: &mut DisplayListBuilder,
+    from: LayoutPoint,
+    to: LayoutPoint,
+    width: f32,
+    snap_to: Option<&CoordinateConverter>,
+    color: ColorF,
+    space_and_clip: SpaceAndClipInfo,
+) {
+    let coords = match snap_to {
+        Some(coords) => coords,
+        None => {
+            push_line_segment(builder, from, to, width, color, space_and_clip);
+            return;
+        }
+    };
+
+    let width = coords.snap_line_width(width);
+    let half = width / 2.0;
+    let (from, to) = (coords.snap_to_device(from), coords.snap_to_device(to));
+    let bounds = if from.y == to.y {
+        let y = coords.snap_line_center(from.y, width);
+        LayoutRect::new(
+            LayoutPoint::new(from.x.min(to.x), y - half),
+            LayoutPoint::new(from.x.max(to.x), y + half),
+        )
+    } else if from.x == to.x {
+        let x = coords.snap_line_center(from.x, width);
+        LayoutRect::new(
+            LayoutPoint::new(x - half, from.y.min(to.y)),
+            LayoutPoint::new(x + half, from.y.max(to.y)),
+        )
+    } else {
+        push_line_segment(builder, from, to, width, color, space_and_clip);
+        return;
+    };
+    builder.push_rect(
+        &CommonItemProperties::new(bounds, space_and_clip),
+        bounds,
+        color,
+    );
+}
+
+/// Draws a grid of 1px lines over the whole window, for checking how thin
+/// lines land on the device pixel grid.
+pub fn push_grid(
+    builder: &mut DisplayListBuilder,
+    coords: &CoordinateConverter,
+    snap: bool,
+    color: ColorF,
+    space_and_clip: SpaceAndClipInfo,
+) {
+    let size = coords.layout_size();
+    let snap_to = Some(coords).filter(|_| snap);
+    let mut x = GRID_SPACING;
+    while x < size.width {
+        let (top, bottom) = (LayoutPoint::new(x, 0.0), LayoutPoint::new(x, size.height));
+        push_line(builder, top, bottom, 1.0, snap_to, color, space_and_clip);
+        x += GRID_SPACING;
+    }
+    let mut y = GRID_SPACING;
+    while y < size.height {
+        let (left, right) = (LayoutPoint::new(0.0, y), LayoutPoint::new(size.width, y));
+        push_line(builder, left, right, 1.0, snap_to, color, space_and_clip);
+        y += GRID_SPACING;
+    }
+}
+
+/// Fills the spans produced by `path::fill_spans` with `color`.
+pub fn push_path(
+    builder: &mut DisplayListBuilder,
+    spans: &[LayoutRect],
+    color: ColorF,
+    space_and_clip: SpaceAndClipInfo,
+) {
+    for &span in spans {
+        builder.push_rect(
+            &CommonItemProperties::new(span, space_and_clip),
+            span,
+            color,
+        );
+    }
+}
+
+/// Red to green to blue and back to red, so the gradient wraps without a
+/// seam when repeated or spun.
+fn cyclic_stops() -> Vec<GradientStop> {
+    [
+        ColorF::new(1.0, 0.0, 0.0, 1.0),
+        ColorF::new(0.0, 1.0, 0.0, 1.0),
+        ColorF::new(0.0, 0.0, 1.0, 1.0),
+        ColorF::new(1.0, 0.0, 0.0, 1.0),
+    ]
+    .iter()
+    .enumerate()
+    .map(|(i, &color)| GradientStop {
+        offset: i as f32 / 3.0,
+        color,
+    })
+    .collect()
+}
+
+/// Fills `bounds` with a repeating horizontal gradient shifted by `phase`
+/// (0 to 1) of its length, so advancing `phase` makes it sweep sideways.
+pub fn push_gradient_sweep(
+    builder: &mut DisplayListBuilder,
+    bounds: LayoutRect,
+    phase: f32,
+    space_and_clip: SpaceAndClipInfo,
+) {
+    // Gradient points are relative to the item's origin.
+    let start = LayoutPoint::new(phase * SWEEP_LENGTH, 0.0);
+    let gradient = builder.create_gradient(
+        start,
+        start + LayoutVector2D::new(SWEEP_LENGTH, 0.0),
+        cyclic_stops(),
+        ExtendMode::Repeat,
+    );
+    builder.push_gradient(
+        &CommonItemProperties::new(bounds, space_and_clip),
+        bounds,
+        gradient,
+        bounds.size(),
+        LayoutSize::zero(),
+    );
+}
+
+/// Fills `bounds` with a gradient from black at the top to white at the
+/// bottom. Over a tall window each of the 256 steps an 8-bit framebuffer
+/// has is several pixels high, so without dithering the bands show.
+pub fn push_ramp(
+    builder: &mut DisplayListBuilder,
+    bounds: LayoutRect,
+    space_and_clip: SpaceAndClipInfo,
+) {
+    let gradient = builder.create_gradient(
+        LayoutPoint::zero(),
+        LayoutPoint::new(0.0, bounds.height()),
+        vec![
+            GradientStop {
+                offset: 0.0,
+                color: ColorF::BLACK,
+            },
+            GradientStop {
+                offset: 1.0,
+                color: ColorF::WHITE,
+            },
+        ],
+        ExtendMode::Clamp,
+    );
+    builder.push_gradient(
+        &CommonItemProperties::new(bounds, space_and_clip),
+        bounds,
+        gradient,
+        bounds.size(),
+        LayoutSize::zero(),
+    );
+}
+
+/// Fills `bounds` with a conic gradient around its center, turned by
+/// `angle` radians.
+pub fn push_conic_spin(
+    builder: &mut DisplayListBuilder,
+    bounds: LayoutRect,
+    angle: f32,
+    space_and_clip: SpaceAndClipInfo,
+) {
+    let size = bounds.size();
+    let gradient = builder.create_conic_gradient(
+        LayoutPoint::new(size.width / 2.0, size.height / 2.0),
+        angle,
+        cyclic_stops(),
+        ExtendMode::Clamp,
+    );
+    builder.push_conic_gradient(
+        &CommonItemProperties::new(bounds, space_and_clip),
+        bounds,
+        gradient,
+        size,
+        LayoutSize::zero(),
+    );
+}
+
+/// Draws a border of `width` just inside `bounds`, colored by a linear
+/// gradient through `colors` at `angle` radians, CSS style: 0 runs bottom
+/// to top and a quarter turn runs left to right.
+///
+/// Webrender draws gradient borders as a nine-patch whose source is the
+/// gradient. The source is as large as `bounds` and sliced at the border
+/// width, so each edge and corner shows exactly the part of the gradient
+/// under it and the color runs continuously around the corners.
+pub fn push_gradient_border(
+    builder: &mut DisplayListBuilder,
+    bounds: LayoutRect,
+    width: f32,
+    colors: &[ColorF],
+    angle: f32,
+    space_and_clip: SpaceAndClipInfo,
+) {
+    let size = bounds.size();
+    let direction = LayoutVector2D::new(angle.sin(), -angle.cos());
+    // Long enough for the rect's corners to hit the first and last stops.
+    let half_length =
+        (size.width / 2.0 * direction.x).abs() + (size.height / 2.0 * direction.y).abs();
+    let center = LayoutPoint::new(size.width / 2.0, size.height / 2.0);
+    let last = colors.len().saturating_sub(1).max(1) as f32;
+    let gradient = builder.create_gradient(
+        center - direction * half_length,
+        center + direction * half_length,
+        colors
+            .iter()
+            .enumerate()
+            .map(|(i, &color)| GradientStop {
+                offset: i as f32 / last,
+                color,
+            })
+            .collect(),
+        ExtendMode::Clamp,
+    );
+
+    let slice = width.round() as i32;
+    builder.push_border(
+        &CommonItemProperties::new(bounds, space_and_clip),
+        bounds,
+        LayoutSideOffsets::new_all_same(width),
+        BorderDetails::NinePatch(NinePatchBorder {
+            source: NinePatchBorderSource::Gradient(gradient),
+            width: size.width.round() as i32,
+            height: size.height.round() as i32,
+            slice: DeviceIntSideOffsets::new_all_same(slice),
+            fill: false,
+            repeat_horizontal: RepeatMode::Stretch,
+            repeat_vertical: RepeatMode::Stretch,
+            outset: LayoutSideOffsets::zero(),
+        }),
+    );
+}
+
+/// Darkens `bounds` towards its edges with a radial gradient from clear at
+/// the center to black at `intensity` alpha in the corners.
+pub fn push_vignette(
+    builder: &mut DisplayListBuilder,
+    bounds: LayoutRect,
+    intensity: f32,
+    space_and_clip: SpaceAndClipInfo,
+) {
+    let size = bounds.size();
+    // An ellipse through the corners, so the darkening follows the
+    // window's aspect ratio and peaks exactly at the corners.
+    let gradient = builder.create_radial_gradient(
+        LayoutPoint::new(size.width / 2.0, size.height / 2.0),
+        size / 2.0 * SQRT_2,
+        vec![
+            GradientStop {
+                offset: 0.0,
+                color: ColorF::TRANSPARENT,
+            },
+            GradientStop {
+                offset: VIGNETTE_CLEAR,
+                color: ColorF::TRANSPARENT,
+            },
+            GradientStop {
+                offset: 1.0,
+                color: ColorF::new(0.0, 0.0, 0.0, intensity),
+            },
+        ],
+        ExtendMode::Clamp,
+    );
+    builder.push_radial_gradient(
+        &CommonItemProperties::new(bounds, space_and_clip),
+        bounds,
+        gradient,
+        size,
+        LayoutSize::zero(),
+    );
+}
@@ -0,0 +1,74 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+use log::error;
+use serde::Serialize;
+
+/// Frames written between flushes, so an interrupted run loses at most
+/// this many.
+const FLUSH_INTERVAL: u64 = 60;
+
+/// One line of `--profile-json` output. Times are in milliseconds.
+#[derive(Clone, Debug, Serialize)]
+pub struct FrameProfile {
+    pub frame: u64,
+    /// Seconds since the first frame.
+    pub time: f64,
+    /// Last display list epoch submitted before this frame.
+    pub epoch: u32,
+    /// CPU time spent building display lists since the previous frame.
+    pub build_ms: f64,
+    /// Wall time of `Renderer::update` and `Renderer::render`.
+    pub render_ms: f64,
+    pub scene_build_ms: f64,
+    pub frame_build_ms: f64,
+    pub draw_calls: usize,
+    /// Rects in the scene model.
+    pub rects: usize,
+    pub image_tiles: usize,
+}
+
+pub fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Writes a `FrameProfile` per rendered frame as newline-delimited JSON.
+pub struct ProfileWriter {
+    file: BufWriter<File>,
+    unflushed: u64,
+}
+
+impl ProfileWriter {
+    pub fn create(path: &Path) -> Result<ProfileWriter, String> {
+        let file = File::create(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        Ok(ProfileWriter {
+            file: BufWriter::new(file),
+            unflushed: 0,
+        })
+    }
+
+    pub fn write(&mut self, profile: &FrameProfile) {
+        let result = serde_json::to_string(profile)
+            .map_err(|err| err.to_string())
+            .and_then(|line| writeln!(self.file, "{}", line).map_err(|err| err.to_string()));
+        if let Err(err) = result {
+            error!("failed to write frame profile: {}", err);
+            return;
+        }
+        self.unflushed += 1;
+        if self.unflushed == FLUSH_INTERVAL {
+            self.flush();
+        }
+    }
+
+    pub fn flush(&mut self) {
+        self.unflushed = 0;
+        if let Err(err) = self.file.flush() {
+            error!("failed to write frame profile: {}", err);
+        }
+    }
+}
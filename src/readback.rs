@@ -0,0 +1,64 @@
+use gleam::gl::{self, Gl};
+use webrender::api::{
+    units::{DeviceIntPoint, DeviceIntRect, DeviceIntSize},
+    ColorF,
+};
+
+/// Reads the color of a single pixel from the current back buffer.
+///
+/// `point` is in window coordinates (origin at the top left), so it is
+/// flipped before reading. Must be called after rendering and before the
+/// buffers are swapped.
+pub fn read_pixel(
+    gl: &dyn Gl,
+    point: DeviceIntPoint,
+    device_size: DeviceIntSize,
+) -> Option<ColorF> {
+    if point.x < 0 || point.y < 0 || point.x >= device_size.width || point.y >= device_size.height {
+        return None;
+    }
+
+    let y = device_size.height - 1 - point.y;
+    let pixel = gl.read_pixels(point.x, y, 1, 1, gl::RGBA, gl::UNSIGNED_BYTE);
+    let [r, g, b, a] = [pixel[0], pixel[1], pixel[2], pixel[3]].map(|c| c as f32 / 255.0);
+
+    // The framebuffer holds premultiplied colors.
+    if a == 0.0 {
+        return Some(ColorF::TRANSPARENT);
+    }
+    Some(ColorF::new(r / a, g / a, b / a, a))
+}
+
+/// Reads the part of `rect` inside the back buffer as RGBA rows, top row
+/// first, with its size. `rect` is in window coordinates, with the origin
+/// at the top left. Returns `None` when none of it is inside.
+pub fn read_region(
+    gl: &dyn Gl,
+    rect: DeviceIntRect,
+    device_size: DeviceIntSize,
+) -> Option<(DeviceIntSize, Vec<u8>)> {
+    let rect = rect.intersection(&DeviceIntRect::from_size(device_size))?;
+    let size = rect.size();
+    if size.is_empty() {
+        return None;
+    }
+
+    // GL rows start at the bottom, so the region's bottom edge in window
+    // coordinates is its first row.
+    let y = device_size.height - rect.max.y;
+    let pixels = gl.read_pixels(
+        rect.min.x,
+        y,
+        size.width,
+        size.height,
+        gl::RGBA,
+        gl::UNSIGNED_BYTE,
+    );
+    let rgba = pixels
+        .chunks_exact(size.width as usize * 4)
+        .rev()
+        .flatten()
+        .copied()
+        .collect();
+    Some((size, rgba))
+}
@@ -0,0 +1,138 @@
+use webrender::api::{
+    units::{LayoutPoint, LayoutRect, LayoutSize, LayoutVector2D},
+    ColorF, CommonItemProperties, DisplayListBuilder, SpaceAndClipInfo,
+};
+
+use crate::{
+    coords::CoordinateConverter,
+    primitives::push_line,
+    text::{push_text, text_size},
+};
+
+/// Size of a label's font cells, in layout pixels.
+const LABEL_CELL: f32 = 2.0;
+
+/// Padding around labels, in layout pixels.
+const LABEL_PADDING: f32 = 4.0;
+
+/// Measures the distance between two clicked points.
+///
+/// Points are kept in layout pixels of the window, unaffected by the view's
+/// pan and zoom, and distances are reported in device pixels.
+#[derive(Default)]
+pub struct Ruler {
+    start: Option<LayoutPoint>,
+    end: Option<LayoutPoint>,
+}
+
+impl Ruler {
+    /// Sets the start, then the end, and clears the ruler on the third click.
+    pub fn click(&mut self, point: LayoutPoint) {
+        match (self.start, self.end) {
+            (None, _) => self.start = Some(point),
+            (Some(_), None) => self.end = Some(point),
+            (Some(_), Some(_)) => self.clear(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start.is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.start = None;
+        self.end = None;
+    }
+
+    pub fn push(
+        &self,
+        builder: &mut DisplayListBuilder,
+        coords: &CoordinateConverter,
+        snap: bool,
+        space_and_clip: SpaceAndClipInfo,
+    ) {
+        let start = match self.start {
+            Some(start) => start,
+            None => return,
+        };
+        push_marker(builder, start, space_and_clip);
+
+        let end = match self.end {
+            Some(end) => end,
+            None => return,
+        };
+        push_marker(builder, end, space_and_clip);
+        let snap_to = Some(coords).filter(|_| snap);
+        push_line(
+            builder,
+            start,
+            end,
+            2.0,
+            snap_to,
+            ColorF::BLACK,
+            space_and_clip,
+        );
+        push_line(
+            builder,
+            start,
+            end,
+            1.0,
+            snap_to,
+            ColorF::WHITE,
+            space_and_clip,
+        );
+
+        let delta = coords.layout_to_device(end) - coords.layout_to_device(start);
+        let labels = [
+            format!("{:.1} px", delta.length()),
+            format!("dx {:.0} dy {:.0}", delta.x.abs(), delta.y.abs()),
+        ];
+        let line_step = text_size("", LABEL_CELL).height + LABEL_PADDING * 2.0;
+        let mut origin = start.lerp(end, 0.5) + LayoutVector2D::new(LABEL_PADDING, LABEL_PADDING);
+        for label in &labels {
+            push_label(builder, origin, label, space_and_clip);
+            origin.y += line_step;
+        }
+    }
+}
+
+fn push_marker(
+    builder: &mut DisplayListBuilder,
+    center: LayoutPoint,
+    space_and_clip: SpaceAndClipInfo,
+) {
+    let bounds = LayoutRect::from_origin_and_size(
+        center - LayoutVector2D::new(3.0, 3.0),
+        LayoutSize::new(6.0, 6.0),
+    );
+    builder.push_rect(
+        &CommonItemProperties::new(bounds, space_and_clip),
+        bounds,
+        ColorF::WHITE,
+    );
+}
+
+/// Draws `text` in white over a dark backing so it reads on any scene.
+fn push_label(
+    builder: &mut DisplayListBuilder,
+    origin: LayoutPoint,
+    text: &str,
+    space_and_clip: SpaceAndClipInfo,
+) {
+    let size = text_size(text, LABEL_CELL);
+    let backing = LayoutRect::from_origin_and_size(origin, size)
+        .inflate(LABEL_PADDING / 2.0, LABEL_PADDING / 2.0);
+    builder.push_rect(
+        &CommonItemProperties::new(backing, space_and_clip),
+        backing,
+        ColorF::new(0.0, 0.0, 0.0, 0.7),
+    );
+    push_text(
+        builder,
+        origin,
+        text,
+        LABEL_CELL,
+        ColorF::WHITE,
+        space_and_clip,
+    );
+}
@@ -0,0 +1,1016 @@
+use std::f32::consts::FRAC_PI_2;
+
+use webrender::{
+    api::{
+        units::{LayoutPoint, LayoutRect, LayoutSideOffsets, LayoutSize, LayoutTransform},
+        BorderDetails, BorderRadius, BorderSide, BorderStyle, ClipChainId, ClipId, ClipMode,
+        ColorF, CommonItemProperties, ComplexClipRegion, DisplayListBuilder, FilterOp,
+        NormalBorder, PrimitiveFlags, PropertyBinding, PropertyBindingKey, ReferenceFrameKind,
+        SpaceAndClipInfo, SpatialId, SpatialTreeItemKey, TransformStyle,
+    },
+    euclid::{vec3, Angle},
+};
+
+use crate::{clip_debug::ClipLog, color::over, palette::Palette, primitives::push_gradient_border};
+
+/// Colors used by the built-in scenes.
+const COLORS: [ColorF; 3] = [
+    ColorF {
+        r: 1.0,
+        g: 0.0,
+        b: 0.0,
+        a: 1.0,
+    },
+    ColorF {
+        r: 0.0,
+        g: 1.0,
+        b: 0.0,
+        a: 1.0,
+    },
+    ColorF {
+        r: 0.0,
+        g: 0.0,
+        b: 1.0,
+        a: 1.0,
+    },
+];
+
+/// Width of the outlines the wireframe mode draws instead of fills.
+const WIREFRAME_WIDTH: f32 = 1.0;
+
+/// Side length, in cells, of the same-colored blocks in the stress grid.
+const GRID_BLOCK: usize = 8;
+
+/// The scenes that can be generated without any input files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuiltinScene {
+    ThreeRects,
+    Grid(usize),
+    /// Half transparent red over half transparent green, overlapping in
+    /// the center, for `--alpha-test`.
+    AlphaOverlap,
+    /// The same bordered rect faded two ways, where group opacity and
+    /// per-item alpha differ.
+    OpacityCompare,
+}
+
+/// A built-in scene `--scene-name` can pick, with what `--list-scenes`
+/// says about it.
+struct RegisteredScene {
+    name: &'static str,
+    summary: &'static str,
+    /// Makes the scene, from the count after its name if it takes one.
+    build: fn(Option<usize>) -> BuiltinScene,
+    takes_count: bool,
+}
+
+/// Rects in the `grid` scene without a count.
+const DEFAULT_GRID_COUNT: usize = 100;
+
+/// Every built-in scene, by name.
+const REGISTRY: [RegisteredScene; 4] = [
+    RegisteredScene {
+        name: "three-rects",
+        summary: "a red, a green and a blue rect",
+        build: |_| BuiltinScene::ThreeRects,
+        takes_count: false,
+    },
+    RegisteredScene {
+        name: "grid",
+        summary: "a grid of rects, 100 of them or N as grid-N",
+        build: |count| BuiltinScene::Grid(count.unwrap_or(DEFAULT_GRID_COUNT)),
+        takes_count: true,
+    },
+    RegisteredScene {
+        name: "alpha-overlap",
+        summary: "half transparent red over half transparent green",
+        build: |_| BuiltinScene::AlphaOverlap,
+        takes_count: false,
+    },
+    RegisteredScene {
+        name: "opacity-compare",
+        summary: "a bordered rect at 50% opacity next to one at 50% alpha",
+        build: |_| BuiltinScene::OpacityCompare,
+        takes_count: false,
+    },
+];
+
+impl BuiltinScene {
+    /// Looks up a scene by the name `name` gives it, or by its base name
+    /// alone for the default count. An unknown name's error suggests the
+    /// closest known one.
+    pub fn from_name(name: &str) -> Result<BuiltinScene, String> {
+        let (base, count) = match name.rsplit_once('-') {
+            Some((base, count))
+                if !count.is_empty() && count.bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                let count = count
+                    .parse()
+                    .map_err(|_| format!("scene count `{}` is too large", count))?;
+                (base, Some(count))
+            }
+            _ => (name, None),
+        };
+        match REGISTRY.iter().find(|scene| scene.name == base) {
+            Some(scene) if count.is_none() || scene.takes_count => Ok((scene.build)(count)),
+            Some(scene) => Err(format!("scene `{}` takes no count", scene.name)),
+            None => {
+                let known: Vec<&str> = REGISTRY.iter().map(|scene| scene.name).collect();
+                let suggestion = known
+                    .iter()
+                    .map(|known| (edit_distance(base, known), known))
+                    .min()
+                    .filter(|&(distance, known)| distance <= (known.len() / 3).max(2))
+                    .map_or_else(
+                        || ".".to_string(),
+                        |(_, known)| format!(", did you mean `{}`?", known),
+                    );
+                Err(format!(
+                    "unknown scene `{}`{} Known scenes: {}",
+                    name,
+                    suggestion,
+                    known.join(", ")
+                ))
+            }
+        }
+    }
+
+    /// One line per scene for `--list-scenes`: its name and what it shows.
+    pub fn list() -> String {
+        let width = REGISTRY
+            .iter()
+            .map(|scene| scene.name.len())
+            .max()
+            .unwrap_or(0);
+        REGISTRY
+            .iter()
+            .map(|scene| format!("{:width$}  {}\n", scene.name, scene.summary, width = width))
+            .collect()
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            BuiltinScene::ThreeRects => "three-rects".to_string(),
+            BuiltinScene::Grid(count) => format!("grid-{}", count),
+            BuiltinScene::AlphaOverlap => "alpha-overlap".to_string(),
+            BuiltinScene::OpacityCompare => "opacity-compare".to_string(),
+        }
+    }
+
+    /// Lays the scene out to fill `size`. Generated scenes take their
+    /// colors from `palette` if there is one.
+    pub fn model(&self, size: LayoutSize, palette: Option<Palette>) -> SceneModel {
+        match *self {
+            BuiltinScene::ThreeRects => SceneModel::three_rects(size),
+            BuiltinScene::Grid(count) => SceneModel::grid(count, size, palette),
+            BuiltinScene::AlphaOverlap => SceneModel::alpha_overlap(size),
+            BuiltinScene::OpacityCompare => SceneModel::opacity_compare(size),
+        }
+    }
+}
+
+/// The number of single character insertions, deletions and substitutions
+/// that turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &b) in b.iter().enumerate() {
+            let substituted = diagonal + usize::from(a != b);
+            diagonal = row[j + 1];
+            row[j + 1] = substituted.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// A border drawn inside a rect's edges with a linear gradient.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GradientBorder {
+    pub width: f32,
+    /// Evenly spaced gradient stops, at least two.
+    pub colors: Vec<ColorF>,
+    /// Direction of the gradient in radians, CSS style: 0 runs bottom to
+    /// top and a quarter turn runs left to right.
+    pub angle: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneRect {
+    pub bounds: LayoutRect,
+    /// Fill color. Its alpha fades the fill itself.
+    pub color: ColorF,
+    /// Group opacity: below 1 the rect is drawn into its own stacking
+    /// context which is then faded as a whole.
+    pub opacity: f32,
+    pub border: Option<GradientBorder>,
+    /// Radii of the top left, top right, bottom right and bottom left
+    /// corners, as in CSS `border-radius`. All zero for square corners.
+    pub corner_radii: [f32; 4],
+    /// Turn about the rect's center in degrees, clockwise on screen.
+    pub rotation: f32,
+    /// Index into `SceneModel::layers`, or `None` outside any layer.
+    pub layer: Option<usize>,
+}
+
+impl SceneRect {
+    pub fn is_rounded(&self) -> bool {
+        self.corner_radii.iter().any(|&radius| radius > 0.0)
+    }
+
+    /// The translation taking a rect of the same size at the origin to
+    /// where this one is.
+    pub fn placement(&self) -> LayoutTransform {
+        LayoutTransform::translation(self.bounds.min.x, self.bounds.min.y, 0.0)
+    }
+
+    /// The transform turning the rect about its center, or `None` when it
+    /// isn't turned.
+    pub fn rotation_transform(&self) -> Option<LayoutTransform> {
+        if self.rotation == 0.0 {
+            return None;
+        }
+        let center = self.bounds.center();
+        Some(
+            LayoutTransform::translation(-center.x, -center.y, 0.0)
+                .then_rotate(0.0, 0.0, 1.0, Angle::degrees(self.rotation))
+                .then_translate(vec3(center.x, center.y, 0.0)),
+        )
+    }
+
+    /// Whether `point` is over the rect as drawn, turned or not.
+    pub fn contains(&self, point: LayoutPoint) -> bool {
+        let point = match self.rotation_transform().and_then(|t| t.inverse()) {
+            Some(inverse) => match inverse.transform_point2d(point) {
+                Some(point) => point,
+                None => return false,
+            },
+            None => point,
+        };
+        self.bounds.contains(point)
+    }
+
+    /// The smallest unturned rect around the rect as drawn.
+    pub fn footprint(&self) -> LayoutRect {
+        let transform = match self.rotation_transform() {
+            Some(transform) => transform,
+            None => return self.bounds,
+        };
+        let (min, max) = (self.bounds.min, self.bounds.max);
+        let corners = [
+            min,
+            LayoutPoint::new(max.x, min.y),
+            max,
+            LayoutPoint::new(min.x, max.y),
+        ];
+        corners
+            .iter()
+            .filter_map(|&corner| transform.transform_point2d(corner))
+            .fold(None, |footprint: Option<LayoutRect>, point| {
+                let point = LayoutRect::new(point, point);
+                Some(footprint.map_or(point, |footprint| footprint.union(&point)))
+            })
+            .unwrap_or(self.bounds)
+    }
+
+    /// Each corner's radius, clamped to half the rect's width and height
+    /// so that neighbouring corners can't overlap.
+    fn border_radius(&self) -> BorderRadius {
+        let half = self.bounds.size() / 2.0;
+        let [top_left, top_right, bottom_right, bottom_left] = self
+            .corner_radii
+            .map(|radius| LayoutSize::new(radius.min(half.width), radius.min(half.height)));
+        BorderRadius {
+            top_left,
+            top_right,
+            bottom_left,
+            bottom_right,
+        }
+    }
+}
+
+/// A named group of rects that can be hidden together.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneLayer {
+    pub name: String,
+    pub visible: bool,
+    /// The layer's box, in layout pixels, as a group's box in CSS.
+    pub bounds: Option<LayoutRect>,
+    /// Clips the layer's rects to its `bounds`, like CSS `overflow: hidden`.
+    pub overflow_hidden: bool,
+}
+
+impl SceneLayer {
+    pub fn new(name: &str) -> SceneLayer {
+        SceneLayer {
+            name: name.to_string(),
+            visible: true,
+            bounds: None,
+            overflow_hidden: false,
+        }
+    }
+
+    /// The box its rects are clipped to, if any.
+    pub fn clip(&self) -> Option<LayoutRect> {
+        self.bounds.filter(|_| self.overflow_hidden)
+    }
+}
+
+/// The scene content, in paint order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SceneModel {
+    pub rects: Vec<SceneRect>,
+    pub layers: Vec<SceneLayer>,
+}
+
+impl SceneModel {
+    /// Whether `rect` is drawn: it is in no layer or in a visible one.
+    pub fn is_visible(&self, rect: &SceneRect) -> bool {
+        match rect.layer.and_then(|layer| self.layers.get(layer)) {
+            Some(layer) => layer.visible,
+            None => true,
+        }
+    }
+
+    /// The smallest rect covering every visible rect.
+    pub fn bounds(&self) -> Option<LayoutRect> {
+        self.rects
+            .iter()
+            .filter(|rect| self.is_visible(rect))
+            .map(|rect| rect.bounds)
+            .reduce(|a, b| a.union(&b))
+    }
+
+    /// Shows or hides layer `index`. Returns whether there is such a layer.
+    pub fn toggle_layer(&mut self, index: usize) -> bool {
+        match self.layers.get_mut(index) {
+            Some(layer) => {
+                layer.visible = !layer.visible;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Copies the visibility of same-named layers from `other`, so hidden
+    /// layers stay hidden when the scene is laid out again.
+    pub fn keep_visibility(&mut self, other: &SceneModel) {
+        for layer in &mut self.layers {
+            if let Some(previous) = other.layers.iter().find(|l| l.name == layer.name) {
+                layer.visible = previous.visible;
+            }
+        }
+    }
+
+    /// The default scene: three overlapping rects spanning `size`, each in
+    /// a layer named after its color.
+    pub fn three_rects(size: LayoutSize) -> SceneModel {
+        let (width, height) = (size.width, size.height);
+        let corners = [
+            ((0.0, 0.0), (0.5, 0.5)),
+            ((0.25, 0.25), (0.75, 0.75)),
+            ((0.5, 0.5), (1.0, 1.0)),
+        ];
+
+        SceneModel {
+            rects: corners
+                .iter()
+                .zip(COLORS)
+                .enumerate()
+                .map(|(i, (&((x0, y0), (x1, y1)), color))| SceneRect {
+                    bounds: LayoutRect::new(
+                        LayoutPoint::new(width * x0, height * y0),
+                        LayoutPoint::new(width * x1, height * y1),
+                    ),
+                    color,
+                    opacity: 1.0,
+                    border: None,
+                    corner_radii: [0.0; 4],
+                    rotation: 0.0,
+                    layer: Some(i),
+                })
+                .collect(),
+            layers: ["red", "green", "blue"]
+                .iter()
+                .map(|name| SceneLayer::new(name))
+                .collect(),
+        }
+    }
+
+    /// 50% green, then 50% red on top, overlapping over the center of
+    /// `size`.
+    pub fn alpha_overlap(size: LayoutSize) -> SceneModel {
+        let rect = |x0: f32, x1: f32, color: ColorF| SceneRect {
+            bounds: LayoutRect::new(
+                LayoutPoint::new(size.width * x0, size.height * 0.3),
+                LayoutPoint::new(size.width * x1, size.height * 0.7),
+            ),
+            color,
+            opacity: 1.0,
+            border: None,
+            corner_radii: [0.0; 4],
+            rotation: 0.0,
+            layer: None,
+        };
+        SceneModel {
+            rects: vec![
+                rect(0.2, 0.6, ColorF::new(0.0, 1.0, 0.0, 0.5)),
+                rect(0.4, 0.8, ColorF::new(1.0, 0.0, 0.0, 0.5)),
+            ],
+            layers: Vec::new(),
+        }
+    }
+
+    /// Two green rects with blue borders over the center of `size`: the
+    /// left one opaque inside a group at 50% opacity, the right one with
+    /// its fill and border at 50% alpha. They match inside, but where the
+    /// border overlaps the fill the left one shows only the faded border,
+    /// the right one the border blended over the fill.
+    pub fn opacity_compare(size: LayoutSize) -> SceneModel {
+        let (width, height) = (size.width * 0.25, size.height * 0.5);
+        let rect = |x: f32, alpha: f32, opacity: f32| SceneRect {
+            bounds: LayoutRect::from_origin_and_size(
+                LayoutPoint::new(x, size.height * 0.25),
+                LayoutSize::new(width, height),
+            ),
+            color: ColorF::new(0.0, 1.0, 0.0, alpha),
+            opacity,
+            border: Some(GradientBorder {
+                width: width.min(height) * 0.15,
+                colors: vec![ColorF::new(0.0, 0.0, 1.0, alpha); 2],
+                angle: FRAC_PI_2,
+            }),
+            corner_radii: [0.0; 4],
+            rotation: 0.0,
+            layer: None,
+        };
+        SceneModel {
+            rects: vec![
+                rect(size.width * 0.1875, 1.0, 0.5),
+                rect(size.width * 0.5625, 0.5, 1.0),
+            ],
+            layers: Vec::new(),
+        }
+    }
+
+    /// The same scene with `f` applied to every color.
+    pub fn map_colors(&self, f: impl Fn(ColorF) -> ColorF) -> SceneModel {
+        let mut model = self.clone();
+        for rect in &mut model.rects {
+            rect.color = f(rect.color);
+            if let Some(border) = &mut rect.border {
+                for color in &mut border.colors {
+                    *color = f(*color);
+                }
+            }
+        }
+        model
+    }
+
+    /// The color the visible rects composite to at `point` over
+    /// `background`, all in straight alpha.
+    pub fn color_at(&self, point: LayoutPoint, background: ColorF) -> ColorF {
+        self.rects
+            .iter()
+            .filter(|rect| self.is_visible(rect) && rect.contains(point))
+            .fold(background, |below, rect| {
+                let color = ColorF {
+                    a: rect.color.a * rect.opacity,
+                    ..rect.color
+                };
+                over(color, below)
+            })
+    }
+
+    /// A grid of `count` cells filling `size`, colored in square blocks so
+    /// neighbouring cells often share a color. Without a `palette` the
+    /// blocks cycle through red, green and blue diagonally; with one every
+    /// block gets its own color.
+    pub fn grid(count: usize, size: LayoutSize, palette: Option<Palette>) -> SceneModel {
+        let columns = (count as f32).sqrt().ceil().max(1.0) as usize;
+        let rows = count.div_ceil(columns);
+        let block_columns = columns.div_ceil(GRID_BLOCK);
+        let cell = LayoutSize::new(
+            size.width / columns as f32,
+            size.height / rows.max(1) as f32,
+        );
+
+        SceneModel {
+            rects: (0..count)
+                .map(|i| {
+                    let (row, column) = (i / columns, i % columns);
+                    // Corners are computed per edge so neighbouring cells
+                    // share exactly the same coordinates, and the last edges
+                    // are the scene's own, which rounding could leave short
+                    // of the window edge at fractional scales.
+                    let corner = |column: usize, row: usize| {
+                        LayoutPoint::new(
+                            if column == columns {
+                                size.width
+                            } else {
+                                column as f32 * cell.width
+                            },
+                            if row == rows {
+                                size.height
+                            } else {
+                                row as f32 * cell.height
+                            },
+                        )
+                    };
+                    SceneRect {
+                        bounds: LayoutRect::new(corner(column, row), corner(column + 1, row + 1)),
+                        color: match palette {
+                            Some(palette) => palette
+                                .color(row / GRID_BLOCK * block_columns + column / GRID_BLOCK),
+                            None => COLORS[(row / GRID_BLOCK + column / GRID_BLOCK) % COLORS.len()],
+                        },
+                        opacity: 1.0,
+                        border: None,
+                        corner_radii: [0.0; 4],
+                        rotation: 0.0,
+                        layer: None,
+                    }
+                })
+                .collect(),
+            layers: Vec::new(),
+        }
+    }
+
+    /// Pushes every visible rect into `builder`, or with `wireframe` just
+    /// its outline, in its own color made opaque and following its corners.
+    ///
+    /// Each `overflow_hidden` layer gets a clip chain of its bounds, defined
+    /// in `space_and_clip` so that it moves and scales with the rects, under
+    /// the clip already there. Its rects are pushed in that chain, and a
+    /// rounded rect's corners are clipped on top of it. All of them are
+    /// defined through `clips`.
+    ///
+    /// With `rect_keys`, the rect at each index is placed by the transform
+    /// bound to the key that many after it, and filled with the color bound
+    /// to the same key, so that moving it or changing its color only needs
+    /// new values for the bindings.
+    pub fn push(
+        &self,
+        builder: &mut DisplayListBuilder,
+        wireframe: bool,
+        clips: &mut ClipLog,
+        rect_keys: Option<u64>,
+        space_and_clip: SpaceAndClipInfo,
+    ) {
+        let layer_clips: Vec<Option<ClipChainId>> = self
+            .layers
+            .iter()
+            .map(|layer| {
+                let bounds = layer.clip()?;
+                let clip = clips.define_clip_rect(builder, space_and_clip.spatial_id, bounds);
+                Some(clips.define_clip_chain(builder, None, [space_and_clip.clip_id, clip]))
+            })
+            .collect();
+
+        for (index, rect) in self.rects.iter().enumerate() {
+            if !self.is_visible(rect) {
+                continue;
+            }
+            let layer_clip = rect
+                .layer
+                .and_then(|layer| layer_clips.get(layer).copied().flatten());
+            let space_and_clip = match layer_clip {
+                Some(chain) => SpaceAndClipInfo {
+                    clip_id: ClipId::ClipChain(chain),
+                    ..space_and_clip
+                },
+                None => space_and_clip,
+            };
+            // A bound rect is pushed at the origin of a frame placing it.
+            let key = rect_keys.map(|first_key| first_key + index as u64);
+            let placed;
+            let (rect, space_and_clip) = match key {
+                Some(key) => {
+                    let frame = builder.push_reference_frame(
+                        LayoutPoint::zero(),
+                        space_and_clip.spatial_id,
+                        TransformStyle::Flat,
+                        PropertyBinding::Binding(PropertyBindingKey::new(key), rect.placement()),
+                        ReferenceFrameKind::Transform {
+                            is_2d_scale_translation: true,
+                            should_snap: false,
+                            paired_with_perspective: false,
+                        },
+                        SpatialTreeItemKey::new(5, index as u64),
+                    );
+                    placed = SceneRect {
+                        bounds: LayoutRect::from_size(rect.bounds.size()),
+                        ..rect.clone()
+                    };
+                    let space_and_clip = SpaceAndClipInfo {
+                        spatial_id: frame,
+                        ..space_and_clip
+                    };
+                    (&placed, space_and_clip)
+                }
+                None => (rect, space_and_clip),
+            };
+            let rotated = push_rotation(
+                builder,
+                rect,
+                space_and_clip.spatial_id,
+                SpatialTreeItemKey::new(2, index as u64),
+            );
+            let space_and_clip = SpaceAndClipInfo {
+                spatial_id: rotated.unwrap_or(space_and_clip.spatial_id),
+                ..space_and_clip
+            };
+            let grouped = rect.opacity < 1.0;
+            if grouped {
+                builder.push_simple_stacking_context_with_filters(
+                    LayoutPoint::zero(),
+                    space_and_clip.spatial_id,
+                    PrimitiveFlags::IS_BACKFACE_VISIBLE,
+                    &[FilterOp::Opacity(
+                        PropertyBinding::Value(rect.opacity),
+                        rect.opacity,
+                    )],
+                    &[],
+                    &[],
+                );
+            }
+            // Rounded corners clip the fill and the border alike.
+            let space_and_clip = if rect.is_rounded() {
+                let rounded = clips.define_clip_rounded_rect(
+                    builder,
+                    space_and_clip.spatial_id,
+                    ComplexClipRegion::new(rect.bounds, rect.border_radius(), ClipMode::Clip),
+                );
+                SpaceAndClipInfo {
+                    spatial_id: space_and_clip.spatial_id,
+                    clip_id: match layer_clip {
+                        Some(chain) => ClipId::ClipChain(clips.define_clip_chain(
+                            builder,
+                            Some(chain),
+                            [rounded],
+                        )),
+                        None => rounded,
+                    },
+                }
+            } else {
+                space_and_clip
+            };
+            if wireframe {
+                push_outline(builder, rect, space_and_clip);
+            } else {
+                let common = CommonItemProperties::new(rect.bounds, space_and_clip);
+                match key {
+                    Some(key) => builder.push_rect_with_animation(
+                        &common,
+                        rect.bounds,
+                        PropertyBinding::Binding(PropertyBindingKey::new(key), rect.color),
+                    ),
+                    None => builder.push_rect(&common, rect.bounds, rect.color),
+                }
+            }
+            if let Some(border) = rect.border.as_ref().filter(|_| !wireframe) {
+                push_gradient_border(
+                    builder,
+                    rect.bounds,
+                    border.width,
+                    &border.colors,
+                    border.angle,
+                    space_and_clip,
+                );
+            }
+            if grouped {
+                builder.pop_stacking_context();
+            }
+            if rotated.is_some() {
+                builder.pop_reference_frame();
+            }
+            if key.is_some() {
+                builder.pop_reference_frame();
+            }
+        }
+    }
+}
+
+/// Pushes a thin border around `rect` in its color at full alpha.
+fn push_outline(
+    builder: &mut DisplayListBuilder,
+    rect: &SceneRect,
+    space_and_clip: SpaceAndClipInfo,
+) {
+    let side = BorderSide {
+        color: ColorF {
+            a: 1.0,
+            ..rect.color
+        },
+        style: BorderStyle::Solid,
+    };
+    builder.push_border(
+        &CommonItemProperties::new(rect.bounds, space_and_clip),
+        rect.bounds,
+        LayoutSideOffsets::new_all_same(WIREFRAME_WIDTH),
+        BorderDetails::Normal(NormalBorder {
+            left: side,
+            right: side,
+            top: side,
+            bottom: side,
+            radius: rect.border_radius(),
+            do_aa: true,
+        }),
+    );
+}
+
+/// Pushes a reference frame turning `rect` about its center, if it is
+/// turned, and returns its id. The caller pops it once the rect's items are
+/// pushed.
+pub fn push_rotation(
+    builder: &mut DisplayListBuilder,
+    rect: &SceneRect,
+    parent: SpatialId,
+    key: SpatialTreeItemKey,
+) -> Option<SpatialId> {
+    let transform = rect.rotation_transform()?;
+    Some(builder.push_reference_frame(
+        LayoutPoint::zero(),
+        parent,
+        TransformStyle::Flat,
+        PropertyBinding::Value(transform),
+        ReferenceFrameKind::Transform {
+            is_2d_scale_translation: false,
+            should_snap: false,
+            paired_with_perspective: false,
+        },
+        key,
+    ))
+}
+
+/// Coalesces same-colored rects whose union is itself a rect, without
+/// changing what is painted.
+///
+/// A rect is only merged into an earlier one if no rect painted between the
+/// two overlaps it, so paint order is preserved. Runs until no more merges
+/// are possible.
+pub fn merge_rects(rects: &[SceneRect]) -> Vec<SceneRect> {
+    let mut current = rects.to_vec();
+    loop {
+        let merged = merge_pass(&current);
+        if merged.len() == current.len() {
+            return merged;
+        }
+        current = merged;
+    }
+}
+
+fn merge_pass(rects: &[SceneRect]) -> Vec<SceneRect> {
+    let mut merged: Vec<SceneRect> = Vec::with_capacity(rects.len());
+
+    'rects: for rect in rects {
+        for other in merged.iter_mut().rev() {
+            // A border or rounded corners follow the edges, so those rects
+            // never merge, and neither do turned ones.
+            let same_paint = other.color == rect.color
+                && other.rotation == 0.0
+                && rect.rotation == 0.0
+                && other.layer == rect.layer
+                && other.border.is_none()
+                && rect.border.is_none()
+                && !other.is_rounded()
+                && !rect.is_rounded()
+                && other.opacity == rect.opacity;
+            if same_paint {
+                // A translucent color or faded group blends twice where two
+                // rects overlap, and only once in their union.
+                let overlap = rect.opacity * rect.color.a == 1.0;
+                if let Some(union) = union_if_rect(&other.bounds, &rect.bounds, overlap) {
+                    other.bounds = union;
+                    continue 'rects;
+                }
+            }
+            if other.footprint().intersects(&rect.footprint()) {
+                break;
+            }
+        }
+        merged.push(rect.clone());
+    }
+
+    merged
+}
+
+/// The union of `a` and `b` if it covers exactly their combined area. Unless
+/// `overlap` is allowed they must only touch at an edge.
+fn union_if_rect(a: &LayoutRect, b: &LayoutRect, overlap: bool) -> Option<LayoutRect> {
+    if !overlap && a.intersects(b) {
+        return None;
+    }
+    let same_rows = a.min.y == b.min.y && a.max.y == b.max.y;
+    let same_columns = a.min.x == b.min.x && a.max.x == b.max.x;
+    let touch_x = a.min.x <= b.max.x && b.min.x <= a.max.x;
+    let touch_y = a.min.y <= b.max.y && b.min.y <= a.max.y;
+
+    if (same_rows && touch_x) || (same_columns && touch_y) || a.contains_box(b) || b.contains_box(a)
+    {
+        Some(a.union(b))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use webrender::api::units::DeviceIntSize;
+
+    use super::*;
+    use crate::coords::CoordinateConverter;
+
+    /// Odd window sizes, which a scale of 1.5 doesn't divide into whole
+    /// layout pixels.
+    const ODD_SIZES: [(i32, i32); 3] = [(1023, 767), (1365, 769), (801, 3)];
+
+    const SCALE: f32 = 1.5;
+
+    fn rect(width: f32, height: f32, corner_radii: [f32; 4]) -> SceneRect {
+        SceneRect {
+            bounds: LayoutRect::from_size(LayoutSize::new(width, height)),
+            color: COLORS[0],
+            opacity: 1.0,
+            border: None,
+            corner_radii,
+            rotation: 0.0,
+            layer: None,
+        }
+    }
+
+    fn rect_at(min: (f32, f32), max: (f32, f32), color: ColorF) -> SceneRect {
+        SceneRect {
+            bounds: LayoutRect::new(
+                LayoutPoint::new(min.0, min.1),
+                LayoutPoint::new(max.0, max.1),
+            ),
+            color,
+            opacity: 1.0,
+            border: None,
+            corner_radii: [0.0; 4],
+            rotation: 0.0,
+            layer: None,
+        }
+    }
+
+    #[test]
+    fn corner_radii_are_in_css_order() {
+        let radius = rect(100.0, 100.0, [1.0, 2.0, 3.0, 4.0]).border_radius();
+        assert_eq!(radius.top_left, LayoutSize::new(1.0, 1.0));
+        assert_eq!(radius.top_right, LayoutSize::new(2.0, 2.0));
+        assert_eq!(radius.bottom_right, LayoutSize::new(3.0, 3.0));
+        assert_eq!(radius.bottom_left, LayoutSize::new(4.0, 4.0));
+    }
+
+    #[test]
+    fn corner_radii_are_clamped_to_half_the_rect() {
+        let radius = rect(40.0, 20.0, [100.0, 15.0, 5.0, 0.0]).border_radius();
+        assert_eq!(radius.top_left, LayoutSize::new(20.0, 10.0));
+        assert_eq!(radius.top_right, LayoutSize::new(15.0, 10.0));
+        assert_eq!(radius.bottom_right, LayoutSize::new(5.0, 5.0));
+        assert_eq!(radius.bottom_left, LayoutSize::zero());
+    }
+
+    fn converters() -> impl Iterator<Item = CoordinateConverter> {
+        ODD_SIZES.into_iter().map(|(width, height)| {
+            CoordinateConverter::new(DeviceIntSize::new(width, height), SCALE)
+        })
+    }
+
+    #[test]
+    fn built_in_scenes_end_at_the_scene_edges() {
+        for coords in converters() {
+            let size = coords.scene_size();
+            for registered in &REGISTRY {
+                let model = BuiltinScene::from_name(registered.name)
+                    .unwrap()
+                    .model(size, None);
+                let union = model
+                    .rects
+                    .iter()
+                    .map(SceneRect::footprint)
+                    .reduce(|a, b| a.union(&b))
+                    .unwrap();
+                let name = registered.name;
+                assert!(
+                    LayoutRect::from_size(size).contains_box(&union),
+                    "{} runs past {:?}",
+                    name,
+                    size
+                );
+                // Every scene is laid out evenly about its center, so one
+                // that starts at the top left corner has to end exactly at
+                // the bottom right one, leaving no seam. The others only
+                // have to stay centered.
+                if union.min == LayoutPoint::zero() {
+                    assert_eq!(union.max, size.to_vector().to_point(), "{}", name);
+                } else {
+                    let center = union.center() - (size / 2.0).to_vector().to_point();
+                    assert!(center.length() < 1e-3, "{} is off center", name);
+                }
+            }
+        }
+    }
+
+    /// Checks that some rect of `model` is under the center of every device
+    /// pixel along the right and bottom edges, and of every `stride`th one
+    /// inside.
+    fn covers_device_pixels(model: &SceneModel, coords: &CoordinateConverter, stride: usize) {
+        let (width, height) = (coords.device_size.width, coords.device_size.height);
+        let inside = (0..width)
+            .step_by(stride)
+            .flat_map(|x| (0..height).step_by(stride).map(move |y| (x, y)));
+        let right = (0..height).map(|y| (width - 1, y));
+        let bottom = (0..width).map(|x| (x, height - 1));
+        for (x, y) in inside.chain(right).chain(bottom) {
+            let point = LayoutPoint::new(x as f32 + 0.5, y as f32 + 0.5) / SCALE;
+            assert!(
+                model.rects.iter().any(|rect| rect.contains(point)),
+                "device pixel ({}, {}) of {:?} is not covered",
+                x,
+                y,
+                coords.device_size
+            );
+        }
+    }
+
+    #[test]
+    fn grids_cover_every_device_pixel() {
+        // Counts that fill every row, as a short last row leaves a gap.
+        for coords in converters() {
+            for count in [1, 12, DEFAULT_GRID_COUNT, 1024] {
+                let model = BuiltinScene::Grid(count).model(coords.scene_size(), None);
+                covers_device_pixels(&model, &coords, 5);
+            }
+        }
+    }
+
+    #[test]
+    fn merges_overlapping_opaque_rects() {
+        let red = ColorF::new(1.0, 0.0, 0.0, 1.0);
+        let merged = merge_rects(&[
+            rect_at((0.0, 0.0), (60.0, 50.0), red),
+            rect_at((40.0, 0.0), (100.0, 50.0), red),
+        ]);
+        assert_eq!(merged, [rect_at((0.0, 0.0), (100.0, 50.0), red)]);
+    }
+
+    #[test]
+    fn keeps_overlapping_translucent_rects_apart() {
+        let red = ColorF::new(1.0, 0.0, 0.0, 0.5);
+        let rects = [
+            rect_at((0.0, 0.0), (60.0, 50.0), red),
+            rect_at((40.0, 0.0), (100.0, 50.0), red),
+        ];
+        assert_eq!(merge_rects(&rects), rects);
+
+        let contained = [
+            rect_at((0.0, 0.0), (100.0, 50.0), red),
+            rect_at((20.0, 10.0), (40.0, 30.0), red),
+        ];
+        assert_eq!(merge_rects(&contained), contained);
+    }
+
+    #[test]
+    fn keeps_overlapping_faded_rects_apart() {
+        let red = ColorF::new(1.0, 0.0, 0.0, 1.0);
+        let faded = |min, max| SceneRect {
+            opacity: 0.5,
+            ..rect_at(min, max, red)
+        };
+        let rects = [
+            faded((0.0, 0.0), (60.0, 50.0)),
+            faded((40.0, 0.0), (100.0, 50.0)),
+        ];
+        assert_eq!(merge_rects(&rects), rects);
+    }
+
+    #[test]
+    fn merges_translucent_rects_sharing_an_edge() {
+        let red = ColorF::new(1.0, 0.0, 0.0, 0.5);
+        let merged = merge_rects(&[
+            rect_at((0.0, 0.0), (50.0, 50.0), red),
+            rect_at((50.0, 0.0), (100.0, 50.0), red),
+        ]);
+        assert_eq!(merged, [rect_at((0.0, 0.0), (100.0, 50.0), red)]);
+    }
+
+    #[test]
+    fn opacity_compare_rects_match_inside_their_borders() {
+        let size = LayoutSize::new(640.0, 480.0);
+        let model = SceneModel::opacity_compare(size);
+        let background = ColorF::new(1.0, 0.0, 0.0, 1.0);
+        let (left, right) = (&model.rects[0], &model.rects[1]);
+        assert_eq!(
+            model.color_at(left.bounds.center(), background),
+            model.color_at(right.bounds.center(), background)
+        );
+        // One is faded as a group, the other item by item.
+        assert!(left.opacity < 1.0 && left.color.a == 1.0);
+        assert!(right.opacity == 1.0 && right.color.a < 1.0);
+        assert_eq!(left.border.as_ref().map(|border| border.width), Some(24.0));
+    }
+}
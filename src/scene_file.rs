@@ -0,0 +1,969 @@
+use std::{convert::TryFrom, fmt, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use webrender::api::{
+    units::{LayoutPoint, LayoutRect, LayoutSize},
+    ColorF,
+};
+
+use crate::scene::{GradientBorder, SceneLayer, SceneModel, SceneRect};
+
+/// A scene loaded with `--scene`, as written in the JSON file.
+///
+/// ```json
+/// { "rects": [{ "x": 10, "y": 10, "width": 100, "height": 50, "color": [1, 0, 0, 1] }] }
+/// ```
+///
+/// Coordinates are layout pixels and colors are `[r, g, b]` or
+/// `[r, g, b, a]` with components between 0 and 1.
+///
+/// A coordinate or size can also be a percentage of the scene's width, for
+/// `x` and `width`, or height, for `y` and `height`, so that the scene
+/// follows the window as it is resized:
+///
+/// ```json
+/// { "x": "25%", "y": 10, "width": "50%", "height": 50, "color": [1, 0, 0] }
+/// ```
+///
+/// A rect's optional `opacity` fades it as a group, through a stacking
+/// context, where the alpha of `color` fades just the fill.
+///
+/// An optional `border_gradient` draws a border inside the rect's edges
+/// filled with a linear gradient:
+///
+/// ```json
+/// "border_gradient": { "stops": [[1, 0, 0], [0, 0, 1]], "direction": 90, "width": 8 }
+/// ```
+///
+/// `stops` are evenly spaced colors, `direction` is in degrees as in CSS,
+/// where 0 runs bottom to top and 90, the default, left to right, and
+/// `width` defaults to 4.
+///
+/// An optional `radius` rounds the corners, either all alike with a single
+/// number or each one with `[top_left, top_right, bottom_right,
+/// bottom_left]` as in CSS `border-radius`:
+///
+/// ```json
+/// "radius": [24, 24, 0, 0]
+/// ```
+///
+/// Rects can also be grouped into named `layers`, which keys 1 to 9 show
+/// and hide. Layers are painted in order after the top-level `rects`:
+///
+/// ```json
+/// "layers": [{ "name": "labels", "visible": false, "rects": [...] }]
+/// ```
+///
+/// A layer with `overflow_hidden` clips its rects to its `bounds`, given as
+/// `[x, y, width, height]`, as CSS `overflow: hidden` clips to the box.
+///
+/// A top-level rect can also name the layer it is in, with `"layer":
+/// "labels"`, to be painted in that layer before the ones that follow it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneFile {
+    #[serde(default)]
+    pub rects: Vec<RectItem>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<LayerItem>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayerItem {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visible: Option<bool>,
+    /// `[x, y, width, height]` of the layer's box.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bounds: Option<[Length; 4]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overflow_hidden: Option<bool>,
+    #[serde(default)]
+    pub rects: Vec<RectItem>,
+}
+
+/// Geometry is kept in `f64` so that an `f32` rect survives being written
+/// as origin and size and read back: the sum and difference of two `f32`s
+/// are exact in `f64`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RectItem {
+    pub x: Length,
+    pub y: Length,
+    pub width: Length,
+    pub height: Length,
+    pub color: Vec<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opacity: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub border_gradient: Option<BorderGradientItem>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub radius: Option<RadiusItem>,
+    /// Turn about the rect's center in degrees, clockwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<f32>,
+    /// Name of the layer a top-level rect is in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layer: Option<String>,
+}
+
+/// A coordinate or size, in layout pixels or as a share of the scene's
+/// width or height.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "LengthItem", into = "LengthItem")]
+pub enum Length {
+    Pixels(f64),
+    Percent(f64),
+}
+
+impl Length {
+    /// The number as written, before it is resolved.
+    fn value(self) -> f64 {
+        match self {
+            Length::Pixels(value) | Length::Percent(value) => value,
+        }
+    }
+
+    /// The length in layout pixels, with percentages taken of `extent`.
+    fn resolve(self, extent: f32) -> f64 {
+        match self {
+            Length::Pixels(pixels) => pixels,
+            Length::Percent(percent) => percent / 100.0 * extent as f64,
+        }
+    }
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Length::Pixels(pixels) => write!(f, "{}", pixels),
+            Length::Percent(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+/// A `Length` as written: a number of pixels, or a string like `"25%"`.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+enum LengthItem {
+    Pixels(f64),
+    Percent(String),
+}
+
+impl TryFrom<LengthItem> for Length {
+    type Error = String;
+
+    fn try_from(item: LengthItem) -> Result<Length, String> {
+        match item {
+            LengthItem::Pixels(pixels) => Ok(Length::Pixels(pixels)),
+            LengthItem::Percent(text) => text
+                .strip_suffix('%')
+                .and_then(|percent| percent.trim().parse().ok())
+                .map(Length::Percent)
+                .ok_or_else(|| {
+                    format!(
+                        "expected a number of pixels or a percentage like \"25%\", found \"{}\"",
+                        text
+                    )
+                }),
+        }
+    }
+}
+
+impl From<Length> for LengthItem {
+    fn from(length: Length) -> LengthItem {
+        match length {
+            Length::Pixels(pixels) => LengthItem::Pixels(pixels),
+            Length::Percent(_) => LengthItem::Percent(length.to_string()),
+        }
+    }
+}
+
+/// Corner radii, written as one number for all four corners or as an array
+/// in CSS order.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum RadiusItem {
+    Uniform(f32),
+    Corners([f32; 4]),
+}
+
+impl RadiusItem {
+    fn corners(&self) -> [f32; 4] {
+        match *self {
+            RadiusItem::Uniform(radius) => [radius; 4],
+            RadiusItem::Corners(corners) => corners,
+        }
+    }
+
+    fn from_corners(corners: [f32; 4]) -> Option<RadiusItem> {
+        if corners.iter().all(|&radius| radius == 0.0) {
+            None
+        } else if corners.iter().all(|&radius| radius == corners[0]) {
+            Some(RadiusItem::Uniform(corners[0]))
+        } else {
+            Some(RadiusItem::Corners(corners))
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct BorderGradientItem {
+    pub stops: Vec<Vec<f32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direction: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<f32>,
+}
+
+impl BorderGradientItem {
+    fn direction(&self) -> f32 {
+        self.direction.unwrap_or(90.0)
+    }
+
+    fn width(&self) -> f32 {
+        self.width.unwrap_or(4.0)
+    }
+}
+
+/// A problem with one field of a `SceneFile`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneError {
+    /// Where the problem is, like `rects[2].color`.
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// Checks everything that parses but can't be drawn as intended, reporting
+/// every problem rather than stopping at the first.
+pub fn validate(file: &SceneFile) -> Result<(), Vec<SceneError>> {
+    let mut errors = Vec::new();
+
+    for (l, layer) in file.layers.iter().enumerate() {
+        let message = if layer.name.is_empty() {
+            "is empty"
+        } else if file.layers[..l]
+            .iter()
+            .any(|other| other.name == layer.name)
+        {
+            "is used by an earlier layer"
+        } else {
+            continue;
+        };
+        errors.push(SceneError {
+            location: format!("layers[{}].name", l),
+            message: format!("`{}` {}", layer.name, message),
+        });
+    }
+
+    for (l, layer) in file.layers.iter().enumerate() {
+        let mut error = |field: &str, message: String| {
+            errors.push(SceneError {
+                location: format!("layers[{}].{}", l, field),
+                message,
+            })
+        };
+        match layer.bounds {
+            Some(bounds) => {
+                if !bounds.iter().all(|length| length.value().is_finite()) {
+                    error(
+                        "bounds",
+                        "has a value that is not a finite number".to_string(),
+                    );
+                } else if bounds[2].value() <= 0.0 || bounds[3].value() <= 0.0 {
+                    error(
+                        "bounds",
+                        format!("{}x{} makes the box empty", bounds[2], bounds[3]),
+                    );
+                }
+            }
+            None if layer.overflow_hidden == Some(true) => {
+                error("overflow_hidden", "needs the layer's bounds".to_string());
+            }
+            None => (),
+        }
+    }
+
+    for (location, layer, rect) in file.all_rects() {
+        let mut error = |field: &str, message: String| {
+            errors.push(SceneError {
+                location: format!("{}.{}", location, field),
+                message,
+            })
+        };
+
+        if let Some(name) = &rect.layer {
+            if !location.starts_with("rects[") {
+                error(
+                    "layer",
+                    "can only be given for a top-level rect".to_string(),
+                );
+            } else if layer.is_none() {
+                error("layer", format!("`{}` is not a layer", name));
+            }
+        }
+
+        for (field, value) in [
+            ("x", rect.x),
+            ("y", rect.y),
+            ("width", rect.width),
+            ("height", rect.height),
+        ] {
+            if !value.value().is_finite() {
+                error(field, format!("{} is not a finite number", value));
+            }
+        }
+        for (field, value) in [("width", rect.width), ("height", rect.height)] {
+            if value.value() <= 0.0 {
+                error(field, format!("{} makes the rect empty", value));
+            }
+        }
+
+        for message in color_errors(&rect.color) {
+            error("color", message);
+        }
+        if let Some(opacity) = rect.opacity {
+            if !(0.0..=1.0).contains(&opacity) {
+                error("opacity", format!("{} is outside 0 to 1", opacity));
+            }
+        }
+        if let Some(radius) = &rect.radius {
+            for (corner, value) in ["top_left", "top_right", "bottom_right", "bottom_left"]
+                .iter()
+                .zip(radius.corners())
+            {
+                if !(value.is_finite() && value >= 0.0) {
+                    error(
+                        "radius",
+                        format!("{} radius {} is not 0 or more", corner, value),
+                    );
+                }
+            }
+        }
+        if let Some(rotation) = rect.rotation {
+            if !rotation.is_finite() {
+                error("rotation", format!("{} is not a finite number", rotation));
+            }
+        }
+        if let Some(border) = &rect.border_gradient {
+            if border.stops.len() < 2 {
+                error(
+                    "border_gradient.stops",
+                    format!("expected at least 2 stops, found {}", border.stops.len()),
+                );
+            }
+            for (s, stop) in border.stops.iter().enumerate() {
+                for message in color_errors(stop) {
+                    error(&format!("border_gradient.stops[{}]", s), message);
+                }
+            }
+            if !border.direction().is_finite() {
+                error(
+                    "border_gradient.direction",
+                    format!("{} is not a finite number", border.direction()),
+                );
+            }
+            // Only known here for sizes in pixels. A border too wide for a
+            // rect sized in percentages is clamped when it is laid out.
+            let half_side = match (rect.width, rect.height) {
+                (Length::Pixels(width), Length::Pixels(height)) => (width.min(height) / 2.0) as f32,
+                _ => f32::INFINITY,
+            };
+            let width = border.width();
+            if !(width.is_finite() && width > 0.0 && width <= half_side) {
+                error(
+                    "border_gradient.width",
+                    format!(
+                        "{} is not between 0 and half the rect's shorter side",
+                        width
+                    ),
+                );
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Problems with a `[r, g, b]` or `[r, g, b, a]` color.
+fn color_errors(color: &[f32]) -> Vec<String> {
+    let mut errors = Vec::new();
+    if color.len() != 3 && color.len() != 4 {
+        errors.push(format!("expected 3 or 4 components, found {}", color.len()));
+    }
+    for (c, &value) in color.iter().enumerate() {
+        if !(0.0..=1.0).contains(&value) {
+            errors.push(format!("component {} is {}, outside 0 to 1", c, value));
+        }
+    }
+    errors
+}
+
+fn to_color(color: &[f32]) -> ColorF {
+    ColorF::new(
+        color[0],
+        color[1],
+        color[2],
+        color.get(3).copied().unwrap_or(1.0),
+    )
+}
+
+fn from_color(color: ColorF) -> Vec<f32> {
+    vec![color.r, color.g, color.b, color.a]
+}
+
+impl SceneFile {
+    /// The file with `f` applied to every color, like
+    /// `SceneModel::map_colors`.
+    pub fn map_colors(&self, f: impl Fn(ColorF) -> ColorF) -> SceneFile {
+        let mut file = self.clone();
+        let rects = file
+            .rects
+            .iter_mut()
+            .chain(file.layers.iter_mut().flat_map(|layer| &mut layer.rects));
+        for rect in rects {
+            rect.color = from_color(f(to_color(&rect.color)));
+            if let Some(border) = &mut rect.border_gradient {
+                for stop in &mut border.stops {
+                    *stop = from_color(f(to_color(stop)));
+                }
+            }
+        }
+        file
+    }
+
+    /// Every rect in paint order, with where it is in the file and the index
+    /// of its layer.
+    fn all_rects(&self) -> impl Iterator<Item = (String, Option<usize>, &RectItem)> {
+        let top_level = self.rects.iter().enumerate().map(|(i, rect)| {
+            let layer = rect
+                .layer
+                .as_ref()
+                .and_then(|name| self.layers.iter().position(|layer| &layer.name == name));
+            (format!("rects[{}]", i), layer, rect)
+        });
+        let layered = self.layers.iter().enumerate().flat_map(|(l, layer)| {
+            layer
+                .rects
+                .iter()
+                .enumerate()
+                .map(move |(i, rect)| (format!("layers[{}].rects[{}]", l, i), Some(l), rect))
+        });
+        top_level.chain(layered)
+    }
+
+    /// The file that loads back into `model`, painted in the same order.
+    ///
+    /// The layers' own lists are painted last, one layer after another, so
+    /// only the longest run of layered rects at the end of the model that
+    /// is already in that order goes into them. Every rect before it is
+    /// written at the top level, naming its layer if it has one.
+    pub fn from_model(model: &SceneModel) -> SceneFile {
+        let mut blocks_start = model.rects.len();
+        let mut next_layer = usize::MAX;
+        for rect in model.rects.iter().rev() {
+            match rect.layer {
+                Some(layer) if layer <= next_layer => next_layer = layer,
+                _ => break,
+            }
+            blocks_start -= 1;
+        }
+        let (top_level, blocks) = model.rects.split_at(blocks_start);
+
+        let item = |rect: &SceneRect, named: bool| {
+            let (min, max) = (rect.bounds.min, rect.bounds.max);
+            RectItem {
+                x: Length::Pixels(min.x as f64),
+                y: Length::Pixels(min.y as f64),
+                width: Length::Pixels(max.x as f64 - min.x as f64),
+                height: Length::Pixels(max.y as f64 - min.y as f64),
+                color: from_color(rect.color),
+                opacity: Some(rect.opacity).filter(|&opacity| opacity < 1.0),
+                border_gradient: rect.border.as_ref().map(|border| BorderGradientItem {
+                    stops: border.colors.iter().copied().map(from_color).collect(),
+                    direction: Some(border.angle.to_degrees()),
+                    width: Some(border.width),
+                }),
+                radius: RadiusItem::from_corners(rect.corner_radii),
+                rotation: Some(rect.rotation).filter(|&rotation| rotation != 0.0),
+                layer: rect
+                    .layer
+                    .filter(|_| named)
+                    .map(|layer| model.layers[layer].name.clone()),
+            }
+        };
+
+        SceneFile {
+            rects: top_level.iter().map(|rect| item(rect, true)).collect(),
+            layers: model
+                .layers
+                .iter()
+                .enumerate()
+                .map(|(l, layer)| LayerItem {
+                    name: layer.name.clone(),
+                    visible: Some(false).filter(|_| !layer.visible),
+                    bounds: layer.bounds.map(|bounds| {
+                        let (min, max) = (bounds.min, bounds.max);
+                        [
+                            Length::Pixels(min.x as f64),
+                            Length::Pixels(min.y as f64),
+                            Length::Pixels(max.x as f64 - min.x as f64),
+                            Length::Pixels(max.y as f64 - min.y as f64),
+                        ]
+                    }),
+                    overflow_hidden: Some(true).filter(|_| layer.overflow_hidden),
+                    rects: blocks
+                        .iter()
+                        .filter(|rect| rect.layer == Some(l))
+                        .map(|rect| item(rect, false))
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Converts a validated file into the model that gets drawn, with
+    /// percentages taken of a scene of `size`.
+    pub fn to_model(&self, size: LayoutSize) -> SceneModel {
+        let bounds = |x: Length, y: Length, width: Length, height: Length| {
+            let (x, y) = (x.resolve(size.width), y.resolve(size.height));
+            LayoutRect::new(
+                LayoutPoint::new(x as f32, y as f32),
+                LayoutPoint::new(
+                    (x + width.resolve(size.width)) as f32,
+                    (y + height.resolve(size.height)) as f32,
+                ),
+            )
+        };
+        SceneModel {
+            rects: self
+                .all_rects()
+                .map(|(_, layer, rect)| {
+                    let bounds = bounds(rect.x, rect.y, rect.width, rect.height);
+                    // Validation checked borders against sizes in pixels.
+                    let half_side = match (rect.width, rect.height) {
+                        (Length::Pixels(_), Length::Pixels(_)) => f32::INFINITY,
+                        _ => bounds.width().min(bounds.height()) / 2.0,
+                    };
+                    SceneRect {
+                        bounds,
+                        color: to_color(&rect.color),
+                        opacity: rect.opacity.unwrap_or(1.0),
+                        border: rect.border_gradient.as_ref().map(|border| GradientBorder {
+                            width: border.width().min(half_side),
+                            colors: border.stops.iter().map(|stop| to_color(stop)).collect(),
+                            angle: border.direction().to_radians(),
+                        }),
+                        corner_radii: rect.radius.as_ref().map_or([0.0; 4], RadiusItem::corners),
+                        rotation: rect.rotation.unwrap_or(0.0),
+                        layer,
+                    }
+                })
+                .collect(),
+            layers: self
+                .layers
+                .iter()
+                .map(|layer| SceneLayer {
+                    name: layer.name.clone(),
+                    visible: layer.visible.unwrap_or(true),
+                    bounds: layer
+                        .bounds
+                        .map(|[x, y, width, height]| bounds(x, y, width, height)),
+                    overflow_hidden: layer.overflow_hidden.unwrap_or(false),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Reads, parses and validates the scene at `path`. The error is a report
+/// ready to print, listing every problem found.
+pub fn load(path: &Path) -> Result<SceneFile, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let file: SceneFile =
+        serde_json::from_str(&text).map_err(|err| format!("{}: {}", path.display(), err))?;
+
+    validate(&file).map_err(|errors| {
+        let mut report = format!("{}: {} problems found", path.display(), errors.len());
+        for error in errors {
+            report.push_str(&format!("\n  {}", error));
+        }
+        report
+    })?;
+
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::BuiltinScene;
+    use webrender::api::units::LayoutSize;
+
+    /// Exports `model` as JSON and loads it back.
+    fn round_trip(model: &SceneModel) -> SceneModel {
+        let json = serde_json::to_string(&SceneFile::from_model(model)).unwrap();
+        let file: SceneFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(validate(&file), Ok(()));
+        file.to_model(size())
+    }
+
+    fn assert_round_trips(model: &SceneModel) {
+        let reloaded = round_trip(model);
+        assert_eq!(reloaded.rects, model.rects);
+        assert_eq!(reloaded.layers, model.layers);
+    }
+
+    fn size() -> LayoutSize {
+        LayoutSize::new(1280.0, 720.0)
+    }
+
+    fn default_scene() -> SceneModel {
+        BuiltinScene::ThreeRects.model(size(), None)
+    }
+
+    fn rect(x: f32, opacity: f32, layer: Option<usize>) -> SceneRect {
+        SceneRect {
+            bounds: LayoutRect::new(LayoutPoint::new(x, 10.1), LayoutPoint::new(x + 50.3, 60.7)),
+            color: ColorF::new(0.2, 0.4, 0.6, 0.8),
+            opacity,
+            border: None,
+            corner_radii: [0.0; 4],
+            rotation: 0.0,
+            layer,
+        }
+    }
+
+    /// Parses a 10 by 10 rect with `radius` set to `json`.
+    fn parse_radius(json: &str) -> Result<RectItem, serde_json::Error> {
+        serde_json::from_str(&format!(
+            r#"{{ "x": 0, "y": 0, "width": 10, "height": 10, "color": [1, 0, 0], "radius": {} }}"#,
+            json
+        ))
+    }
+
+    /// Parses a rect with its geometry set to the JSON values given.
+    fn parse_geometry(x: &str, y: &str, width: &str, height: &str) -> serde_json::Result<RectItem> {
+        serde_json::from_str(&format!(
+            r#"{{ "x": {}, "y": {}, "width": {}, "height": {}, "color": [1, 0, 0] }}"#,
+            x, y, width, height
+        ))
+    }
+
+    /// Where `validate` finds problems in the scene file `json`, in the
+    /// order they are reported.
+    fn error_locations(json: &str) -> Vec<String> {
+        let file: SceneFile = serde_json::from_str(json).unwrap();
+        validate(&file)
+            .err()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|error| error.location)
+            .collect()
+    }
+
+    fn radius_errors(json: &str) -> Vec<SceneError> {
+        let file = SceneFile {
+            rects: vec![parse_radius(json).unwrap()],
+            layers: Vec::new(),
+        };
+        validate(&file).err().unwrap_or_default()
+    }
+
+    #[test]
+    fn round_trips_the_default_scene() {
+        assert_round_trips(&default_scene());
+    }
+
+    #[test]
+    fn round_trips_fractional_bounds_exactly() {
+        let model = SceneModel {
+            rects: vec![
+                rect(0.1, 1.0, None),
+                rect(1e6 + 0.3, 0.5, None),
+                rect(-33.3, 0.25, None),
+            ],
+            layers: Vec::new(),
+        };
+        assert_round_trips(&model);
+    }
+
+    #[test]
+    fn keeps_a_rect_added_over_layers_on_top() {
+        let mut model = default_scene();
+        model.rects.push(rect(100.0, 1.0, None));
+        assert_round_trips(&model);
+    }
+
+    #[test]
+    fn keeps_interleaved_layers_in_paint_order() {
+        let mut model = default_scene();
+        model.layers[1].visible = false;
+        model.rects = [Some(1), None, Some(0), Some(0), None, Some(1), Some(2)]
+            .iter()
+            .enumerate()
+            .map(|(i, &layer)| rect(i as f32 * 33.3, 1.0, layer))
+            .collect();
+        assert_round_trips(&model);
+    }
+
+    #[test]
+    fn one_radius_applies_to_every_corner() {
+        let rect = parse_radius("4").unwrap();
+        assert_eq!(rect.radius.map(|radius| radius.corners()), Some([4.0; 4]));
+    }
+
+    #[test]
+    fn four_radii_are_read_in_css_order() {
+        let rect = parse_radius("[1, 2, 3, 4.5]").unwrap();
+        assert_eq!(
+            rect.radius.map(|radius| radius.corners()),
+            Some([1.0, 2.0, 3.0, 4.5])
+        );
+    }
+
+    #[test]
+    fn other_radius_arrays_are_rejected() {
+        for json in ["[]", "[1, 2]", "[1, 2, 3, 4, 5]", "\"4\""] {
+            assert!(parse_radius(json).is_err(), "{}", json);
+        }
+    }
+
+    #[test]
+    fn negative_radii_are_reported() {
+        assert_eq!(radius_errors("[0, 2, 3, 4]"), Vec::new());
+        let errors = radius_errors("[0, -2, 3, -4]");
+        assert_eq!(errors.len(), 2);
+        assert!(errors
+            .iter()
+            .all(|error| error.location == "rects[0].radius"));
+        assert!(errors[0].message.starts_with("top_right"));
+        assert!(errors[1].message.starts_with("bottom_left"));
+    }
+
+    #[test]
+    fn matching_corners_are_written_as_one_radius() {
+        assert!(RadiusItem::from_corners([0.0; 4]).is_none());
+        assert!(matches!(
+            RadiusItem::from_corners([3.0; 4]),
+            Some(RadiusItem::Uniform(radius)) if radius == 3.0
+        ));
+        assert!(matches!(
+            RadiusItem::from_corners([3.0, 3.0, 0.0, 3.0]),
+            Some(RadiusItem::Corners([3.0, 3.0, 0.0, 3.0]))
+        ));
+    }
+
+    #[test]
+    fn round_trips_corner_radii() {
+        let mut uniform = rect(0.0, 1.0, None);
+        uniform.corner_radii = [6.0; 4];
+        let mut corners = rect(100.0, 1.0, None);
+        corners.corner_radii = [12.0, 12.0, 0.0, 0.0];
+        let model = SceneModel {
+            rects: vec![uniform, corners],
+            layers: Vec::new(),
+        };
+        assert_round_trips(&model);
+    }
+
+    #[test]
+    fn percentages_are_taken_of_the_scene_size() {
+        // Mixed with pixels, which are taken as they are.
+        let rect = parse_geometry(r#""10%""#, "36", r#""25%""#, r#""12.5%""#).unwrap();
+        let file = SceneFile {
+            rects: vec![rect],
+            layers: Vec::new(),
+        };
+        assert_eq!(validate(&file), Ok(()));
+        assert_eq!(
+            file.to_model(size()).rects[0].bounds,
+            LayoutRect::new(
+                LayoutPoint::new(128.0, 36.0),
+                LayoutPoint::new(448.0, 126.0)
+            )
+        );
+    }
+
+    #[test]
+    fn malformed_percentages_are_rejected() {
+        for length in [r#""%""#, r#""12%%""#, r#""12""#, r#""twelve%""#, r#""""#] {
+            assert!(
+                parse_geometry(length, "0", "10", "10").is_err(),
+                "{}",
+                length
+            );
+        }
+    }
+
+    #[test]
+    fn percentages_are_validated_as_written() {
+        let rect = parse_geometry("0", "0", r#""-5%""#, r#""0%""#).unwrap();
+        let file = SceneFile {
+            rects: vec![rect],
+            layers: Vec::new(),
+        };
+        let errors = validate(&file).unwrap_err();
+        let locations: Vec<&str> = errors.iter().map(|error| error.location.as_str()).collect();
+        assert_eq!(locations, ["rects[0].width", "rects[0].height"]);
+    }
+
+    #[test]
+    fn load_export_load_is_stable() {
+        let text = r#"{
+            "rects": [
+                { "x": "10%", "y": 20, "width": "25%", "height": "12.5%", "color": [1, 0, 0] },
+                { "x": 0.1, "y": 0.2, "width": 30.3, "height": 40.7, "color": [0, 1, 0, 0.5],
+                  "opacity": 0.75, "radius": [4, 8, 0, 2], "rotation": 12.5, "layer": "top" }
+            ],
+            "layers": [
+                { "name": "clipped", "bounds": [10, 10, "50%", "50%"], "overflow_hidden": true,
+                  "rects": [{ "x": 5, "y": 5, "width": 500, "height": 500, "color": [0, 0, 1] }] },
+                { "name": "top", "visible": false,
+                  "rects": [{ "x": 7, "y": 9, "width": 11, "height": 13, "color": [1, 1, 0] }] }
+            ]
+        }"#;
+        let file: SceneFile = serde_json::from_str(text).unwrap();
+        assert_eq!(validate(&file), Ok(()));
+        let model = file.to_model(size());
+        assert_round_trips(&model);
+        assert_eq!(
+            serde_json::to_string(&SceneFile::from_model(&round_trip(&model))).unwrap(),
+            serde_json::to_string(&SceneFile::from_model(&model)).unwrap()
+        );
+    }
+
+    #[test]
+    fn missing_fields_are_rejected() {
+        let fields = [
+            r#""x": 0"#,
+            r#""y": 0"#,
+            r#""width": 10"#,
+            r#""height": 10"#,
+            r#""color": [1, 0, 0]"#,
+        ];
+        for (missing, name) in ["x", "y", "width", "height", "color"].iter().enumerate() {
+            let rect: Vec<_> = fields
+                .iter()
+                .enumerate()
+                .filter(|&(f, _)| f != missing)
+                .map(|(_, field)| *field)
+                .collect();
+            let json = format!(r#"{{ "rects": [{{ {} }}] }}"#, rect.join(", "));
+            let err = serde_json::from_str::<SceneFile>(&json).unwrap_err();
+            assert!(
+                err.to_string()
+                    .contains(&format!("missing field `{}`", name)),
+                "{}: {}",
+                json,
+                err
+            );
+        }
+
+        let err =
+            serde_json::from_str::<SceneFile>(r#"{ "layers": [{ "rects": [] }] }"#).unwrap_err();
+        assert!(err.to_string().contains("missing field `name`"), "{}", err);
+    }
+
+    #[test]
+    fn empty_and_negative_sizes_are_reported() {
+        assert_eq!(
+            error_locations(
+                r#"{ "rects": [
+                    { "x": 0, "y": 0, "width": -10, "height": 0, "color": [1, 0, 0] },
+                    { "x": -10, "y": -10, "width": 10, "height": 10, "color": [1, 0, 0] }
+                ] }"#
+            ),
+            ["rects[0].width", "rects[0].height"]
+        );
+        assert_eq!(
+            error_locations(
+                r#"{ "layers": [
+                    { "name": "a", "bounds": [0, 0, -5, 10] },
+                    { "name": "b", "bounds": [0, 0, 10, "0%"] },
+                    { "name": "c", "overflow_hidden": true }
+                ] }"#
+            ),
+            [
+                "layers[0].bounds",
+                "layers[1].bounds",
+                "layers[2].overflow_hidden"
+            ]
+        );
+    }
+
+    #[test]
+    fn out_of_range_colors_are_reported() {
+        assert_eq!(
+            error_locations(
+                r#"{ "rects": [
+                    { "x": 0, "y": 0, "width": 10, "height": 10, "color": [1.5, 0, -0.1] },
+                    { "x": 0, "y": 0, "width": 10, "height": 10, "color": [1, 0] },
+                    { "x": 0, "y": 0, "width": 10, "height": 10, "color": [1, 0, 0, 1],
+                      "opacity": 1.5 },
+                    { "x": 0, "y": 0, "width": 10, "height": 10, "color": [0, 0, 0, 0],
+                      "border_gradient": { "stops": [[1, 0, 0], [0, 2, 0]] } }
+                ] }"#
+            ),
+            [
+                "rects[0].color",
+                "rects[0].color",
+                "rects[1].color",
+                "rects[2].opacity",
+                "rects[3].border_gradient.stops[1]",
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_layer_references_are_reported() {
+        let file: SceneFile = serde_json::from_str(
+            r#"{
+                "rects": [
+                    { "x": 0, "y": 0, "width": 10, "height": 10, "color": [1, 0, 0],
+                      "layer": "labels" },
+                    { "x": 0, "y": 0, "width": 10, "height": 10, "color": [1, 0, 0],
+                      "layer": "missing" }
+                ],
+                "layers": [{ "name": "labels", "rects": [
+                    { "x": 0, "y": 0, "width": 10, "height": 10, "color": [1, 0, 0],
+                      "layer": "labels" }
+                ] }]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            validate(&file),
+            Err(vec![
+                SceneError {
+                    location: "rects[1].layer".to_string(),
+                    message: "`missing` is not a layer".to_string(),
+                },
+                SceneError {
+                    location: "layers[0].rects[0].layer".to_string(),
+                    message: "can only be given for a top-level rect".to_string(),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn duplicate_and_empty_layer_names_are_reported() {
+        assert_eq!(
+            error_locations(r#"{ "layers": [{ "name": "a" }, { "name": "" }, { "name": "a" }] }"#),
+            ["layers[1].name", "layers[2].name"]
+        );
+    }
+}
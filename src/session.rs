@@ -0,0 +1,292 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use webrender::api::{
+    units::{DeviceIntPoint, DeviceIntSize, LayoutPoint, LayoutRect, LayoutSize, LayoutVector2D},
+    ColorF,
+};
+
+use crate::{
+    app::App,
+    scene::SceneRect,
+    scene_file::{self, SceneFile},
+    text_input::TextEdit,
+    touch::Phase,
+};
+
+/// Latest time accepted in a log, in seconds, well short of what
+/// `Duration` can hold.
+const MAX_TIME: f64 = 1e9;
+
+/// A change made to the app in response to input, as recorded by
+/// `--record-transactions`.
+///
+/// Webrender's transactions can't be serialized, so the session is recorded
+/// as the calls that built them instead. Replaying those through the same
+/// `App` methods sends the same transactions again. Points and sizes are in
+/// device pixels of the document.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Resize {
+        width: i32,
+        height: i32,
+    },
+    DevicePixelRatio(f32),
+    PointerMoved {
+        x: i32,
+        y: i32,
+    },
+    PointerDown {
+        x: i32,
+        y: i32,
+    },
+    PointerUp,
+    /// A finger, told apart from others by `id`, starting, moving or
+    /// lifting at a point.
+    Touch {
+        id: u64,
+        phase: Phase,
+        x: i32,
+        y: i32,
+    },
+    /// A wheel turn at a point, scrolling the content by `dx` and `dy`.
+    Wheel {
+        x: i32,
+        y: i32,
+        dx: f32,
+        dy: f32,
+    },
+    MeasureClick {
+        x: i32,
+        y: i32,
+    },
+    ClearMeasurement,
+    ToggleGrid,
+    ToggleWireframe,
+    ToggleClipDebug,
+    ToggleImageSampling,
+    ToggleLayer(usize),
+    ScrollTo(usize),
+    RotateHue(f32),
+    RotateRect(f32),
+    ResetView,
+    Wipe,
+    RecreateDocument,
+    EditText(TextEdit),
+    BlurTextInput,
+    SubmitTextInput,
+    /// A rect added with `add_rect`, with its color as it was given.
+    AddRect {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        color: [f32; 4],
+    },
+    ClearScene,
+    Zoom(f32),
+    /// The color the eyedropper read back and applied.
+    PickColor([f32; 4]),
+    /// A `--watch` reload, with the scene in the `--scene` format as it was
+    /// read, so that replaying doesn't depend on the file.
+    ReloadScene(serde_json::Value),
+}
+
+impl Action {
+    pub fn reload_scene(file: &SceneFile) -> Action {
+        Action::ReloadScene(serde_json::to_value(file).expect("scene files always serialize"))
+    }
+
+    pub fn apply(&self, app: &mut App) {
+        match *self {
+            Action::Resize { width, height } => app.resize(DeviceIntSize::new(width, height)),
+            Action::DevicePixelRatio(ratio) => app.set_device_pixel_ratio(ratio),
+            Action::PointerMoved { x, y } => app.pointer_moved(DeviceIntPoint::new(x, y)),
+            Action::PointerDown { x, y } => app.pointer_down(DeviceIntPoint::new(x, y)),
+            Action::PointerUp => app.pointer_up(),
+            Action::Wheel { x, y, dx, dy } => {
+                app.wheel(DeviceIntPoint::new(x, y), LayoutVector2D::new(dx, dy))
+            }
+            Action::Touch { id, phase, x, y } => app.touch(id, phase, DeviceIntPoint::new(x, y)),
+            Action::MeasureClick { x, y } => app.measure_click(DeviceIntPoint::new(x, y)),
+            Action::ClearMeasurement => {
+                app.clear_measurement();
+            }
+            Action::ToggleGrid => app.toggle_grid(),
+            Action::ToggleWireframe => app.toggle_wireframe(),
+            Action::ToggleClipDebug => app.toggle_clip_debug(),
+            Action::ToggleImageSampling => app.toggle_image_sampling(),
+            Action::ToggleLayer(index) => app.toggle_layer(index),
+            Action::ScrollTo(index) => app.scroll_to(index),
+            Action::RotateHue(degrees) => app.rotate_selected_hue(degrees),
+            Action::RotateRect(degrees) => app.rotate_selected(degrees),
+            Action::ResetView => app.reset_view(),
+            Action::Wipe => app.wipe(),
+            Action::RecreateDocument => app.recreate_document(),
+            Action::EditText(edit) => app.edit_text(edit),
+            Action::BlurTextInput => app.blur_text_input(),
+            Action::SubmitTextInput => app.submit_text_input(),
+            Action::AddRect {
+                x,
+                y,
+                width,
+                height,
+                color: [r, g, b, a],
+            } => app.add_rect(SceneRect {
+                bounds: LayoutRect::from_origin_and_size(
+                    LayoutPoint::new(x, y),
+                    LayoutSize::new(width, height),
+                ),
+                color: ColorF::new(r, g, b, a),
+                opacity: 1.0,
+                border: None,
+                corner_radii: [0.0; 4],
+                rotation: 0.0,
+                layer: None,
+            }),
+            Action::ClearScene => app.clear_scene(),
+            Action::Zoom(zoom) => app.zoom(zoom),
+            Action::PickColor([r, g, b, a]) => app.apply_picked_color(ColorF::new(r, g, b, a)),
+            Action::ReloadScene(ref scene) => app.set_scene_file(
+                serde_json::from_value(scene.clone())
+                    .map_err(|err| format!("invalid scene in the log: {}", err)),
+            ),
+        }
+    }
+}
+
+/// One line of the log: an action and when it happened, in seconds since
+/// recording started.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Entry {
+    time: f64,
+    action: Action,
+}
+
+/// Appends every action to a `--record-transactions` log as a line of JSON.
+pub struct Recorder {
+    file: File,
+    started: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> Result<Recorder, String> {
+        let file = File::create(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        Ok(Recorder {
+            file,
+            started: Instant::now(),
+        })
+    }
+
+    /// Writes `action` out straight away, so a session that ends in a
+    /// crash is still on disk.
+    pub fn record(&mut self, now: Instant, action: &Action) {
+        let entry = Entry {
+            time: now.saturating_duration_since(self.started).as_secs_f64(),
+            action: action.clone(),
+        };
+        let written = serde_json::to_string(&entry)
+            .map_err(|err| err.to_string())
+            .and_then(|line| writeln!(self.file, "{}", line).map_err(|err| err.to_string()));
+        if let Err(err) = written {
+            error!("failed to record {:?}: {}", action, err);
+        }
+    }
+}
+
+/// Plays a recorded log back with `--replay`, on the timeline it was
+/// recorded on sped up by `speed`.
+///
+/// The timeline follows the clock the event loop passes in, so under
+/// `--fixed-timestep` it runs as fast as frames render.
+pub struct Replay {
+    entries: Vec<Entry>,
+    next: usize,
+    started: Option<Instant>,
+    speed: f64,
+}
+
+impl Replay {
+    pub fn load(path: &Path, speed: f64) -> Result<Replay, String> {
+        let text =
+            fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let mut entries = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: Entry = serde_json::from_str(line)
+                .map_err(|err| format!("{}:{}: {}", path.display(), i + 1, err))?;
+            if !(0.0..=MAX_TIME).contains(&entry.time) {
+                return Err(format!(
+                    "{}:{}: invalid time {}",
+                    path.display(),
+                    i + 1,
+                    entry.time
+                ));
+            }
+            entries.push(entry);
+        }
+        info!(
+            "replaying {} actions from {}",
+            entries.len(),
+            path.display()
+        );
+        Ok(Replay {
+            entries,
+            next: 0,
+            started: None,
+            speed,
+        })
+    }
+
+    /// Applies every action due by `now` and returns when the next one is,
+    /// or `None` once the whole log has been played.
+    pub fn update(&mut self, app: &mut App, now: Instant) -> Option<Instant> {
+        let started = *self.started.get_or_insert(now);
+        while let Some(entry) = self.entries.get(self.next) {
+            let due = started + Duration::from_secs_f64(entry.time / self.speed);
+            if due > now {
+                return Some(due);
+            }
+            entry.action.apply(app);
+            self.next += 1;
+        }
+        None
+    }
+}
+
+/// Checks that the app's scene is the one saved at `path`, as
+/// `--expect-scene` does once a replay ends. Both are compared as the JSON
+/// Ctrl+Shift+S would save, and the error names the first line that
+/// differs.
+pub fn check_scene(app: &App, path: &Path) -> Result<(), String> {
+    let expected = scene_file::load(path)?.to_model(app.coords().scene_size());
+    let expected = serde_json::to_string_pretty(&SceneFile::from_model(&expected))
+        .expect("scene models always serialize");
+    let actual = app.export_scene();
+    if actual == expected {
+        return Ok(());
+    }
+    let (line, (actual, expected)) = actual
+        .lines()
+        .chain(std::iter::repeat(""))
+        .zip(expected.lines().chain(std::iter::repeat("")))
+        .enumerate()
+        .find(|(_, (actual, expected))| actual != expected)
+        .expect("different texts differ on some line");
+    Err(format!(
+        "the scene differs from {} at line {}: expected `{}`, got `{}`",
+        path.display(),
+        line + 1,
+        expected.trim(),
+        actual.trim()
+    ))
+}
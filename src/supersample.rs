@@ -0,0 +1,190 @@
+use gleam::gl::{self, GLuint, Gl};
+use log::{info, warn};
+use webrender::api::units::DeviceIntSize;
+
+/// An offscreen framebuffer with a color and a depth renderbuffer.
+struct Target {
+    size: DeviceIntSize,
+    fbo: GLuint,
+    renderbuffers: Vec<GLuint>,
+}
+
+impl Target {
+    fn new(gl: &dyn Gl, size: DeviceIntSize, depth: bool) -> Target {
+        let fbo = gl.gen_framebuffers(1)[0];
+        let renderbuffers = gl.gen_renderbuffers(if depth { 2 } else { 1 });
+        gl.bind_framebuffer(gl::FRAMEBUFFER, fbo);
+
+        let formats = [
+            (gl::RGBA8, gl::COLOR_ATTACHMENT0),
+            (gl::DEPTH_COMPONENT24, gl::DEPTH_ATTACHMENT),
+        ];
+        for (&renderbuffer, (format, attachment)) in renderbuffers.iter().zip(formats) {
+            gl.bind_renderbuffer(gl::RENDERBUFFER, renderbuffer);
+            gl.renderbuffer_storage(gl::RENDERBUFFER, format, size.width, size.height);
+            gl.framebuffer_renderbuffer(
+                gl::FRAMEBUFFER,
+                attachment,
+                gl::RENDERBUFFER,
+                renderbuffer,
+            );
+        }
+        if gl.check_frame_buffer_status(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
+            warn!("supersample target {:?} is incomplete", size);
+        }
+        gl.bind_framebuffer(gl::FRAMEBUFFER, 0);
+
+        Target {
+            size,
+            fbo,
+            renderbuffers,
+        }
+    }
+
+    /// Bytes of GPU memory held by the renderbuffers.
+    fn memory(&self) -> usize {
+        self.size.width as usize * self.size.height as usize * 4 * self.renderbuffers.len()
+    }
+
+    fn delete(self, gl: &dyn Gl) {
+        gl.delete_framebuffers(&[self.fbo]);
+        gl.delete_renderbuffers(&self.renderbuffers);
+    }
+}
+
+/// Renders at `factor` times the window resolution into offscreen targets
+/// and downsamples to the window, for `--supersample`.
+///
+/// Webrender draws into whatever framebuffer is bound when rendering starts,
+/// so binding the large target first is all it takes. Downsampling halves
+/// the size with a linear blit per step, so every source pixel contributes
+/// even at a factor of 4.
+///
+/// No target is allowed past `limit` on either side, the same limit the
+/// document is capped at, or past the largest renderbuffer the context
+/// supports. The factor is lowered at startup to fit, and a target that
+/// outgrows it on a later resize is clamped.
+pub struct Supersampler {
+    factor: i32,
+    limit: i32,
+    window_size: DeviceIntSize,
+    /// Largest first, each half the size of the one before.
+    targets: Vec<Target>,
+}
+
+impl Supersampler {
+    /// Returns `None` if not even a factor of 2 fits in `limit`.
+    pub fn new(
+        gl: &dyn Gl,
+        requested: i32,
+        window_size: DeviceIntSize,
+        limit: i32,
+    ) -> Option<Supersampler> {
+        let mut renderbuffer = [0];
+        unsafe {
+            gl.get_integer_v(gl::MAX_RENDERBUFFER_SIZE, &mut renderbuffer);
+        }
+        let limit = limit.min(renderbuffer[0]);
+
+        let mut factor = requested;
+        while factor > 1 && window_size.width.max(window_size.height) * factor > limit {
+            factor /= 2;
+        }
+        if factor != requested {
+            warn!(
+                "supersampling {}x at {}x{} exceeds the {}px limit, using {}x",
+                requested, window_size.width, window_size.height, limit, factor
+            );
+        }
+        if factor == 1 {
+            return None;
+        }
+
+        let mut supersampler = Supersampler {
+            factor,
+            limit,
+            window_size,
+            targets: Vec::new(),
+        };
+        supersampler.resize(gl, window_size);
+        Some(supersampler)
+    }
+
+    pub fn factor(&self) -> i32 {
+        self.factor
+    }
+
+    /// The size webrender renders at: the window's size times the factor,
+    /// unless the target had to be clamped.
+    pub fn render_size(&self) -> DeviceIntSize {
+        self.targets[0].size
+    }
+
+    /// Reallocates the targets for a new window size.
+    pub fn resize(&mut self, gl: &dyn Gl, window_size: DeviceIntSize) {
+        for target in self.targets.drain(..) {
+            target.delete(gl);
+        }
+
+        self.window_size = window_size;
+        let mut scale = self.factor;
+        while scale > 1 {
+            let size = window_size * scale;
+            let clamped = size.min(DeviceIntSize::new(self.limit, self.limit));
+            if clamped != size {
+                warn!(
+                    "supersample target {:?} exceeds the {}px limit, clamped to {:?}",
+                    size, self.limit, clamped
+                );
+            }
+            // Only webrender's target needs depth.
+            let depth = self.targets.is_empty();
+            self.targets.push(Target::new(gl, clamped, depth));
+            scale /= 2;
+        }
+
+        let memory: usize = self.targets.iter().map(Target::memory).sum();
+        info!(
+            "supersampling {}x: {:.1} MiB of extra render targets",
+            self.factor,
+            memory as f64 / (1024.0 * 1024.0)
+        );
+    }
+
+    /// Binds the full-size target; call before `Renderer::render`.
+    pub fn bind(&self, gl: &dyn Gl) {
+        gl.bind_framebuffer(gl::FRAMEBUFFER, self.targets[0].fbo);
+    }
+
+    /// Downsamples the rendered frame into the window's framebuffer and
+    /// leaves that bound.
+    pub fn resolve(&self, gl: &dyn Gl) {
+        for (i, source) in self.targets.iter().enumerate() {
+            let (fbo, size) = match self.targets.get(i + 1) {
+                Some(target) => (target.fbo, target.size),
+                None => (0, self.window_size),
+            };
+            gl.bind_framebuffer(gl::READ_FRAMEBUFFER, source.fbo);
+            gl.bind_framebuffer(gl::DRAW_FRAMEBUFFER, fbo);
+            gl.blit_framebuffer(
+                0,
+                0,
+                source.size.width,
+                source.size.height,
+                0,
+                0,
+                size.width,
+                size.height,
+                gl::COLOR_BUFFER_BIT,
+                gl::LINEAR,
+            );
+        }
+        gl.bind_framebuffer(gl::FRAMEBUFFER, 0);
+    }
+
+    pub fn deinit(mut self, gl: &dyn Gl) {
+        for target in self.targets.drain(..) {
+            target.delete(gl);
+        }
+    }
+}
@@ -0,0 +1,488 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use log::warn;
+use webrender::api::{
+    units::{LayoutPoint, LayoutRect, LayoutSize},
+    ColorF,
+};
+
+use crate::scene::{SceneModel, SceneRect};
+
+/// An opening, closing or empty tag, with its attributes in order.
+struct Tag<'a> {
+    name: &'a str,
+    attributes: Vec<(&'a str, &'a str)>,
+    closing: bool,
+    empty: bool,
+}
+
+impl<'a> Tag<'a> {
+    /// An attribute, or the same property in the `style` attribute, which
+    /// takes precedence as in CSS.
+    fn get(&self, name: &str) -> Option<&'a str> {
+        let styled = self
+            .attributes
+            .iter()
+            .find(|&&(attribute, _)| attribute == "style")
+            .and_then(|&(_, style)| {
+                style.split(';').find_map(|declaration| {
+                    let (property, value) = declaration.split_once(':')?;
+                    Some(value.trim()).filter(|_| property.trim() == name)
+                })
+            });
+        styled.or_else(|| {
+            self.attributes
+                .iter()
+                .find(|&&(attribute, _)| attribute == name)
+                .map(|&(_, value)| value)
+        })
+    }
+}
+
+/// Splits `text` into its tags, skipping text, comments, processing
+/// instructions and declarations.
+fn tags(text: &str) -> Result<Vec<Tag<'_>>, String> {
+    let mut tags = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start..];
+        let skip_to = |rest: &str, end: &str| {
+            rest.find(end)
+                .map(|offset| offset + end.len())
+                .ok_or_else(|| format!("unterminated `{}`", &rest[..rest.len().min(20)]))
+        };
+        if rest.starts_with("<!--") {
+            rest = &rest[skip_to(rest, "-->")?..];
+            continue;
+        }
+        if rest.starts_with("<?") || rest.starts_with("<!") {
+            rest = &rest[skip_to(rest, ">")?..];
+            continue;
+        }
+
+        let end = tag_end(rest)
+            .ok_or_else(|| format!("unterminated tag `{}`", &rest[..rest.len().min(20)]))?;
+        let inner = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        let (closing, inner) = match inner.strip_prefix('/') {
+            Some(inner) => (true, inner),
+            None => (false, inner),
+        };
+        let (empty, inner) = match inner.strip_suffix('/') {
+            Some(inner) => (true, inner),
+            None => (false, inner),
+        };
+        let name_end = inner
+            .find(|c: char| c.is_whitespace())
+            .unwrap_or(inner.len());
+        tags.push(Tag {
+            name: &inner[..name_end],
+            attributes: attributes(&inner[name_end..])?,
+            closing,
+            empty,
+        });
+    }
+    Ok(tags)
+}
+
+/// The offset of the `>` ending the tag that `s` starts with, past any
+/// quoted attribute values.
+fn tag_end(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(open), c) if c == open => quote = None,
+            (None, '>') => return Some(i),
+            _ => (),
+        }
+    }
+    None
+}
+
+fn attributes(mut s: &str) -> Result<Vec<(&str, &str)>, String> {
+    let mut attributes = Vec::new();
+    loop {
+        s = s.trim_start();
+        if s.is_empty() {
+            return Ok(attributes);
+        }
+        let (name, rest) = s
+            .split_once('=')
+            .ok_or_else(|| format!("attribute `{}` has no value", s))?;
+        let rest = rest.trim_start();
+        let quote = rest
+            .chars()
+            .next()
+            .filter(|&c| c == '"' || c == '\'')
+            .ok_or_else(|| format!("value of `{}` isn't quoted", name.trim()))?;
+        let value_end = rest[1..]
+            .find(quote)
+            .ok_or_else(|| format!("value of `{}` isn't closed", name.trim()))?;
+        attributes.push((name.trim(), &rest[1..1 + value_end]));
+        s = &rest[value_end + 2..];
+    }
+}
+
+/// Parses an SVG color: `#rgb`, `#rrggbb`, `rgb(r, g, b)` with numbers or
+/// percentages, or one of a few names. `none` is `Ok(None)`.
+fn parse_color(s: &str) -> Result<Option<ColorF>, String> {
+    let s = s.trim();
+    let hex = |digits: &str| {
+        u8::from_str_radix(digits, 16)
+            .ok()
+            .map(|v| v as f32 / 255.0)
+    };
+    let color = match s {
+        "none" | "transparent" => return Ok(None),
+        "black" => Some(ColorF::BLACK),
+        "white" => Some(ColorF::WHITE),
+        "red" => Some(ColorF::new(1.0, 0.0, 0.0, 1.0)),
+        "green" => Some(ColorF::new(0.0, 0.5, 0.0, 1.0)),
+        "lime" => Some(ColorF::new(0.0, 1.0, 0.0, 1.0)),
+        "blue" => Some(ColorF::new(0.0, 0.0, 1.0, 1.0)),
+        "yellow" => Some(ColorF::new(1.0, 1.0, 0.0, 1.0)),
+        "orange" => Some(ColorF::new(1.0, 0.647, 0.0, 1.0)),
+        "purple" => Some(ColorF::new(0.5, 0.0, 0.5, 1.0)),
+        "gray" | "grey" => Some(ColorF::new(0.5, 0.5, 0.5, 1.0)),
+        _ if s.starts_with('#') => {
+            let digits = &s[1..];
+            match digits.len() {
+                3 => {
+                    let channel = |i: usize| hex(&digits[i..i + 1].repeat(2));
+                    channel(0)
+                        .zip(channel(1))
+                        .zip(channel(2))
+                        .map(|((r, g), b)| ColorF::new(r, g, b, 1.0))
+                }
+                6 => {
+                    let channel = |i: usize| hex(&digits[i..i + 2]);
+                    channel(0)
+                        .zip(channel(2))
+                        .zip(channel(4))
+                        .map(|((r, g), b)| ColorF::new(r, g, b, 1.0))
+                }
+                _ => None,
+            }
+        }
+        _ => s
+            .strip_prefix("rgb(")
+            .and_then(|rest| rest.strip_suffix(')'))
+            .and_then(|channels| {
+                let channels: Vec<f32> = channels
+                    .split(',')
+                    .map(|channel| {
+                        let channel = channel.trim();
+                        match channel.strip_suffix('%') {
+                            Some(percent) => percent.parse::<f32>().ok().map(|p| p / 100.0),
+                            None => channel.parse::<f32>().ok().map(|v| v / 255.0),
+                        }
+                    })
+                    .collect::<Option<_>>()?;
+                match channels[..] {
+                    [r, g, b] => Some(ColorF::new(
+                        r.clamp(0.0, 1.0),
+                        g.clamp(0.0, 1.0),
+                        b.clamp(0.0, 1.0),
+                        1.0,
+                    )),
+                    _ => None,
+                }
+            }),
+    };
+    color
+        .map(Some)
+        .ok_or_else(|| format!("unsupported color `{}`", s))
+}
+
+/// Parses a length in user units, which are layout pixels here, with an
+/// optional `px`.
+fn parse_length(tag: &Tag, name: &str) -> Result<Option<f32>, String> {
+    let value = match tag.get(name) {
+        Some(value) => value.trim(),
+        None => return Ok(None),
+    };
+    match value.strip_suffix("px").unwrap_or(value).parse::<f32>() {
+        Ok(length) if length.is_finite() => Ok(Some(length)),
+        _ => Err(format!("unsupported {} `{}`", name, value)),
+    }
+}
+
+fn parse_opacity(tag: &Tag, name: &str) -> Result<f32, String> {
+    match tag.get(name) {
+        Some(value) => match value.trim().parse::<f32>() {
+            Ok(opacity) if opacity.is_finite() => Ok(opacity.clamp(0.0, 1.0)),
+            _ => Err(format!("unsupported {} `{}`", name, value)),
+        },
+        None => Ok(1.0),
+    }
+}
+
+/// The fill a shape inherits from the groups around it.
+#[derive(Clone, Copy)]
+struct Paint {
+    fill: Option<ColorF>,
+    opacity: f32,
+}
+
+impl Paint {
+    /// The paint inside `tag`, given the paint around it.
+    fn within(self, tag: &Tag) -> Result<Paint, String> {
+        let fill = match tag.get("fill") {
+            Some(fill) => parse_color(fill)?,
+            None => self.fill,
+        };
+        // Opacity on a single shape fades it like its fill's alpha. On a
+        // group it would fade overlapping shapes together, which this
+        // approximates by fading each.
+        let opacity = self.opacity * parse_opacity(tag, "opacity")?;
+        Ok(Paint { fill, opacity })
+    }
+}
+
+/// The rect `tag` draws, or `None` when it isn't filled.
+fn shape(tag: &Tag, paint: Paint) -> Result<Option<SceneRect>, String> {
+    let paint = paint.within(tag)?;
+    let mut color = match paint.fill {
+        Some(color) => color,
+        None => return Ok(None),
+    };
+    color.a *= paint.opacity * parse_opacity(tag, "fill-opacity")?;
+
+    let length = |name: &str| parse_length(tag, name);
+    let (bounds, radius) = match tag.name {
+        "rect" => {
+            let origin = LayoutPoint::new(length("x")?.unwrap_or(0.0), length("y")?.unwrap_or(0.0));
+            let size = LayoutSize::new(
+                length("width")?.unwrap_or(0.0),
+                length("height")?.unwrap_or(0.0),
+            );
+            // Corners are rounded alike, so elliptical ones get the smaller
+            // of their radii.
+            let (rx, ry) = match (length("rx")?, length("ry")?) {
+                (Some(rx), Some(ry)) => (rx, ry),
+                (Some(r), None) | (None, Some(r)) => (r, r),
+                (None, None) => (0.0, 0.0),
+            };
+            let radius = rx.min(size.width / 2.0).min(ry.min(size.height / 2.0));
+            (LayoutRect::from_origin_and_size(origin, size), radius)
+        }
+        _ => {
+            let center =
+                LayoutPoint::new(length("cx")?.unwrap_or(0.0), length("cy")?.unwrap_or(0.0));
+            let r = length("r")?.unwrap_or(0.0);
+            let bounds = LayoutRect::new(
+                LayoutPoint::new(center.x - r, center.y - r),
+                LayoutPoint::new(center.x + r, center.y + r),
+            );
+            (bounds, r)
+        }
+    };
+    if bounds.is_empty() {
+        return Err("is empty".to_string());
+    }
+    Ok(Some(SceneRect {
+        bounds,
+        color,
+        opacity: 1.0,
+        border: None,
+        corner_radii: [radius.max(0.0); 4],
+        rotation: 0.0,
+        layer: None,
+    }))
+}
+
+/// Builds a scene from the `<rect>` and `<circle>` elements of an SVG
+/// document, in document order, with their fills inherited through
+/// `<svg>` and `<g>`. A circle becomes a rect with corners rounded to its
+/// radius.
+///
+/// Anything else, or any attribute that would move or change a shape
+/// beyond that, such as `transform`, `stroke` or a `viewBox`, is left out
+/// with a warning, once for each kind, and so are empty shapes. With
+/// `strict` those are errors instead.
+pub fn parse(text: &str, strict: bool) -> Result<SceneModel, String> {
+    let mut rects = Vec::new();
+    let mut ignored = BTreeMap::new();
+    let mut ignore = |what: String| *ignored.entry(what).or_insert(0) += 1;
+    let mut groups = vec![Paint {
+        fill: Some(ColorF::BLACK),
+        opacity: 1.0,
+    }];
+    let mut seen_svg = false;
+
+    for tag in tags(text)? {
+        let paint = *groups.last().unwrap();
+        if tag.closing {
+            if matches!(tag.name, "svg" | "g") && groups.len() > 1 {
+                groups.pop();
+            }
+            continue;
+        }
+        for attribute in [
+            "transform",
+            "stroke",
+            "viewBox",
+            "clip-path",
+            "mask",
+            "filter",
+        ] {
+            if tag.get(attribute).is_some() {
+                ignore(format!("`{}` on <{}>", attribute, tag.name));
+            }
+        }
+        let located = |err: String| format!("<{}>: {}", tag.name, err);
+        match tag.name {
+            "svg" | "g" => {
+                seen_svg |= tag.name == "svg";
+                if !tag.empty {
+                    groups.push(paint.within(&tag).map_err(located)?);
+                }
+            }
+            "rect" | "circle" => match shape(&tag, paint) {
+                Ok(Some(rect)) => rects.push(rect),
+                Ok(None) => (),
+                Err(err) => ignore(located(err)),
+            },
+            "title" | "desc" | "metadata" | "defs" => (),
+            name => ignore(format!("<{}>", name)),
+        }
+    }
+    if !seen_svg {
+        return Err("no <svg> element".to_string());
+    }
+
+    if strict && !ignored.is_empty() {
+        let ignored: Vec<String> = ignored.into_keys().collect();
+        return Err(format!("unsupported content: {}", ignored.join(", ")));
+    }
+    for (what, count) in ignored {
+        warn!("ignored {} ({} times)", what, count);
+    }
+    Ok(SceneModel {
+        rects,
+        layers: Vec::new(),
+    })
+}
+
+/// Reads and parses the SVG at `path`. The error is ready to print.
+pub fn load(path: &Path, strict: bool) -> Result<SceneModel, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    parse(&text, strict).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rects(svg: &str) -> Vec<SceneRect> {
+        parse(svg, true).unwrap().rects
+    }
+
+    fn bounds(x0: f32, y0: f32, x1: f32, y1: f32) -> LayoutRect {
+        LayoutRect::new(LayoutPoint::new(x0, y0), LayoutPoint::new(x1, y1))
+    }
+
+    #[test]
+    fn rects_keep_their_geometry_and_fill() {
+        let rects = rects(
+            r##"<svg xmlns="http://www.w3.org/2000/svg">
+                <rect x="10" y="20px" width="30" height="40" fill="#f00"/>
+                <rect width="50" height="20" rx="12" ry="30" fill="rgb(0, 50%, 255)"/>
+            </svg>"##,
+        );
+        assert_eq!(rects.len(), 2);
+        assert_eq!(rects[0].bounds, bounds(10.0, 20.0, 40.0, 60.0));
+        assert_eq!(rects[0].color, ColorF::new(1.0, 0.0, 0.0, 1.0));
+        assert_eq!(rects[0].corner_radii, [0.0; 4]);
+        assert_eq!(rects[1].bounds, bounds(0.0, 0.0, 50.0, 20.0));
+        assert_eq!(rects[1].color, ColorF::new(0.0, 0.5, 1.0, 1.0));
+        // The smaller radius, which `ry` is once clamped to half the height.
+        assert_eq!(rects[1].corner_radii, [10.0; 4]);
+    }
+
+    #[test]
+    fn circles_become_rounded_squares() {
+        let rects = rects(r#"<svg><circle cx="50" cy="40" r="10" fill="blue"/></svg>"#);
+        assert_eq!(rects.len(), 1);
+        assert_eq!(rects[0].bounds, bounds(40.0, 30.0, 60.0, 50.0));
+        assert_eq!(rects[0].corner_radii, [10.0; 4]);
+        assert_eq!(rects[0].color, ColorF::new(0.0, 0.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn fills_and_opacity_are_inherited_through_groups() {
+        let rects = rects(
+            r#"<svg fill="lime">
+                <g opacity="0.5">
+                    <rect width="10" height="10" fill-opacity="0.5"/>
+                    <rect width="10" height="10" style="fill: red; opacity: 0.5" fill="blue"/>
+                </g>
+                <rect width="10" height="10"/>
+                <rect width="10" height="10" fill="none"/>
+            </svg>"#,
+        );
+        let colors: Vec<ColorF> = rects.iter().map(|rect| rect.color).collect();
+        assert_eq!(
+            colors,
+            [
+                ColorF::new(0.0, 1.0, 0.0, 0.25),
+                ColorF::new(1.0, 0.0, 0.0, 0.25),
+                ColorF::new(0.0, 1.0, 0.0, 1.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn unsupported_elements_and_attributes_are_skipped() {
+        let svg = r#"<svg>
+            <!-- a comment -->
+            <path d="M0,0 L10,0 L5,10 Z"/>
+            <ellipse cx="5" cy="5" rx="5" ry="2"/>
+            <rect width="10" height="10" transform="rotate(45)"/>
+            <title>ignored</title>
+        </svg>"#;
+        assert_eq!(parse(svg, false).unwrap().rects.len(), 1);
+        assert_eq!(
+            parse(svg, true).unwrap_err(),
+            "unsupported content: <ellipse>, <path>, `transform` on <rect>"
+        );
+    }
+
+    #[test]
+    fn malformed_shapes_are_skipped() {
+        let svg = r#"<svg>
+            <rect x="ten" width="10" height="10"/>
+            <rect width="10" height="10" fill="bogus"/>
+            <rect width="10" height="0"/>
+            <circle r="-1"/>
+            <rect width="10" height="10"/>
+        </svg>"#;
+        assert_eq!(parse(svg, false).unwrap().rects.len(), 1);
+        let err = parse(svg, true).unwrap_err();
+        for problem in [
+            "<rect>: unsupported x `ten`",
+            "<rect>: unsupported color `bogus`",
+            "<rect>: is empty",
+            "<circle>: is empty",
+        ] {
+            assert!(err.contains(problem), "{} is missing from {}", problem, err);
+        }
+    }
+
+    #[test]
+    fn malformed_markup_is_rejected() {
+        for svg in [
+            r#"<svg><rect width=10 height="10"/></svg>"#,
+            r#"<svg><rect width="10 height="10"/></svg>"#,
+            r#"<svg><rect width="10" height="10" hidden/></svg>"#,
+            r#"<svg><rect width="10""#,
+            r#"<svg><!-- never closed </svg>"#,
+            r#"<svg><g fill="bogus"><rect width="10" height="10"/></g></svg>"#,
+            r#"<rect width="10" height="10"/>"#,
+        ] {
+            assert!(parse(svg, false).is_err(), "{}", svg);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use webrender::api::{
+    units::{LayoutPoint, LayoutRect, LayoutSize},
+    ColorF, CommonItemProperties, DisplayListBuilder, SpaceAndClipInfo,
+};
+
+/// Glyph cells across and down.
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+
+/// Bitmap glyphs, one row per byte with the leftmost cell in the highest of
+/// the three low bits. Letters are uppercase only; lowercase is drawn with
+/// the same glyphs.
+#[rustfmt::skip]
+const GLYPHS: &[(char, [u8; GLYPH_HEIGHT])] = &[
+    (' ', [0b000, 0b000, 0b000, 0b000, 0b000]),
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b111, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b001, 0b001, 0b001]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    ('A', [0b010, 0b101, 0b111, 0b101, 0b101]),
+    ('B', [0b110, 0b101, 0b110, 0b101, 0b110]),
+    ('C', [0b011, 0b100, 0b100, 0b100, 0b011]),
+    ('D', [0b110, 0b101, 0b101, 0b101, 0b110]),
+    ('E', [0b111, 0b100, 0b110, 0b100, 0b111]),
+    ('F', [0b111, 0b100, 0b110, 0b100, 0b100]),
+    ('G', [0b011, 0b100, 0b101, 0b101, 0b011]),
+    ('H', [0b101, 0b101, 0b111, 0b101, 0b101]),
+    ('I', [0b111, 0b010, 0b010, 0b010, 0b111]),
+    ('J', [0b001, 0b001, 0b001, 0b101, 0b010]),
+    ('K', [0b101, 0b101, 0b110, 0b101, 0b101]),
+    ('L', [0b100, 0b100, 0b100, 0b100, 0b111]),
+    ('M', [0b101, 0b111, 0b111, 0b101, 0b101]),
+    ('N', [0b110, 0b101, 0b101, 0b101, 0b101]),
+    ('O', [0b010, 0b101, 0b101, 0b101, 0b010]),
+    ('P', [0b110, 0b101, 0b110, 0b100, 0b100]),
+    ('Q', [0b010, 0b101, 0b101, 0b110, 0b011]),
+    ('R', [0b110, 0b101, 0b110, 0b101, 0b101]),
+    ('S', [0b011, 0b100, 0b010, 0b001, 0b110]),
+    ('T', [0b111, 0b010, 0b010, 0b010, 0b010]),
+    ('U', [0b101, 0b101, 0b101, 0b101, 0b111]),
+    ('V', [0b101, 0b101, 0b101, 0b101, 0b010]),
+    ('W', [0b101, 0b101, 0b111, 0b111, 0b101]),
+    ('X', [0b101, 0b101, 0b010, 0b101, 0b101]),
+    ('Y', [0b101, 0b101, 0b010, 0b010, 0b010]),
+    ('Z', [0b111, 0b001, 0b010, 0b100, 0b111]),
+    ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+    (',', [0b000, 0b000, 0b000, 0b010, 0b100]),
+    (':', [0b000, 0b010, 0b000, 0b010, 0b000]),
+    ('-', [0b000, 0b000, 0b111, 0b000, 0b000]),
+    ('+', [0b000, 0b010, 0b111, 0b010, 0b000]),
+    ('=', [0b000, 0b111, 0b000, 0b111, 0b000]),
+    ('/', [0b001, 0b001, 0b010, 0b100, 0b100]),
+    ('%', [0b101, 0b001, 0b010, 0b100, 0b101]),
+    ('(', [0b001, 0b010, 0b010, 0b010, 0b001]),
+    (')', [0b100, 0b010, 0b010, 0b010, 0b100]),
+    ('[', [0b011, 0b010, 0b010, 0b010, 0b011]),
+    (']', [0b110, 0b010, 0b010, 0b010, 0b110]),
+    ('<', [0b001, 0b010, 0b100, 0b010, 0b001]),
+    ('>', [0b100, 0b010, 0b001, 0b010, 0b100]),
+    ('#', [0b101, 0b111, 0b101, 0b111, 0b101]),
+    ('_', [0b000, 0b000, 0b000, 0b000, 0b111]),
+    ('!', [0b010, 0b010, 0b010, 0b000, 0b010]),
+    ('?', [0b111, 0b001, 0b010, 0b000, 0b010]),
+];
+
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    let c = c.to_ascii_uppercase();
+    GLYPHS
+        .iter()
+        .find(|&&(glyph, _)| glyph == c)
+        .or_else(|| GLYPHS.iter().find(|&&(glyph, _)| glyph == '?'))
+        .map(|&(_, rows)| rows)
+        .unwrap()
+}
+
+/// Size of `text` drawn by `push_text` with cells of `cell` layout pixels.
+pub fn text_size(text: &str, cell: f32) -> LayoutSize {
+    let count = text.chars().count();
+    let columns = (count * (GLYPH_WIDTH + 1)).saturating_sub(1);
+    LayoutSize::new(columns as f32 * cell, GLYPH_HEIGHT as f32 * cell)
+}
+
+/// Draws `text` in a small built-in bitmap font, one rect per horizontal run
+/// of lit cells, with its top left corner at `origin`.
+///
+/// This needs no font files, which keeps overlays and readouts working
+/// everywhere, at the cost of looking blocky.
+pub fn push_text(
+    builder: &mut DisplayListBuilder,
+    origin: LayoutPoint,
+    text: &str,
+    cell: f32,
+    color: ColorF,
+    space_and_clip: SpaceAndClipInfo,
+) {
+    for (i, c) in text.chars().enumerate() {
+        let left = origin.x + (i * (GLYPH_WIDTH + 1)) as f32 * cell;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            let mut column = 0;
+            while column < GLYPH_WIDTH {
+                let lit = |column: usize| bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0;
+                if !lit(column) {
+                    column += 1;
+                    continue;
+                }
+                let start = column;
+                while column < GLYPH_WIDTH && lit(column) {
+                    column += 1;
+                }
+
+                let bounds = LayoutRect::from_origin_and_size(
+                    LayoutPoint::new(left + start as f32 * cell, origin.y + row as f32 * cell),
+                    LayoutSize::new((column - start) as f32 * cell, cell),
+                );
+                builder.push_rect(
+                    &CommonItemProperties::new(bounds, space_and_clip),
+                    bounds,
+                    color,
+                );
+            }
+        }
+    }
+}
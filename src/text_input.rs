@@ -0,0 +1,274 @@
+use std::time::{Duration, Instant};
+
+use glutin::event::VirtualKeyCode;
+use serde::{Deserialize, Serialize};
+use webrender::api::{
+    units::{LayoutPoint, LayoutRect, LayoutSideOffsets, LayoutSize},
+    BorderDetails, BorderRadius, BorderSide, BorderStyle, ClipMode, ColorF, CommonItemProperties,
+    ComplexClipRegion, DisplayListBuilder, ItemTag, NormalBorder, SpaceAndClipInfo,
+};
+
+use crate::{
+    clip_debug::ClipLog,
+    hit_map::HitMap,
+    text::{push_text, text_size},
+};
+
+/// Hit-test tag of the field, with a kind the editor doesn't use.
+pub const TAG: ItemTag = (0, 5);
+
+/// Size of the font cells, in layout pixels.
+const TEXT_CELL: f32 = 2.0;
+
+/// Space between the border and the text.
+const PADDING: f32 = 6.0;
+
+const CORNER_RADIUS: f32 = 4.0;
+
+/// How long the caret stays on, then off.
+const BLINK_INTERVAL: Duration = Duration::from_millis(530);
+
+const FILL_COLOR: ColorF = ColorF {
+    r: 0.12,
+    g: 0.12,
+    b: 0.12,
+    a: 0.9,
+};
+
+const BORDER_COLOR: ColorF = ColorF {
+    r: 0.5,
+    g: 0.5,
+    b: 0.5,
+    a: 1.0,
+};
+
+const FOCUSED_BORDER_COLOR: ColorF = ColorF {
+    r: 0.3,
+    g: 0.6,
+    b: 1.0,
+    a: 1.0,
+};
+
+/// A change to the field's contents or caret.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum TextEdit {
+    Insert(char),
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+}
+
+impl TextEdit {
+    /// The edit made by a non-character key, if any.
+    pub fn from_key(key: VirtualKeyCode) -> Option<TextEdit> {
+        match key {
+            VirtualKeyCode::Back => Some(TextEdit::Backspace),
+            VirtualKeyCode::Delete => Some(TextEdit::Delete),
+            VirtualKeyCode::Left => Some(TextEdit::Left),
+            VirtualKeyCode::Right => Some(TextEdit::Right),
+            VirtualKeyCode::Home => Some(TextEdit::Home),
+            VirtualKeyCode::End => Some(TextEdit::End),
+            _ => None,
+        }
+    }
+}
+
+/// A single-line text field drawn entirely with display items: a rounded
+/// fill and border, the text in the bitmap font clipped to the inside, and
+/// a blinking caret while focused.
+pub struct TextInput {
+    bounds: LayoutRect,
+    value: String,
+    /// Caret position, in chars.
+    caret: usize,
+    focused: bool,
+    /// When the caret last came on, or `None` to restart the blink at the
+    /// next `update_blink`.
+    blink_started: Option<Instant>,
+    caret_on: bool,
+}
+
+impl TextInput {
+    /// A field `width` wide with its top left corner at `origin`, tall
+    /// enough for one line of text. `move_to` puts it somewhere else.
+    pub fn new(origin: LayoutPoint, width: f32) -> TextInput {
+        let height = text_size("", TEXT_CELL).height + PADDING * 2.0;
+        TextInput {
+            bounds: LayoutRect::from_origin_and_size(origin, LayoutSize::new(width, height)),
+            value: String::new(),
+            caret: 0,
+            focused: false,
+            blink_started: None,
+            caret_on: true,
+        }
+    }
+
+    pub fn size(&self) -> LayoutSize {
+        self.bounds.size()
+    }
+
+    pub fn move_to(&mut self, origin: LayoutPoint) {
+        self.bounds = LayoutRect::from_origin_and_size(origin, self.bounds.size());
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Focuses or blurs the field. Returns whether that changed anything.
+    pub fn set_focused(&mut self, focused: bool) -> bool {
+        if focused == self.focused {
+            return false;
+        }
+        self.focused = focused;
+        self.restart_blink();
+        true
+    }
+
+    fn restart_blink(&mut self) {
+        self.blink_started = None;
+        self.caret_on = true;
+    }
+
+    fn byte_offset(&self, chars: usize) -> usize {
+        self.value
+            .char_indices()
+            .nth(chars)
+            .map_or(self.value.len(), |(offset, _)| offset)
+    }
+
+    /// Applies `edit`. Returns whether the field needs redrawing.
+    pub fn edit(&mut self, edit: TextEdit) -> bool {
+        let length = self.value.chars().count();
+        match edit {
+            TextEdit::Insert(c) => {
+                let offset = self.byte_offset(self.caret);
+                self.value.insert(offset, c);
+                self.caret += 1;
+            }
+            TextEdit::Backspace if self.caret > 0 => {
+                self.caret -= 1;
+                let offset = self.byte_offset(self.caret);
+                self.value.remove(offset);
+            }
+            TextEdit::Delete if self.caret < length => {
+                let offset = self.byte_offset(self.caret);
+                self.value.remove(offset);
+            }
+            TextEdit::Left if self.caret > 0 => self.caret -= 1,
+            TextEdit::Right if self.caret < length => self.caret += 1,
+            TextEdit::Home => self.caret = 0,
+            TextEdit::End => self.caret = length,
+            _ => return false,
+        }
+        // Typing keeps the caret visible.
+        self.restart_blink();
+        true
+    }
+
+    /// Advances the caret blink to `now`. Returns whether the caret turned
+    /// on or off, and when it next will while focused.
+    pub fn update_blink(&mut self, now: Instant) -> (bool, Option<Instant>) {
+        if !self.focused {
+            return (false, None);
+        }
+        let started = *self.blink_started.get_or_insert(now);
+        let intervals =
+            (now.saturating_duration_since(started).as_nanos() / BLINK_INTERVAL.as_nanos()) as u32;
+        let caret_on = intervals & 1 == 0;
+        let changed = caret_on != self.caret_on;
+        self.caret_on = caret_on;
+        (changed, Some(started + BLINK_INTERVAL * (intervals + 1)))
+    }
+
+    pub fn push(
+        &self,
+        builder: &mut DisplayListBuilder,
+        hits: &mut HitMap,
+        clips: &mut ClipLog,
+        space_and_clip: SpaceAndClipInfo,
+    ) {
+        let rounded = clips.define_clip_rounded_rect(
+            builder,
+            space_and_clip.spatial_id,
+            ComplexClipRegion::new(
+                self.bounds,
+                BorderRadius::uniform(CORNER_RADIUS),
+                ClipMode::Clip,
+            ),
+        );
+        builder.push_rect(
+            &CommonItemProperties::new(
+                self.bounds,
+                SpaceAndClipInfo {
+                    spatial_id: space_and_clip.spatial_id,
+                    clip_id: rounded,
+                },
+            ),
+            self.bounds,
+            FILL_COLOR,
+        );
+
+        let side = BorderSide {
+            color: if self.focused {
+                FOCUSED_BORDER_COLOR
+            } else {
+                BORDER_COLOR
+            },
+            style: BorderStyle::Solid,
+        };
+        builder.push_border(
+            &CommonItemProperties::new(self.bounds, space_and_clip),
+            self.bounds,
+            LayoutSideOffsets::new_all_same(1.0),
+            BorderDetails::Normal(NormalBorder {
+                left: side,
+                right: side,
+                top: side,
+                bottom: side,
+                radius: BorderRadius::uniform(CORNER_RADIUS),
+                do_aa: true,
+            }),
+        );
+
+        // Scroll the text left once the caret would go past the right edge.
+        let inner = self.bounds.inflate(-PADDING, -PADDING);
+        let before_caret = &self.value[..self.byte_offset(self.caret)];
+        let caret_x = text_size(before_caret, TEXT_CELL).width + TEXT_CELL / 2.0;
+        let scroll = (caret_x + TEXT_CELL - inner.width()).max(0.0);
+        let text_clip = SpaceAndClipInfo {
+            spatial_id: space_and_clip.spatial_id,
+            clip_id: clips.define_clip_rect(builder, space_and_clip.spatial_id, inner),
+        };
+        let origin = LayoutPoint::new(inner.min.x - scroll, inner.min.y);
+        push_text(
+            builder,
+            origin,
+            &self.value,
+            TEXT_CELL,
+            ColorF::WHITE,
+            text_clip,
+        );
+
+        if self.focused && self.caret_on {
+            let caret = LayoutRect::from_origin_and_size(
+                LayoutPoint::new(origin.x + caret_x - TEXT_CELL / 2.0, inner.min.y),
+                LayoutSize::new(TEXT_CELL / 2.0, inner.height()),
+            );
+            builder.push_rect(
+                &CommonItemProperties::new(caret, text_clip),
+                caret,
+                ColorF::WHITE,
+            );
+        }
+
+        hits.push(builder, TAG, "text input", self.bounds, space_and_clip);
+    }
+}
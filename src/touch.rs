@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+
+use glutin::event::TouchPhase;
+use serde::{Deserialize, Serialize};
+use webrender::api::units::LayoutPoint;
+
+use crate::view::View;
+
+/// Where a finger is in its touch, as recorded by `--record-transactions`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Started,
+    Moved,
+    Ended,
+    Cancelled,
+}
+
+impl From<TouchPhase> for Phase {
+    fn from(phase: TouchPhase) -> Phase {
+        match phase {
+            TouchPhase::Started => Phase::Started,
+            TouchPhase::Moved => Phase::Moved,
+            TouchPhase::Ended => Phase::Ended,
+            TouchPhase::Cancelled => Phase::Cancelled,
+        }
+    }
+}
+
+/// Where the fingers are, as used for gestures: their midpoint, and how far
+/// apart the first two are when there are at least two.
+struct Contact {
+    center: LayoutPoint,
+    spread: Option<f32>,
+}
+
+/// Tracks fingers by id and turns their movement into pan and pinch-zoom.
+///
+/// One finger pans; two fingers pan with their midpoint and zoom by the
+/// change in their distance. Further fingers are ignored.
+#[derive(Default)]
+pub struct TouchTracker {
+    /// Ordered by id, so the same two fingers drive a pinch.
+    touches: BTreeMap<u64, LayoutPoint>,
+}
+
+impl TouchTracker {
+    fn contact(&self) -> Option<Contact> {
+        let mut points = self.touches.values().copied();
+        let first = points.next()?;
+        Some(match points.next() {
+            Some(second) => Contact {
+                center: first.lerp(second, 0.5),
+                spread: Some((second - first).length()),
+            },
+            None => Contact {
+                center: first,
+                spread: None,
+            },
+        })
+    }
+
+    /// Records a touch at `point`, in window layout pixels, and applies the
+    /// resulting gesture to `view`. Returns whether the view changed.
+    pub fn update(&mut self, id: u64, phase: Phase, point: LayoutPoint, view: &mut View) -> bool {
+        match phase {
+            Phase::Started => {
+                self.touches.insert(id, point);
+                false
+            }
+            Phase::Moved => {
+                let before = match (self.contact(), self.touches.get_mut(&id)) {
+                    (Some(before), Some(touch)) => {
+                        *touch = point;
+                        before
+                    }
+                    _ => return false,
+                };
+                let after = self.contact().unwrap();
+
+                if let (Some(from), Some(to)) = (before.spread, after.spread) {
+                    if from > 0.0 {
+                        view.zoom_about(before.center, view.zoom * to / from);
+                    }
+                }
+                view.pan += after.center - before.center;
+                true
+            }
+            Phase::Ended | Phase::Cancelled => {
+                self.touches.remove(&id);
+                false
+            }
+        }
+    }
+}
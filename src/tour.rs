@@ -0,0 +1,144 @@
+use std::{
+    f32::consts::{PI, TAU},
+    time::{Duration, Instant},
+};
+
+use log::info;
+use webrender::api::units::LayoutVector2D;
+
+use crate::{
+    app::{App, FRAME_INTERVAL},
+    scene::BuiltinScene,
+    view::View,
+};
+
+/// How long each step of the tour is shown.
+const STEP_DURATION: Duration = Duration::from_secs(2);
+
+/// Zoom reached halfway through a zoom step.
+const TOUR_ZOOM: f32 = 3.0;
+
+/// Radius, in layout pixels, of the circle traced by a pan step.
+const TOUR_PAN_RADIUS: f32 = 120.0;
+
+/// Scenes visited by the tour, in order.
+const TOUR_SCENES: [BuiltinScene; 3] = [
+    BuiltinScene::ThreeRects,
+    BuiltinScene::Grid(100),
+    BuiltinScene::Grid(2500),
+];
+
+#[derive(Clone, Copy, Debug)]
+enum Action {
+    Still,
+    Zoom,
+    Pan,
+    Spin,
+}
+
+const TOUR_ACTIONS: [Action; 4] = [Action::Still, Action::Zoom, Action::Pan, Action::Spin];
+
+impl Action {
+    /// The view at `progress` (0 to 1) through the step. Every action starts
+    /// and ends at the default view, so consecutive steps join up.
+    fn view(self, progress: f32, app: &App) -> View {
+        let mut view = View::default();
+        match self {
+            Action::Still | Action::Spin => (),
+            Action::Zoom => {
+                let center = (app.coords().layout_size() / 2.0).to_vector().to_point();
+                view.zoom_about(center, 1.0 + (TOUR_ZOOM - 1.0) * (progress * PI).sin());
+            }
+            Action::Pan => {
+                let theta = progress * TAU;
+                view.pan = LayoutVector2D::new(theta.cos() - 1.0, theta.sin()) * TOUR_PAN_RADIUS;
+            }
+        }
+        view
+    }
+}
+
+/// Scripted walk through every built-in scene, driven by `--tour`.
+///
+/// The view is computed from the time into each step rather than
+/// accumulated, so a run shows the same frames however the event loop is
+/// scheduled.
+pub struct Tour {
+    looping: bool,
+    step: usize,
+    step_started: Option<Instant>,
+    scene: Option<BuiltinScene>,
+    loops: usize,
+    scenes_shown: usize,
+}
+
+impl Tour {
+    pub fn new(looping: bool) -> Tour {
+        Tour {
+            looping,
+            step: 0,
+            step_started: None,
+            scene: None,
+            loops: 0,
+            scenes_shown: 0,
+        }
+    }
+
+    fn step_count() -> usize {
+        TOUR_SCENES.len() * TOUR_ACTIONS.len()
+    }
+
+    fn enter_step(&mut self, app: &mut App) {
+        let scene = TOUR_SCENES[self.step / TOUR_ACTIONS.len()];
+        let action = TOUR_ACTIONS[self.step % TOUR_ACTIONS.len()];
+
+        if self.scene != Some(scene) {
+            info!("tour: showing {}", scene.name());
+            app.set_scene(scene);
+            self.scene = Some(scene);
+            self.scenes_shown += 1;
+        }
+        app.set_spinner(matches!(action, Action::Spin));
+    }
+
+    /// Moves the tour along to `now` and returns when it next needs to run,
+    /// or `None` once it has finished.
+    pub fn update(&mut self, app: &mut App, now: Instant) -> Option<Instant> {
+        let mut started = match self.step_started {
+            Some(started) => started,
+            None => {
+                self.enter_step(app);
+                now
+            }
+        };
+
+        while now >= started + STEP_DURATION {
+            started += STEP_DURATION;
+            self.step += 1;
+            if self.step == Tour::step_count() {
+                self.step = 0;
+                self.loops += 1;
+                app.reset_view();
+                if !self.looping {
+                    self.step_started = None;
+                    return None;
+                }
+            }
+            self.enter_step(app);
+        }
+        self.step_started = Some(started);
+
+        let progress = (now - started).as_secs_f32() / STEP_DURATION.as_secs_f32();
+        let action = TOUR_ACTIONS[self.step % TOUR_ACTIONS.len()];
+        app.set_view(action.view(progress, app));
+
+        Some(now + FRAME_INTERVAL)
+    }
+
+    pub fn summary(&self, frames: u64) -> String {
+        format!(
+            "tour: {} scenes shown over {} full loops, {} frames rendered",
+            self.scenes_shown, self.loops, frames
+        )
+    }
+}
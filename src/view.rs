@@ -0,0 +1,94 @@
+use webrender::{
+    api::units::{LayoutPoint, LayoutRect, LayoutSize, LayoutTransform, LayoutVector2D},
+    euclid::vec3,
+};
+
+/// Smallest and largest zoom reachable through `View::zoom_about`.
+const ZOOM_RANGE: (f32, f32) = (0.1, 16.0);
+
+/// Pan and zoom applied to the whole scene through a root reference frame.
+///
+/// A scene point `p` is shown at `p * zoom + pan` in the window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct View {
+    pub pan: LayoutVector2D,
+    pub zoom: f32,
+}
+
+impl Default for View {
+    fn default() -> View {
+        View {
+            pan: LayoutVector2D::zero(),
+            zoom: 1.0,
+        }
+    }
+}
+
+impl View {
+    pub fn transform(self) -> LayoutTransform {
+        LayoutTransform::scale(self.zoom, self.zoom, 1.0)
+            .then_translate(vec3(self.pan.x, self.pan.y, 0.0))
+    }
+
+    /// Maps a window point in layout pixels back into scene coordinates.
+    pub fn to_scene(self, point: LayoutPoint) -> LayoutPoint {
+        (point - self.pan) / self.zoom
+    }
+
+    /// This view followed by `outer`, as one view.
+    pub fn then(self, outer: View) -> View {
+        View {
+            pan: self.pan * outer.zoom + outer.pan,
+            zoom: self.zoom * outer.zoom,
+        }
+    }
+
+    /// Maps a scene point to where it is shown in the window, the inverse
+    /// of `to_scene`.
+    pub fn to_layout(self, point: LayoutPoint) -> LayoutPoint {
+        point * self.zoom + self.pan
+    }
+
+    /// Maps a rect in scene coordinates to where it is shown in the window.
+    pub fn to_layout_rect(self, rect: LayoutRect) -> LayoutRect {
+        rect.scale(self.zoom, self.zoom).translate(self.pan)
+    }
+
+    /// Zooms to `zoom` while keeping the scene point under `anchor` fixed.
+    pub fn zoom_about(&mut self, anchor: LayoutPoint, zoom: f32) {
+        let scene = self.to_scene(anchor);
+        self.zoom = zoom.clamp(ZOOM_RANGE.0, ZOOM_RANGE.1);
+        self.pan = anchor.to_vector() - scene.to_vector() * self.zoom;
+    }
+
+    /// The view at the same zoom with `point`, in scene coordinates, at the
+    /// center of a window of `layout_size`.
+    pub fn centered_on(self, point: LayoutPoint, layout_size: LayoutSize) -> View {
+        let center = (layout_size / 2.0).to_vector();
+        View {
+            pan: center - point.to_vector() * self.zoom,
+            ..self
+        }
+    }
+
+    /// Limits the pan so the window stays over `content`, given in scene
+    /// coordinates, along each axis where the content is larger than the
+    /// window, and centers it along the others.
+    pub fn clamp_to(self, content: LayoutRect, layout_size: LayoutSize) -> View {
+        let clamp = |pan: f32, min: f32, max: f32, window: f32| {
+            let (min, max) = (min * self.zoom, max * self.zoom);
+            if max - min <= window {
+                (window - min - max) / 2.0
+            } else {
+                pan.clamp(window - max, -min)
+            }
+        };
+        View {
+            pan: LayoutVector2D::new(
+                clamp(self.pan.x, content.min.x, content.max.x, layout_size.width),
+                clamp(self.pan.y, content.min.y, content.max.y, layout_size.height),
+            ),
+            ..self
+        }
+    }
+}
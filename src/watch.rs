@@ -0,0 +1,58 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use glutin::event_loop::EventLoopProxy;
+use log::debug;
+
+use crate::{scene_file, UserEvent};
+
+/// How often the watched file's modification time is checked.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How long the file must stay unchanged before it is reloaded, so editors
+/// that write in several steps only trigger one reload.
+const DEBOUNCE: Duration = Duration::from_millis(150);
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// Watches `path` on a background thread and posts
+/// `UserEvent::SceneReloaded` with the reparsed scene whenever it changes.
+pub fn watch_scene(path: PathBuf, events_proxy: EventLoopProxy<UserEvent>) {
+    thread::spawn(move || {
+        let mut last = modified(&path);
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let mut current = modified(&path);
+            if current == last {
+                continue;
+            }
+
+            // Wait for writes to settle.
+            loop {
+                thread::sleep(DEBOUNCE);
+                let settled = modified(&path);
+                if settled == current {
+                    break;
+                }
+                current = settled;
+            }
+            last = current;
+
+            debug!("{} changed, reloading", path.display());
+            let result = scene_file::load(&path);
+            if events_proxy
+                .send_event(UserEvent::SceneReloaded(result))
+                .is_err()
+            {
+                // The event loop is gone.
+                return;
+            }
+        }
+    });
+}
@@ -0,0 +1,181 @@
+//! Renders every scene `--list-scenes` knows once and compares the frame
+//! with its reference image in `tests/golden/`, so a newly registered scene
+//! fails until it has one.
+//!
+//! The demo needs a window and a GL context, so the test only runs when
+//! asked for, with `cargo test --test golden -- --ignored`. With
+//! `UPDATE_GOLDEN=1` it saves the frames as the new references instead of
+//! comparing against them.
+
+use std::{
+    env,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Window size the references are rendered at, in physical pixels.
+const WINDOW_SIZE: &str = "640x480";
+
+/// Variants of registered scenes with references of their own, with the
+/// arguments that show them. `--optimize` mustn't change what is painted,
+/// so the merged grid is checked against the grid's look separately.
+const VARIANTS: [(&str, &[&str]); 1] =
+    [("grid-optimized", &["--scene-name", "grid", "--optimize"])];
+
+/// How far apart, out of 255, a channel of the frame and of its reference
+/// may be, to absorb differences in antialiasing between GPUs and drivers.
+const TOLERANCE: u8 = 4;
+
+/// Pixels listed when a frame doesn't match, of however many differ.
+const REPORTED_PIXELS: usize = 5;
+
+const DEMO: &str = env!("CARGO_BIN_EXE_webrender-demo");
+
+/// A decoded PNG as RGBA rows, top row first.
+struct Image {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+}
+
+fn read_png(path: &Path) -> Result<Image, String> {
+    let file = File::open(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder
+        .read_info()
+        .map_err(|err| format!("{}: {}", path.display(), err))?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buf)
+        .map_err(|err| format!("{}: {}", path.display(), err))?;
+    buf.truncate(info.buffer_size());
+    if info.color_type != png::ColorType::Rgba {
+        return Err(format!(
+            "{}: expected RGBA, found {:?}",
+            path.display(),
+            info.color_type
+        ));
+    }
+    Ok(Image {
+        width: info.width,
+        height: info.height,
+        rgba: buf,
+    })
+}
+
+/// The scenes to render, by the name of their reference, with the
+/// arguments that show them: each registered scene under its own name, then
+/// the `VARIANTS`.
+fn scenes() -> Result<Vec<(String, Vec<String>)>, String> {
+    let output = Command::new(DEMO)
+        .arg("--list-scenes")
+        .output()
+        .map_err(|err| format!("can't run the demo: {}", err))?;
+    if !output.status.success() {
+        return Err(format!("--list-scenes failed: {}", output.status));
+    }
+    let listing = String::from_utf8(output.stdout)
+        .map_err(|err| format!("--list-scenes printed non-UTF-8: {}", err))?;
+    let registered = listing
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .map(|name| {
+            let args = vec!["--scene-name".to_string(), name.to_string()];
+            (name.to_string(), args)
+        });
+    let variants = VARIANTS.iter().map(|(name, args)| {
+        let args = args.iter().map(|arg| arg.to_string()).collect();
+        (name.to_string(), args)
+    });
+    Ok(registered.chain(variants).collect())
+}
+
+/// Renders a scene shown by `args` at `WINDOW_SIZE` and a scale of 1, and
+/// saves the first full frame to `path`.
+fn render(args: &[String], path: &Path) -> Result<(), String> {
+    let status = Command::new(DEMO)
+        .args(args)
+        .args(["--window-size", WINDOW_SIZE, "--scale", "1", "--screenshot"])
+        .arg(path)
+        .status()
+        .map_err(|err| format!("can't run the demo: {}", err))?;
+    if !status.success() {
+        return Err(format!("rendering {:?} failed: {}", args, status));
+    }
+    Ok(())
+}
+
+/// Why `frame` doesn't match `reference`, if it doesn't.
+fn compare(frame: &Image, reference: &Image) -> Option<String> {
+    if (frame.width, frame.height) != (reference.width, reference.height) {
+        return Some(format!(
+            "the frame is {}x{}, the reference {}x{}",
+            frame.width, frame.height, reference.width, reference.height
+        ));
+    }
+    let differing: Vec<(usize, &[u8], &[u8])> = frame
+        .rgba
+        .chunks_exact(4)
+        .zip(reference.rgba.chunks_exact(4))
+        .enumerate()
+        .filter(|(_, (a, b))| a.iter().zip(*b).any(|(a, b)| a.abs_diff(*b) > TOLERANCE))
+        .map(|(i, (a, b))| (i, a, b))
+        .collect();
+    if differing.is_empty() {
+        return None;
+    }
+    let width = frame.width as usize;
+    let examples: Vec<String> = differing
+        .iter()
+        .take(REPORTED_PIXELS)
+        .map(|(i, a, b)| format!("({}, {}): {:?} vs {:?}", i % width, i / width, a, b))
+        .collect();
+    Some(format!(
+        "{} pixels differ by more than {}, including {}",
+        differing.len(),
+        TOLERANCE,
+        examples.join(", ")
+    ))
+}
+
+#[test]
+#[ignore = "needs a window and a GL context"]
+fn built_in_scenes_match_their_references() {
+    let update = env::var_os("UPDATE_GOLDEN").is_some_and(|value| value == "1");
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let frames = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+
+    let mut failures = Vec::new();
+    for (name, args) in scenes().unwrap() {
+        let frame_path = frames.join(format!("{}.png", name));
+        let reference_path = golden.join(format!("{}.png", name));
+        if let Err(err) = render(&args, &frame_path) {
+            failures.push(err);
+            continue;
+        }
+
+        if update {
+            fs::copy(&frame_path, &reference_path).unwrap();
+            println!("updated {}", reference_path.display());
+            continue;
+        }
+        let result = read_png(&frame_path).and_then(|frame| {
+            let reference = read_png(&reference_path)?;
+            Ok(compare(&frame, &reference))
+        });
+        match result {
+            Ok(None) => (),
+            Ok(Some(mismatch)) => failures.push(format!(
+                "`{}` doesn't match {}: {}; the frame is at {}",
+                name,
+                reference_path.display(),
+                mismatch,
+                frame_path.display()
+            )),
+            Err(err) => failures.push(err),
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}